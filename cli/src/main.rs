@@ -0,0 +1,387 @@
+mod localize;
+mod progress;
+mod session;
+
+use self::session::{Device, Session};
+use clap::{App, AppSettings, Arg, ArgMatches, ErrorKind, SubCommand};
+use firmware_manager::{
+    format_error, history, Config, PendingAction, RefreshDeferral, UpdateOutcome, UpdateStatus,
+    Urgency,
+};
+use i18n_embed::DesktopLanguageRequester;
+use std::process::exit;
+
+/// The command succeeded.
+const EXIT_SUCCESS: i32 = 0;
+
+/// An update failed, or no firmware service could be scanned.
+const EXIT_FAILURE: i32 = 1;
+
+/// The arguments were invalid.
+const EXIT_USAGE: i32 = 2;
+
+/// The device was not found, or it has no update available.
+const EXIT_NOT_FOUND: i32 = 3;
+
+/// Updates are available, as reported by `refresh`.
+const EXIT_UPDATES_AVAILABLE: i32 = 100;
+
+fn main() {
+    translate();
+
+    // Clap borrows its help messages, so the translations must outlive it.
+    let help = [
+        fl!("about"),
+        fl!("exit-codes"),
+        fl!("about-list"),
+        fl!("about-refresh"),
+        fl!("about-update"),
+        fl!("about-changelog"),
+        fl!("about-history"),
+        fl!("arg-device"),
+        fl!("arg-all"),
+    ];
+
+    let matches = App::new("firmware-manager-cli")
+        .about(&*help[0])
+        .after_help(&*help[1])
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("list").about(&*help[2]))
+        .subcommand(SubCommand::with_name("refresh").about(&*help[3]))
+        .subcommand(
+            SubCommand::with_name("update")
+                .about(&*help[4])
+                .arg(
+                    Arg::with_name("device")
+                        .help(&help[7])
+                        .required_unless("all")
+                        .conflicts_with("all"),
+                )
+                .arg(Arg::with_name("all").long("all").help(&help[8])),
+        )
+        .subcommand(
+            SubCommand::with_name("changelog")
+                .about(&*help[5])
+                .arg(Arg::with_name("device").help(&help[7]).required(true)),
+        )
+        .subcommand(SubCommand::with_name("history").about(&*help[6]))
+        .get_matches_safe()
+        .unwrap_or_else(|why| match why.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => why.exit(),
+            _ => {
+                eprintln!("{}", why.message);
+                exit(EXIT_USAGE);
+            }
+        });
+
+    let config = Config::load().unwrap_or_else(|why| {
+        eprintln!("{}: {}", fl!("error-config"), format_error(&why));
+        Config::default()
+    });
+
+    let result = match matches.subcommand() {
+        ("list", _) => list(config),
+        ("refresh", _) => refresh(config),
+        ("update", Some(matches)) => update(config, matches),
+        ("changelog", Some(matches)) => changelog(config, matches.value_of("device").unwrap()),
+        ("history", _) => show_history(),
+        _ => unreachable!("a subcommand is required"),
+    };
+
+    match result {
+        Ok(code) => exit(code),
+        Err((code, why)) => {
+            eprintln!("{}", why);
+            exit(code);
+        }
+    }
+}
+
+/// The exit code and message of a command which failed.
+type Failure = (i32, Box<str>);
+
+/// The exit code of a command which completed, or the reason that it failed.
+type Outcome = Result<i32, Failure>;
+
+/// Lists the devices and the state of their firmware.
+fn list(config: Config) -> Outcome {
+    let devices = scan(config, false)?;
+
+    print_devices(&devices);
+    Ok(EXIT_SUCCESS)
+}
+
+/// Refreshes the fwupd metadata regardless of when it was last refreshed, or whether the network
+/// connection is metered, and lists the devices.
+fn refresh(config: Config) -> Outcome {
+    let devices = scan(config, true)?;
+
+    print_devices(&devices);
+
+    if devices.iter().any(|device| device.upgradeable()) {
+        Ok(EXIT_UPDATES_AVAILABLE)
+    } else {
+        Ok(EXIT_SUCCESS)
+    }
+}
+
+/// Updates the device given on the command line, or every device with an update available.
+fn update(config: Config, matches: &ArgMatches) -> Outcome {
+    let mut session = Session::new(config);
+    let devices = scan_with(&mut session, false)?;
+
+    let selected: Vec<&Device> = match matches.value_of("device") {
+        Some(device) => {
+            let selected = find(&devices, device)?;
+            if !selected.iter().any(|device| device.upgradeable()) {
+                let why = fl!("error-no-update", device = device);
+                return Err((EXIT_NOT_FOUND, why.into()));
+            }
+
+            selected
+        }
+        None => devices.iter().filter(|device| device.upgradeable()).collect(),
+    };
+
+    if selected.is_empty() {
+        println!("{}", fl!("no-updates"));
+        return Ok(EXIT_SUCCESS);
+    }
+
+    let mut failed = false;
+    for device in selected.into_iter().filter(|device| device.upgradeable()) {
+        let name = device.info.name.as_ref();
+        let version = device.info.latest.as_deref().unwrap_or_default();
+
+        match session.update(device).map_err(|why| (EXIT_FAILURE, why))? {
+            UpdateOutcome::Updated => {
+                println!("{}", fl!("progress-updated", device = name, version = version));
+            }
+            UpdateOutcome::Scheduled => {
+                println!("{}", fl!("progress-scheduled", device = name, version = version));
+            }
+            UpdateOutcome::Failed(why) => {
+                eprintln!("{}: {}", fl!("error-update", device = name), why);
+                failed = true;
+            }
+        }
+    }
+
+    Ok(if failed { EXIT_FAILURE } else { EXIT_SUCCESS })
+}
+
+/// Shows the releases of the device given on the command line, from the newest to the oldest.
+fn changelog(config: Config, device: &str) -> Outcome {
+    let devices = scan(config, false)?;
+    let device = find(&devices, device)?[0];
+
+    let changelog = &device.info.changelog;
+    if changelog.is_empty() {
+        println!("{}", fl!("changelog-unavailable"));
+        return Ok(EXIT_SUCCESS);
+    }
+
+    for (id, entry) in changelog.entries.iter().enumerate() {
+        if id != 0 {
+            println!();
+        }
+
+        match entry.date {
+            Some(date) => println!("{} ({})", entry.version, format_date(date)),
+            None => println!("{}", entry.version),
+        }
+
+        if let Some(urgency) = urgency(entry.urgency) {
+            println!("{}", fl!("changelog-urgency", urgency = urgency));
+        }
+
+        if !entry.cves.is_empty() {
+            println!("{}", fl!("changelog-fixes", issues = entry.cves.join(", ")));
+        }
+
+        match entry.description {
+            Some(ref description) => {
+                println!("{}", html2runes::markdown::convert_string(description).trim_end())
+            }
+            None => println!("{}", fl!("changelog-unavailable")),
+        }
+    }
+
+    Ok(EXIT_SUCCESS)
+}
+
+/// Shows the firmware updates which have been attempted, from the oldest to the newest.
+fn show_history() -> Outcome {
+    let entries = history().map_err(|why| {
+        (EXIT_FAILURE, [&*fl!("error-history"), ": ", &format_error(&why)].concat().into())
+    })?;
+
+    if entries.is_empty() {
+        println!("{}", fl!("no-history"));
+    }
+
+    for entry in entries {
+        let outcome = match entry.outcome {
+            UpdateOutcome::Updated => fl!("history-updated"),
+            UpdateOutcome::Scheduled => fl!("history-scheduled"),
+            UpdateOutcome::Failed(ref reason) => fl!("history-failed", reason = reason.as_ref()),
+        };
+
+        let unattended = if entry.unattended { fl!("history-unattended") } else { String::new() };
+
+        println!(
+            "{}  {}: {} → {}  {} {}",
+            format_date(entry.time),
+            entry.name,
+            entry.from,
+            entry.to,
+            outcome,
+            unattended
+        );
+    }
+
+    Ok(EXIT_SUCCESS)
+}
+
+fn scan(config: Config, refresh: bool) -> Result<Vec<Device>, Failure> {
+    scan_with(&mut Session::new(config), refresh)
+}
+
+/// Scans for devices, failing if no firmware service could be scanned.
+///
+/// A warning is printed if the fwupd remote metadata was due to be refreshed, but was not.
+fn scan_with(session: &mut Session, refresh: bool) -> Result<Vec<Device>, Failure> {
+    let scan = session.scan(refresh).map_err(|why| (EXIT_FAILURE, why))?;
+
+    match scan.report.refresh_deferred {
+        Some(RefreshDeferral::Metered) => eprintln!("{}", fl!("warning-refresh-metered")),
+        Some(RefreshDeferral::LowBattery) => eprintln!("{}", fl!("warning-refresh-low-battery")),
+        None => (),
+    }
+
+    if !scan.report.any_scanned() {
+        return Err((EXIT_FAILURE, fl!("error-scan").into()));
+    }
+
+    Ok(scan.devices)
+}
+
+/// The devices identified by the ID or name given on the command line.
+///
+/// Thelio I/O boards share an ID, so more than one device may be found.
+fn find<'a>(devices: &'a [Device], device: &str) -> Result<Vec<&'a Device>, Failure> {
+    let found: Vec<&Device> = devices.iter().filter(|entry| entry.matches(device)).collect();
+
+    if found.is_empty() {
+        Err((EXIT_NOT_FOUND, fl!("error-not-found", device = device).into()))
+    } else {
+        Ok(found)
+    }
+}
+
+fn print_devices(devices: &[Device]) {
+    if devices.is_empty() {
+        println!("{}", fl!("no-devices"));
+        return;
+    }
+
+    let header = [
+        fl!("header-id"),
+        fl!("header-name"),
+        fl!("header-current"),
+        fl!("header-latest"),
+        fl!("header-status"),
+    ];
+
+    let rows: Vec<[String; 5]> = devices
+        .iter()
+        .map(|device| {
+            let info = &device.info;
+            [
+                info.id.to_string(),
+                info.name.to_string(),
+                info.current.to_string(),
+                info.latest.as_deref().unwrap_or_default().to_owned(),
+                status(device.status),
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 5];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, column) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let mut line = String::new();
+        for (width, column) in widths.iter().zip(row.iter()) {
+            line.push_str(column);
+            line.extend(std::iter::repeat(' ').take(width - column.chars().count() + 2));
+        }
+
+        println!("{}", line.trim_end());
+    }
+}
+
+fn status(status: UpdateStatus) -> String {
+    match status {
+        UpdateStatus::UpToDate => fl!("status-up-to-date"),
+        UpdateStatus::Available => fl!("status-available"),
+        UpdateStatus::Held => fl!("status-held"),
+        UpdateStatus::Scheduled => fl!("status-scheduled"),
+        UpdateStatus::Pending(PendingAction::Activate) => fl!("status-activation"),
+        UpdateStatus::Pending(PendingAction::Unlock) => fl!("status-locked"),
+    }
+}
+
+fn urgency(urgency: Urgency) -> Option<String> {
+    let urgency = match urgency {
+        Urgency::Unknown => return None,
+        Urgency::Low => fl!("urgency-low"),
+        Urgency::Medium => fl!("urgency-medium"),
+        Urgency::High => fl!("urgency-high"),
+        Urgency::Critical => fl!("urgency-critical"),
+    };
+
+    Some(urgency)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date, such as `2021-04-01`.
+fn format_date(time: u64) -> String {
+    // Converts days since the epoch to a civil date, per Howard Hinnant's `civil_from_days`.
+    let days = (time / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn translate() {
+    let localizer = crate::localize::localizer();
+    let requested_languages = DesktopLanguageRequester::requested_languages();
+
+    if let Err(error) = localizer.select(&requested_languages) {
+        eprintln!("Error while loading languages for firmware-manager-cli {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_formatted() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_617_235_200), "2021-04-01");
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+
+/// The number of characters between the brackets of the bar.
+const WIDTH: usize = 30;
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// A text progress bar for firmware downloads, which is redrawn in place on standard error.
+pub struct ProgressBar {
+    label: String,
+    downloaded: u64,
+    size: u64,
+    drawn: bool,
+}
+
+impl ProgressBar {
+    pub fn new(label: String) -> Self { Self { label, downloaded: 0, size: 0, drawn: false } }
+
+    /// A download of the given size has begun.
+    pub fn begin(&mut self, size: u64) {
+        self.downloaded = 0;
+        self.size = size;
+        self.draw();
+    }
+
+    /// The given number of bytes were downloaded since the last update.
+    pub fn advance(&mut self, bytes: u64) {
+        self.downloaded = self.downloaded.saturating_add(bytes).min(self.size);
+        self.draw();
+    }
+
+    /// Ends the line of the bar, if it was drawn.
+    pub fn finish(&mut self) {
+        if self.drawn {
+            self.drawn = false;
+            eprintln!();
+        }
+    }
+
+    fn draw(&mut self) {
+        let fraction = if self.size == 0 { 1.0 } else { self.downloaded as f64 / self.size as f64 };
+
+        let filled = (fraction * WIDTH as f64) as usize;
+
+        let mut stderr = io::stderr();
+        let _ = write!(
+            stderr,
+            "\r{} [{}{}] {:>3}% {:.1}/{:.1} MiB",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            (fraction * 100.0) as u8,
+            self.downloaded as f64 / MIB,
+            self.size as f64 / MIB,
+        );
+
+        let _ = stderr.flush();
+        self.drawn = true;
+    }
+}
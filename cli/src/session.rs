@@ -0,0 +1,139 @@
+use crate::{fl, progress::ProgressBar};
+use firmware_manager::{
+    event_loop_with_config, format_error, Config, Entities, Entity, FirmwareEvent, FirmwareSignal,
+    FoundDevice, ScanReport, UpdateOutcome,
+};
+use std::{
+    ops::Deref,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// A device which was found by a scan.
+pub struct Device {
+    pub entity: Entity,
+    pub found: FoundDevice,
+}
+
+impl Deref for Device {
+    type Target = FoundDevice;
+
+    fn deref(&self) -> &FoundDevice { &self.found }
+}
+
+impl Device {
+    /// Checks if the device is identified by the ID or name given on the command line.
+    pub fn matches(&self, device: &str) -> bool {
+        &*self.info.id == device || self.info.name.eq_ignore_ascii_case(device)
+    }
+}
+
+/// The devices found by a scan, along with the outcome of scanning each firmware service.
+pub struct Scan {
+    pub devices: Vec<Device>,
+    pub report: ScanReport,
+}
+
+/// The event loop of the core, running in a background thread.
+pub struct Session {
+    entities: Entities,
+    events: Sender<FirmwareEvent>,
+    signals: Receiver<FirmwareSignal>,
+    background: Option<JoinHandle<()>>,
+}
+
+impl Session {
+    pub fn new(config: Config) -> Self {
+        let (events, receiver) = mpsc::channel();
+        let (sender, signals) = mpsc::channel();
+
+        let background = thread::spawn(move || {
+            event_loop_with_config(&config, receiver, |signal| {
+                let _ = sender.send(signal);
+            })
+        });
+
+        Self { entities: Entities::default(), events, signals, background: Some(background) }
+    }
+
+    /// Scans the firmware services for devices, after refreshing the fwupd remote metadata if
+    /// `refresh` is set, even if it is not due.
+    ///
+    /// Errors which are not associated with a device are printed as they are received.
+    pub fn scan(&mut self, refresh: bool) -> Result<Scan, Box<str>> {
+        let event = if refresh { FirmwareEvent::Refresh } else { FirmwareEvent::Scan };
+        let _ = self.events.send(event);
+
+        let mut devices = Vec::new();
+        loop {
+            let found = match self.recv()? {
+                FirmwareSignal::Scanning => {
+                    self.entities.clear();
+                    devices.clear();
+                    continue;
+                }
+                FirmwareSignal::ScanningComplete(report) => return Ok(Scan { devices, report }),
+                FirmwareSignal::Error(_, why) => {
+                    eprintln!("{}", format_error(&why));
+                    continue;
+                }
+                signal => match FoundDevice::from_signal(signal) {
+                    Some(found) => found,
+                    None => continue,
+                },
+            };
+
+            let device = Device { entity: self.entities.create(), found };
+            devices.push(device);
+        }
+    }
+
+    /// Installs the update of a device, showing its progress.
+    pub fn update(&mut self, device: &Device) -> Result<UpdateOutcome, Box<str>> {
+        let event = Some(device)
+            .filter(|device| device.upgradeable())
+            .and_then(|device| device.event(device.entity))
+            .ok_or_else(|| Box::from(fl!("error-no-update", device = device.info.name.as_ref())))?;
+
+        let _ = self.events.send(event);
+
+        let entity = device.entity;
+        let mut progress = ProgressBar::new(fl!("progress-downloading"));
+
+        loop {
+            match self.recv()? {
+                FirmwareSignal::DownloadBegin(id, size) if id == entity => progress.begin(size),
+                FirmwareSignal::DownloadUpdate(id, bytes) if id == entity => {
+                    progress.advance(bytes as u64)
+                }
+                FirmwareSignal::DownloadComplete(id) if id == entity => progress.finish(),
+                FirmwareSignal::DeviceFlashing(id) if id == entity => {
+                    eprintln!("{}", fl!("progress-flashing", device = device.info.name.as_ref()));
+                }
+                FirmwareSignal::DeviceUpdated(id) if id == entity => {
+                    return Ok(UpdateOutcome::Updated)
+                }
+                FirmwareSignal::SystemScheduled => return Ok(UpdateOutcome::Scheduled),
+                FirmwareSignal::Error(Some(id), why) if id == entity => {
+                    progress.finish();
+                    return Ok(UpdateOutcome::Failed(format_error(&why).into()));
+                }
+                FirmwareSignal::Error(_, why) => eprintln!("{}", format_error(&why)),
+                _ => (),
+            }
+        }
+    }
+
+    fn recv(&self) -> Result<FirmwareSignal, Box<str>> {
+        self.signals.recv().map_err(|_| Box::from(fl!("error-stopped")))
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.events.send(FirmwareEvent::Stop);
+        if let Some(background) = self.background.take() {
+            let _ = background.join();
+        }
+    }
+}
@@ -8,4 +8,6 @@ GtkWidget *s76_firmware_widget_container (const S76FirmwareWidget *self);
 
 int s76_firmware_widget_scan (S76FirmwareWidget *self);
 
+int s76_firmware_widget_refresh (S76FirmwareWidget *self);
+
 void s76_firmware_widget_free (S76FirmwareWidget *self);
\ No newline at end of file
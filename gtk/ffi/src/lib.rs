@@ -41,6 +41,16 @@ pub extern "C" fn s76_firmware_widget_scan(ptr: *mut S76FirmwareWidget) -> i32 {
     })
 }
 
+#[no_mangle]
+pub extern "C" fn s76_firmware_widget_refresh(ptr: *mut S76FirmwareWidget) -> i32 {
+    let value = unsafe { (ptr as *mut FirmwareWidget).as_mut() };
+
+    value.map_or(-1, |widget| {
+        widget.refresh();
+        0
+    })
+}
+
 fn translate() {
     let localizer = firmware_manager_gtk::localizer();
     let requested_languages = DesktopLanguageRequester::requested_languages();
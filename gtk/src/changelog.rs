@@ -1,4 +1,5 @@
 use crate::fl;
+use firmware_manager::Changelog;
 use gtk::prelude::*;
 
 pub fn generate_widget_none() -> gtk::Box {
@@ -14,23 +15,17 @@ pub fn generate_widget_none() -> gtk::Box {
         .build()
 }
 
-pub fn generate_widget<I, S, X>(changelog: I) -> gtk::Box
-where
-    S: AsRef<str>,
-    X: AsRef<str>,
-    I: Iterator<Item = (S, X)>,
-{
+pub fn generate_widget(changelog: &Changelog) -> gtk::Box {
     let changelog_entries = cascade! {
         gtk::Box::new(gtk::Orientation::Vertical, 12);
         ..show_all();
     };
 
     let mut initiated = false;
-    changelog.for_each(|(version, entry)| {
-        let markdown = if entry.as_ref().is_empty() {
-            fl!("changelog-unavailable")
-        } else {
-            html2runes::markdown::convert_string(entry.as_ref())
+    for entry in &changelog.entries {
+        let markdown = match entry.description {
+            Some(ref description) => html2runes::markdown::convert_string(description),
+            None => fl!("changelog-unavailable"),
         };
 
         // NOTE: If we don't set a max width in chars, the label resizes its parent.
@@ -40,7 +35,7 @@ where
         const PADDING: i32 = 48;
 
         let version = gtk::LabelBuilder::new()
-            .label(&*format!("<b>{}</b>", version.as_ref()))
+            .label(&*format!("<b>{}</b>", entry.version))
             .use_markup(true)
             .xalign(0.0)
             .max_width_chars(40)
@@ -64,7 +59,7 @@ where
         initiated = true;
         changelog_entries.add(&version);
         changelog_entries.add(&changelog);
-    });
+    }
 
     changelog_entries
 }
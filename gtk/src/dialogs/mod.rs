@@ -1,25 +1,18 @@
-mod fwupd;
-mod system76;
-
-pub use self::{fwupd::FwupdDialog, system76::System76Dialog};
-
 use crate::fl;
+use firmware_manager::{Changelog, ReleaseTrust, TrustDetail, TrustWarning};
 use gtk::prelude::*;
 
 /// A generic GTK dialog which is displayed for firmware which requires a system reboot.
 ///
 /// This dialog displays a changelog covering the details of the updates, and all prior updates, as
 /// well as a confirmation button that will initiate configuring the system to be rebooted into the
-/// firmware upgrade environment.
+/// firmware upgrade environment. If the firmware service reports how far the release may be
+/// trusted, that is displayed above the changelog.
 #[derive(Shrinkwrap)]
 pub struct FirmwareUpdateDialog(gtk::Dialog);
 
 impl FirmwareUpdateDialog {
-    pub fn new<S: AsRef<str>, I: Iterator<Item = (S, S)>>(
-        version: &str,
-        changelog: I,
-        has_battery: bool,
-    ) -> Self {
+    pub fn new(version: &str, changelog: &Changelog, has_battery: bool) -> Self {
         let changelog_entries = crate::changelog::generate_widget(changelog);
 
         let mut header = fl!("update-available", version = version);
@@ -42,6 +35,14 @@ impl FirmwareUpdateDialog {
             gtk::Box::new(gtk::Orientation::Vertical, 12);
             ..set_vexpand(true);
             ..add(&gtk::LabelBuilder::new().label(&*header).wrap(true).xalign(0.0).use_markup(true).build());
+        };
+
+        if let Some(trust) = changelog.latest().and_then(|entry| entry.trust.as_ref()) {
+            changelog_container.add(&trust_details(trust));
+        }
+
+        cascade! {
+            &changelog_container;
             ..add(&gtk::LabelBuilder::new().label(&*changelog_text).use_markup(true).xalign(0.0).build());
             ..add(&changelog_entries);
             ..show_all();
@@ -128,3 +129,43 @@ impl FirmwareUpdateDialog {
         Self(dialog)
     }
 }
+
+/// Describes how far a release may be trusted, with a warning for releases which are unsigned or
+/// still being tested.
+fn trust_details(trust: &ReleaseTrust) -> gtk::Box {
+    let details: Vec<String> = trust
+        .details()
+        .into_iter()
+        .map(|detail| match detail {
+            TrustDetail::PayloadSigned => fl!("trust-payload-signed"),
+            TrustDetail::PayloadUnsigned => fl!("trust-payload-unsigned"),
+            TrustDetail::MetadataSigned => fl!("trust-metadata-signed"),
+            TrustDetail::MetadataUnsigned => fl!("trust-metadata-unsigned"),
+            TrustDetail::LvfsRemote => fl!("trust-remote-lvfs"),
+            TrustDetail::Remote(remote) => fl!("trust-remote", remote = remote),
+        })
+        .collect();
+
+    let container = cascade! {
+        gtk::Box::new(gtk::Orientation::Vertical, 12);
+        ..add(&gtk::LabelBuilder::new().label(&format!("<b>{}</b>", fl!("trust-header"))).use_markup(true).xalign(0.0).build());
+    };
+
+    let warning = trust.warning().map(|warning| match warning {
+        TrustWarning::Unsigned => fl!("trust-warning-unsigned"),
+        TrustWarning::Testing => fl!("trust-warning-testing"),
+    });
+
+    if let Some(warning) = warning {
+        let info_bar = gtk::InfoBarBuilder::new().message_type(gtk::MessageType::Warning).build();
+        info_bar
+            .content_area()
+            .add(&gtk::LabelBuilder::new().label(&warning).wrap(true).xalign(0.0).build());
+        container.add(&info_bar);
+    }
+
+    let details =
+        gtk::LabelBuilder::new().label(&details.join("\n")).wrap(true).xalign(0.0).build();
+    container.add(&details);
+    container
+}
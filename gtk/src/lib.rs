@@ -4,7 +4,8 @@
 //!
 //! This crate is a frontend for the firmware manager to be used with GTK-based desktop
 //! environments. Only GTK-specific application logic is contained here. All application logic is
-//! delegated to the core which this wraps.
+//! delegated to the firmware manager service, which hosts the core on the session bus, so that
+//! the widget shares its devices and updates with every other frontend in the session.
 //!
 //! See the [application crate] for an example of how this can be integrated into an application.
 //!
@@ -29,12 +30,14 @@ pub use self::localize::localizer;
 
 use self::{state::State, views::*};
 use firmware_manager::*;
+use firmware_manager_service::{Request, Signal};
 use gtk::{self, prelude::*};
 use slotmap::DefaultKey as Entity;
 use std::{
+    cell::Cell,
     collections::HashSet,
-    error::Error as _,
     process::Command,
+    rc::Rc,
     sync::{
         atomic::{AtomicBool, Ordering},
         mpsc::{channel, Receiver, Sender, TryRecvError},
@@ -56,22 +59,34 @@ pub(crate) enum ActivateEvent {
 /// The complete firmware manager, as a widget structure
 pub struct FirmwareWidget {
     container: gtk::Container,
-    sender: Sender<FirmwareEvent>,
+    sender: Sender<Request>,
     background: Option<JoinHandle<()>>,
-    is_admin: bool,
+    is_admin: Rc<Cell<bool>>,
 }
 
 /// An event which the GTK UI may propagate to the event loop in the main context.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 enum UiEvent {
+    /// A context menu was requested for an entity
+    ContextMenu(Entity),
+    /// The firmware inventory is to be exported to a file
+    ExportInventory,
     /// It was requested to hide the upgrade stack of an entity
     HideStack(Entity),
+    /// The holds of an entity are to be changed
+    Hold(Entity, HoldChange),
     /// An entity is scheduled to be revealed
     Reveal(Entity),
+    /// The user responded to a request to unlock the widget
+    Unlocked(Authorization),
     /// An entity has been revealed
     Revealed(Entity, bool),
+    /// The host security attributes are to be displayed
+    Security,
     /// The update button of an entity was triggered
     Update(Entity),
+    /// The firmware of an entity is to be verified, or recorded as trusted
+    Verify(Entity, bool),
 }
 
 /// An event that requests for the UI to perform a specific action.
@@ -81,7 +96,7 @@ enum UiEvent {
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 enum Event {
-    Firmware(FirmwareSignal),
+    Service(Signal),
     Ui(UiEvent),
     Stop,
 }
@@ -90,13 +105,17 @@ impl FirmwareWidget {
     /// Create a new firmware manager widget.
     ///
     /// # Notes
-    /// - This will spawn a background thread to handle non-UI events.
-    /// - On drop, the background thread will exit
+    /// - This will spawn a background thread which attaches to the firmware manager service.
+    /// - If the user may manage firmware, the devices which the service has already found are
+    ///   shown, and devices are only scanned for if the service has not scanned yet.
+    /// - On drop, the background thread will exit, but the service continues to run.
     pub fn new() -> Self {
         let (sender, rx) = channel();
 
         let view_devices = DevicesView::new();
         let view_empty = EmptyView::new();
+        let view_security = SecurityView::new();
+        let view_unavailable = UnavailableView::new();
 
         let info_bar_label = cascade! {
             gtk::Label::new(None);
@@ -114,11 +133,11 @@ impl FirmwareWidget {
             ..set_valign(gtk::Align::Start);
             ..connect_close(move |info_bar| {
                 info_bar.set_visible(false);
-                let _ = sender1.send(FirmwareEvent::Scan);
+                let _ = sender1.send(Request::Scan);
             });
             ..connect_response(move |info_bar, _| {
                 info_bar.set_visible(false);
-                let _ = sender2.send(FirmwareEvent::Scan);
+                let _ = sender2.send(Request::Scan);
             });
             ..set_no_show_all(true);
         };
@@ -126,34 +145,91 @@ impl FirmwareWidget {
         let area = info_bar.content_area();
         area.add(&info_bar_label);
 
+        let refresh_bar_label = cascade! {
+            gtk::Label::new(None);
+            ..set_line_wrap(true);
+            ..show();
+        };
+
+        // Offers to refresh the metadata when it was deferred, such as on a metered connection.
+        let refresh_bar = {
+            let sender = sender.clone();
+            cascade! {
+                gtk::InfoBar::new();
+                ..set_message_type(gtk::MessageType::Warning);
+                ..set_show_close_button(true);
+                ..add_button(&fl!("button-refresh-anyway"), gtk::ResponseType::Accept);
+                ..connect_close(|refresh_bar| refresh_bar.set_visible(false));
+                ..connect_response(move |refresh_bar, response| {
+                    refresh_bar.set_visible(false);
+                    if response == gtk::ResponseType::Accept {
+                        let _ = sender.send(Request::Refresh);
+                    }
+                });
+                ..set_no_show_all(true);
+            }
+        };
+
+        refresh_bar.content_area().add(&refresh_bar_label);
+
+        let bars = cascade! {
+            gtk::Box::new(gtk::Orientation::Vertical, 0);
+            ..set_valign(gtk::Align::Start);
+            ..add(&info_bar);
+            ..add(&refresh_bar);
+        };
+
         let stack = cascade! {
             gtk::Stack::new();
             ..add(view_empty.as_ref());
+            ..add(view_unavailable.as_ref());
             ..add(view_devices.as_ref());
+            ..add(view_security.as_ref());
             ..set_no_show_all(true);
         };
 
-        let is_admin = user_is_admin();
+        {
+            let stack = stack.clone();
+            let devices: gtk::Container = view_devices.as_ref().clone();
+            view_security.connect_back(move || stack.set_visible_child(&devices));
+        }
+
+        // The service loads the configuration itself, so it is only loaded to find the
+        // administrative groups, and to show its errors.
+        let config = Config::load();
+        let admin_config = config.as_ref().map_or_else(|_| Config::default(), Config::clone);
+
+        let authorization = user_authorization(&admin_config);
+        let is_admin = Rc::new(Cell::new(user_is_permitted(&admin_config, authorization)));
 
-        if is_admin {
+        let view_permission = if is_admin.get() {
             stack.set_visible_child(view_empty.as_ref());
+            None
         } else {
-            let view = PermissionView::new();
+            let view = PermissionView::new(authorization == Authorization::Challenge);
             stack.add(view.as_ref());
             stack.set_visible_child(view.as_ref());
             stack.show();
-        }
+            Some(view)
+        };
 
         let container = {
             let sender = sender.clone();
             let container = cascade! {
                 gtk::Overlay::new();
-                ..add_overlay(&info_bar);
+                ..add_overlay(&bars);
                 ..add(&stack);
                 ..set_can_default(true);
                 ..connect_key_press_event(move |_, event| {
                     gtk::Inhibit(if event.keyval() == gdk::keys::constants::F5 {
-                        let _ = sender.send(FirmwareEvent::Scan);
+                        // Shift+F5 also refreshes the metadata, even if it is not due.
+                        let request = if event.state().contains(gdk::ModifierType::SHIFT_MASK) {
+                            Request::Refresh
+                        } else {
+                            Request::Scan
+                        };
+
+                        let _ = sender.send(request);
                         true
                     } else {
                         false
@@ -166,13 +242,49 @@ impl FirmwareWidget {
         };
 
         info_bar.hide();
+        refresh_bar.hide();
 
         let (tx_progress, rx_progress) = channel();
         let (tx_events, rx_events) = glib::MainContext::channel::<Event>(glib::PRIORITY_DEFAULT);
 
-        // Spawns a background thread to handle all background events.
+        if let Err(why) = config {
+            let error_message = format_error(&why);
+            error!("{}", error_message);
+            info_bar.set_visible(true);
+            info_bar_label.set_text(error_message.as_str());
+        }
+
+        // Spawns a background thread to attach to the service.
         let background = Self::background(rx, tx_events.clone());
 
+        {
+            let tx_events = tx_events.clone();
+            view_devices.connect_security(move || {
+                let _ = tx_events.send(Event::Ui(UiEvent::Security));
+            });
+        }
+
+        {
+            let tx_events = tx_events.clone();
+            view_devices.connect_export(move || {
+                let _ = tx_events.send(Event::Ui(UiEvent::ExportInventory));
+            });
+        }
+
+        if let Some(ref view) = view_permission {
+            let tx_events = tx_events.clone();
+            view.connect_unlock(move || {
+                let tx_events = tx_events.clone();
+                let config = admin_config.clone();
+
+                // Authorization blocks until the user responds to the authentication agent.
+                thread::spawn(move || {
+                    let authorization = user_request_authorization(&config);
+                    let _ = tx_events.send(Event::Ui(UiEvent::Unlocked(authorization)));
+                });
+            });
+        }
+
         let state = State::new(
             sender.clone(),
             tx_events,
@@ -180,13 +292,24 @@ impl FirmwareWidget {
             stack.clone(),
             info_bar,
             info_bar_label,
+            refresh_bar,
+            refresh_bar_label,
             view_devices,
             view_empty,
+            view_permission,
+            view_security,
+            view_unavailable,
+            is_admin.clone(),
         );
 
         Self::attach_main_event_loop(state, rx_events);
         Self::connect_progress_events(rx_progress);
 
+        // Shows the devices which the service has already found, or scans for them.
+        if is_admin.get() {
+            let _ = sender.send(Request::Attach);
+        }
+
         Self {
             background: Some(background),
             container: container.upcast::<gtk::Container>(),
@@ -201,8 +324,18 @@ impl FirmwareWidget {
     /// devices view with new devices, if found. If devices are not found, the empty view
     /// will be displayed instead.
     pub fn scan(&self) {
-        if self.is_admin {
-            let _ = self.sender.send(FirmwareEvent::Scan);
+        if self.is_admin.get() {
+            let _ = self.sender.send(Request::Scan);
+        }
+    }
+
+    /// Sends a signal to the background thread to refresh the fwupd remote metadata, and then to
+    /// scan for available firmware.
+    ///
+    /// The metadata is refreshed even if it is not due, or the network connection is metered.
+    pub fn refresh(&self) {
+        if self.is_admin.get() {
+            let _ = self.sender.send(Request::Refresh);
         }
     }
 
@@ -213,11 +346,11 @@ impl FirmwareWidget {
 
     /// The main event loop for this widget.
     ///
-    /// Manages all `FirmwareSignal` events received on the receiver from the background thread.
+    /// Manages all `Signal` events received on the receiver from the background thread.
     /// The `State` input is captured by the receiver's move closure, and therefore retains its
     /// state between executions of the receiver's event loop.
     fn attach_main_event_loop(mut state: State, receiver: glib::Receiver<Event>) {
-        use crate::{Event::*, FirmwareSignal::*, UiEvent::*};
+        use crate::{Event::*, Signal::*, UiEvent::*};
         let mut last_active_revealer = None;
 
         // TODO: Use a better approach than an Arc<AtomicBool>
@@ -226,7 +359,7 @@ impl FirmwareWidget {
         let tx_udev = state.sender.clone();
         let usb_trigger = usb_hotplug_event_loop(move || {
             if !firmware_flashing_.load(Ordering::SeqCst) {
-                let _ = tx_udev.send(FirmwareEvent::Scan);
+                let _ = tx_udev.send(Request::Scan);
             }
         });
 
@@ -235,10 +368,24 @@ impl FirmwareWidget {
             let _ = usb_trigger;
 
             trace!("received UI event: {:#?}", Paint::yellow(&event));
+
+            // Devices which were scanned for other frontends are not shown until the user is
+            // permitted to manage firmware.
+            if let Service(_) = event {
+                if !state.is_admin.get() {
+                    return glib::Continue(true);
+                }
+            }
+
             match event {
                 // When a device begins flashing, we can begin moving the progress bar based on
                 // its duration.
-                Firmware(DeviceFlashing(entity)) => {
+                Service(DeviceFlashing(key)) => {
+                    let entity = match state.entity(key) {
+                        Some(entity) => entity,
+                        None => return glib::Continue(true),
+                    };
+
                     firmware_flashing.store(true, Ordering::SeqCst);
                     let widget = &state.components.device_widgets[entity];
                     let message = if state.entities.is_system(entity) {
@@ -251,60 +398,79 @@ impl FirmwareWidget {
                     state.progress_activate(&widget.stack.progress);
                 }
                 // An event that occurs when firmware has successfully updated.
-                Firmware(DeviceUpdated(entity)) => {
+                Service(DeviceUpdated(key)) => {
                     firmware_flashing.store(false, Ordering::SeqCst);
-                    let latest = state.components.latest.remove(entity);
-                    state.device_updated(entity, latest.expect("updated device without version"))
+                    if let Some(entity) = state.entity(key) {
+                        let latest = state.components.latest.remove(entity);
+                        let latest = latest.expect("updated device without version");
+                        state.device_updated(entity, latest)
+                    }
                 }
                 // Firmware for a device has begun downloading.
-                Firmware(DownloadBegin(entity, size)) => {
+                Service(DownloadBegin(key, size)) => {
+                    let entity = match state.entity(key) {
+                        Some(entity) => entity,
+                        None => return glib::Continue(true),
+                    };
+
                     let widget = &state.components.device_widgets[entity];
                     state.components.firmware_download.insert(entity, (0, size));
                     widget.stack.switch_to_progress(&fl!("action-downloading"));
                 }
                 // Firmware for a device has finished downloading.
-                Firmware(DownloadComplete(entity)) => {
-                    state.components.firmware_download.remove(entity);
-                    let widget = &state.components.device_widgets[entity];
-                    widget.stack.progress.set_fraction(1.0);
+                Service(DownloadComplete(key)) => {
+                    if let Some(entity) = state.entity(key) {
+                        state.components.firmware_download.remove(entity);
+                        let widget = &state.components.device_widgets[entity];
+                        widget.stack.progress.set_fraction(1.0);
+                    }
                 }
                 // Update the progress for the firmware being downloaded.
-                Firmware(DownloadUpdate(entity, downloaded)) => {
-                    let widget = &state.components.device_widgets[entity];
-                    let progress = &mut state.components.firmware_download[entity];
-                    progress.0 += downloaded as u64;
-                    widget.stack.progress.set_fraction(progress.0 as f64 / progress.1 as f64);
+                Service(DownloadUpdate(key, downloaded)) => {
+                    if let Some(entity) = state.entity(key) {
+                        let widget = &state.components.device_widgets[entity];
+                        let progress = &mut state.components.firmware_download[entity];
+                        progress.0 += downloaded;
+                        widget.stack.progress.set_fraction(progress.0 as f64 / progress.1 as f64);
+                    }
+                }
+                // A firmware service has returned, and the core will scan its devices again.
+                Service(BackendAvailable(backend)) => {
+                    info!("{} firmware service is available", backend.name());
+                }
+                // Devices of a firmware service that left the bus can no longer be managed.
+                Service(BackendLost(backend)) => {
+                    info!("{} firmware service was lost", backend.name());
+                    if !firmware_flashing.load(Ordering::SeqCst) {
+                        let _ = state.sender.send(Request::Scan);
+                    }
                 }
                 // An error occurred in the background thread, which we shall display in the UI.
-                Firmware(Error(entity, why)) => {
+                // The service sends the error with its causes already formatted.
+                Service(Error(key, why)) => {
                     firmware_flashing.store(false, Ordering::SeqCst);
-                    // Convert the error and its causes into a string.
-                    let mut error_message = format!("{}", why);
-                    let mut cause = why.source();
-                    while let Some(error) = cause {
-                        error_message.push_str(format!(": {}", error).as_str());
-                        cause = error.source();
-                    }
-
-                    error!("firmware widget error: {}", error_message);
+                    error!("firmware widget error: {}", why);
 
                     state.widgets.info_bar.set_visible(true);
-                    state.widgets.info_bar_label.set_text(error_message.as_str());
+                    state.widgets.info_bar_label.set_text(&why);
 
-                    if let Some(entity) = entity {
+                    if let Some(entity) = key.and_then(|key| state.entity(key)) {
                         let widget = &state.components.device_widgets[entity];
                         widget.stack.set_visible_child(&widget.stack.button);
                         state.components.firmware_download.remove(entity);
+                        state.components.requested.remove(entity);
                         state.progress_deactivate(&widget.stack.progress);
                     }
                 }
-                // An event that occurs when fwupd firmware is found.
-                Firmware(Fwupd(signal)) => state.fwupd(signal),
+                // An event that occurs when a device is found.
+                Service(DeviceFound(device)) => state.device_found(*device),
                 // Begins searching for devices that have firmware upgrade support
-                Firmware(Scanning) => {
+                Service(Scanning) => {
                     state.widgets.view_devices.clear();
                     last_active_revealer = None;
                     state.entities.clear();
+                    state.keys.clear();
+                    state.components.requested.clear();
 
                     let _ = state.progress_sender.send(ActivateEvent::Clear);
 
@@ -313,20 +479,39 @@ impl FirmwareWidget {
                     state.widgets.view_devices.hide_devices();
                 }
                 // Signal is received when scanning has completed.
-                Firmware(ScanningComplete) => {
-                    info!("scanning for firmware is complete");
+                Service(ScanningComplete(report)) => {
+                    info!("scanning for firmware is complete: {:?}", report);
+                    state.refresh_deferred(report.refresh_deferred);
                     if state.entities.entities.is_empty() {
+                        // Distinguish a system without firmware from one without services.
+                        let view: &gtk::Container = if report.any_scanned() {
+                            state.widgets.view_empty.as_ref()
+                        } else {
+                            state.widgets.view_unavailable.set_report(&report);
+                            state.widgets.view_unavailable.as_ref()
+                        };
+
                         state.widgets.stack.show();
-                        state.widgets.view_empty.show_all();
-                        state.widgets.stack.set_visible_child(state.widgets.view_empty.as_ref());
+                        view.show_all();
+                        state.widgets.stack.set_visible_child(view);
+                    }
+                }
+                // Displays the outcome of verifying a device's firmware.
+                Service(Verification(key, verification)) => {
+                    let widgets = &state.components.device_widgets;
+                    if let Some(widget) = state.entity(key).and_then(|e| widgets.get(e)) {
+                        widget.set_verification(&verification);
+                    }
+                }
+                // Displays the host security attributes, if they were requested by this widget.
+                Service(Signal::Security(report)) => state.security(report),
+                // When system firmwmare that this widget requested is successfully scheduled,
+                // reboot the system.
+                Service(SystemScheduled) => {
+                    if state.system_update_requested() {
+                        reboot();
                     }
                 }
-                // When system firmwmare is successfully scheduled, reboot the system.
-                Firmware(SystemScheduled) => reboot(),
-                // An event that occurs when System76 system firmware has been found.
-                Firmware(S76System(info, data)) => state.system76_system(info, data),
-                // An event that occurs when a Thelio I/O board was discovered.
-                Firmware(ThelioIo(info, digest)) => state.thelio_io(info, digest),
                 // Schedules the given firmware for an update, and show a dialog if it requires a
                 // reboot.
                 Ui(Update(entity)) => state.update(entity),
@@ -336,9 +521,21 @@ impl FirmwareWidget {
                         widget.stack.hide();
                     }
                 }
+                // Displays a menu for changing the holds of the entity.
+                Ui(ContextMenu(entity)) => state.context_menu(entity),
+                // Writes the firmware of every device to a file chosen by the user.
+                Ui(ExportInventory) => state.export_inventory(),
+                // Changes the holds of the entity, and scans for devices again.
+                Ui(Hold(entity, change)) => state.hold(entity, change),
+                // Requests the host security attributes from the service.
+                Ui(UiEvent::Security) => state.request_security(),
+                // Verifies the firmware of the entity, or records it as trusted.
+                Ui(Verify(entity, trust)) => state.verify(entity, trust),
                 // Reveals a widget's changelog in a revealer, and generate that changelog if it has
                 // not been revealed yet.
                 Ui(Reveal(entity)) => state.reveal(entity),
+                // Displays the devices if the user was authorized, or lets them try again.
+                Ui(Unlocked(authorization)) => state.unlocked(authorization),
                 // Signals that an entity's revealer has been revealed, and so we should hide the
                 // last-active revealer.
                 Ui(Revealed(entity, revealed)) => {
@@ -364,16 +561,18 @@ impl FirmwareWidget {
         });
     }
 
-    /// Manages all firmware client interactions from a background thread.
-    fn background(
-        receiver: Receiver<FirmwareEvent>,
-        sender: glib::Sender<Event>,
-    ) -> JoinHandle<()> {
+    /// Attaches to the firmware manager service from a background thread.
+    fn background(receiver: Receiver<Request>, sender: glib::Sender<Event>) -> JoinHandle<()> {
         thread::spawn(move || {
-            firmware_manager::event_loop(receiver, |event| {
-                let _ = sender.send(Event::Firmware(event));
+            let result = firmware_manager_service::event_loop(receiver, |signal| {
+                let _ = sender.send(Event::Service(signal));
             });
 
+            if let Err(why) = result {
+                let why = format_error(&why);
+                let _ = sender.send(Event::Service(Signal::Error(None, why.into())));
+            }
+
             info!("firmware manager event loop stopped");
             let _ = sender.send(Event::Stop);
         })
@@ -434,7 +633,7 @@ impl Default for FirmwareWidget {
 impl Drop for FirmwareWidget {
     fn drop(&mut self) {
         trace!("firmware widget dropped: sending stop signal to background thread");
-        let _ = self.sender.send(FirmwareEvent::Stop);
+        let _ = self.sender.send(Request::Stop);
 
         if let Some(handle) = self.background.take() {
             let _ = handle.join();
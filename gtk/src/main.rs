@@ -29,7 +29,6 @@ fn main() {
 
     application.connect_startup(|app| {
         let widget = Rc::new(FirmwareWidget::new());
-        widget.scan();
 
         let weak_widget = Rc::downgrade(&widget);
         let headerbar = cascade! {
@@ -1,9 +1,10 @@
 use crate::{dialogs::*, fl, views::*, widgets::*, ActivateEvent, Event, UiEvent};
 use firmware_manager::*;
+use firmware_manager_service::{Device, Pending, Request};
 
 use gtk::prelude::*;
 use slotmap::{DefaultKey as Entity, SecondaryMap, SparseSecondaryMap};
-use std::sync::mpsc::Sender;
+use std::{cell::Cell, collections::HashMap, rc::Rc, sync::mpsc::Sender};
 
 /// Manages all state and state interactions with the UI.
 pub(crate) struct State {
@@ -13,10 +14,16 @@ pub(crate) struct State {
     pub(crate) entities: Entities,
     /// If this system has a battery.
     pub(crate) has_battery: bool,
+    /// If the user is permitted to manage firmware, which is shared with the firmware widget.
+    pub(crate) is_admin: Rc<Cell<bool>>,
+    /// The entity of each device, by the key which the service identifies it with.
+    pub(crate) keys: HashMap<u64, Entity>,
     /// Sends events to the progress signal
     pub(crate) progress_sender: Sender<ActivateEvent>,
-    /// A sender to send firmware requests to the background thread
-    pub(crate) sender: Sender<FirmwareEvent>,
+    /// The host security attributes were requested by this widget, rather than another frontend.
+    pub(crate) security_requested: bool,
+    /// A sender to send requests to the service through the background thread
+    pub(crate) sender: Sender<Request>,
     /// Events to be processed by the main event loop
     pub(crate) ui_sender: glib::Sender<Event>,
     /// Widgets that will be actively managed.
@@ -29,34 +36,59 @@ pub(crate) struct Widgets {
     pub(crate) info_bar: gtk::InfoBar,
     /// Error messages will be set in this label.
     pub(crate) info_bar_label: gtk::Label,
+    /// Offers to refresh the fwupd remote metadata when a scan deferred refreshing it.
+    pub(crate) refresh_bar: gtk::InfoBar,
+    /// The reason that the metadata was not refreshed will be set in this label.
+    pub(crate) refresh_bar_label: gtk::Label,
     /// Controls which view to display in the UI
     pub(crate) stack: gtk::Stack,
     /// The devices view shows a list of all supported devices.
     pub(crate) view_devices: DevicesView,
     /// The empty view is displayed when a scan found no devices.
     pub(crate) view_empty: EmptyView,
+    /// The security view lists the host security attributes.
+    pub(crate) view_security: SecurityView,
+    /// The permission view is displayed when the user is not permitted to manage firmware.
+    pub(crate) view_permission: Option<PermissionView>,
+    /// The unavailable view is displayed when no firmware service could be scanned.
+    pub(crate) view_unavailable: UnavailableView,
 }
 
 /// Components are optional pieces of data that are assigned to entities
 #[derive(Default)]
 pub(crate) struct Components {
+    /// The changelog of a device, which is shown when the device is revealed.
+    pub(crate) changelog: SecondaryMap<Entity, Changelog>,
+
     /// The GTK widgets associated with a device are stored here.
     pub(crate) device_widgets: SecondaryMap<Entity, DeviceWidget>,
 
     /// Tracks progress of a firmware download.
     pub(crate) firmware_download: SecondaryMap<Entity, (u64, u64)>,
 
+    /// The identifier that the holds of a device are recorded against.
+    pub(crate) hold_ids: SecondaryMap<Entity, Box<str>>,
+
+    /// The currently-installed version of a device.
+    pub(crate) current: SecondaryMap<Entity, Box<str>>,
+
+    /// The key which the service identifies a device with.
+    pub(crate) keys: SecondaryMap<Entity, u64>,
+
+    /// The device and the state of its firmware, as it is written to an exported inventory.
+    pub(crate) inventory: SecondaryMap<Entity, InventoryEntry>,
+
     /// The latest version associated with a device, if one exists.
     pub(crate) latest: SecondaryMap<Entity, Box<str>>,
 
-    /// Details about a fwupd device
-    pub(crate) fwupd: SparseSecondaryMap<Entity, (FwupdDevice, Vec<FwupdRelease>)>,
+    /// What a device is waiting on, which is performed in place of updating it.
+    pub(crate) pending: SparseSecondaryMap<Entity, Pending>,
 
-    /// Details about system76 system firmware.
-    pub(crate) system76: SparseSecondaryMap<Entity, (System76Digest, System76Changelog)>,
+    /// Devices which this widget requested to be updated, rather than another frontend.
+    pub(crate) requested: SparseSecondaryMap<Entity, ()>,
 
-    /// Details about thelio I/O firmware
-    pub(crate) thelio: SparseSecondaryMap<Entity, System76Digest>,
+    /// Devices managed by fwupd, whose firmware may be verified.
+    pub(crate) verifiable: SparseSecondaryMap<Entity, ()>,
 }
 
 impl State {
@@ -64,25 +96,44 @@ impl State {
     /// context.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        sender: Sender<FirmwareEvent>,
+        sender: Sender<Request>,
         ui_sender: glib::Sender<Event>,
         progress_sender: Sender<ActivateEvent>,
         stack: gtk::Stack,
         info_bar: gtk::InfoBar,
         info_bar_label: gtk::Label,
+        refresh_bar: gtk::InfoBar,
+        refresh_bar_label: gtk::Label,
         view_devices: DevicesView,
         view_empty: EmptyView,
+        view_permission: Option<PermissionView>,
+        view_security: SecurityView,
+        view_unavailable: UnavailableView,
+        is_admin: Rc<Cell<bool>>,
     ) -> Self {
-        let has_battery =
-            upower_dbus::UPower::new(-1).and_then(|upower| upower.on_battery()).unwrap_or(false);
+        let has_battery = system_on_battery();
 
         Self {
             entities: Entities::default(),
             components: Components::default(),
             has_battery,
+            is_admin,
+            keys: HashMap::new(),
             progress_sender,
+            security_requested: false,
             sender,
-            widgets: Widgets { info_bar, info_bar_label, stack, view_devices, view_empty },
+            widgets: Widgets {
+                info_bar,
+                info_bar_label,
+                refresh_bar,
+                refresh_bar_label,
+                stack,
+                view_devices,
+                view_empty,
+                view_permission,
+                view_security,
+                view_unavailable,
+            },
             ui_sender,
         }
     }
@@ -96,14 +147,100 @@ impl State {
         self.widgets.stack.set_visible_child(self.widgets.view_devices.as_ref());
     }
 
+    /// Records the identity of a device, and connects the actions of its widget.
+    fn connect_device(&mut self, entity: Entity, info: &FirmwareInfo, widget: &DeviceWidget) {
+        self.components.hold_ids.insert(entity, info.id.clone());
+        self.components.current.insert(entity, info.current.clone());
+        self.components.changelog.insert(entity, info.changelog.clone());
+
+        let sender = self.ui_sender.clone();
+        widget.connect_clicked(move |_| {
+            let _ = sender.send(Event::Ui(UiEvent::Reveal(entity)));
+        });
+
+        let sender = self.ui_sender.clone();
+        widget.connect_context_menu(move || {
+            let _ = sender.send(Event::Ui(UiEvent::ContextMenu(entity)));
+        });
+    }
+
+    /// Displays a menu for adding or removing the holds of a device, and for verifying the
+    /// firmware of fwupd devices.
+    pub fn context_menu(&self, entity: Entity) {
+        let (id, widget) = match (
+            self.components.hold_ids.get(entity),
+            self.components.device_widgets.get(entity),
+        ) {
+            (Some(id), Some(widget)) => (id, widget),
+            _ => return,
+        };
+
+        let holds = Holds::load_or_default();
+        let hold = holds.get(id);
+
+        let menu = gtk::Menu::new();
+        let append = |label: String, event: UiEvent| {
+            let sender = self.ui_sender.clone();
+            let item = gtk::MenuItem::with_label(&label);
+            item.connect_activate(move |_| {
+                let _ = sender.send(Event::Ui(event.clone()));
+            });
+
+            menu.append(&item);
+        };
+
+        if hold.map_or(false, |hold| hold.held) {
+            append(fl!("hold-release"), UiEvent::Hold(entity, HoldChange::Release));
+        } else {
+            append(fl!("hold-current"), UiEvent::Hold(entity, HoldChange::Hold));
+        }
+
+        let current = self.components.current.get(entity);
+        if let Some(latest) = self.components.latest.get(entity) {
+            if Some(latest) != current {
+                let version = latest.as_ref();
+                if hold.map_or(false, |hold| hold.ignored.contains(latest)) {
+                    let label = fl!("hold-unignore", version = version);
+                    append(label, UiEvent::Hold(entity, HoldChange::Unignore(latest.clone())));
+                } else {
+                    let label = fl!("hold-ignore", version = version);
+                    append(label, UiEvent::Hold(entity, HoldChange::Ignore(latest.clone())));
+                }
+            }
+        }
+
+        if self.components.verifiable.contains_key(entity) {
+            menu.append(&gtk::SeparatorMenuItem::new());
+            append(fl!("device-verify"), UiEvent::Verify(entity, false));
+            append(fl!("device-verify-update"), UiEvent::Verify(entity, true));
+        }
+
+        menu.set_attach_widget(Some(&widget.event_box));
+        menu.show_all();
+        menu.popup_at_widget(
+            &widget.event_box,
+            gdk::Gravity::SouthWest,
+            gdk::Gravity::NorthWest,
+            None,
+        );
+    }
+
     /// An event that occurs when firmware has successfully updated.
     pub fn device_updated(&mut self, entity: Entity, latest: Box<str>) {
+        if let Some(entry) = self.components.inventory.get_mut(entity) {
+            entry.current = latest.clone();
+            entry.update_available = false;
+        }
+
+        self.components.current.insert(entity, latest.clone());
+
         if let Some(widget) = self.components.device_widgets.get(entity) {
             widget.stack.progress.set_fraction(1.0);
             widget.label.set_text(latest.as_ref());
 
             self.progress_deactivate(&widget.stack.progress);
-            if self.entities.is_system(entity) {
+            let requested = self.components.requested.remove(entity).is_some();
+            if requested && self.entities.is_system(entity) {
                 crate::reboot();
             }
 
@@ -117,11 +254,101 @@ impl State {
         }
     }
 
-    /// An event that occurs when fwupd firmware is found.
-    pub fn fwupd(&mut self, signal: FwupdSignal) {
+    /// Asks where the firmware inventory should be saved, and writes every device to it.
+    ///
+    /// Files named with a `.csv` extension, or saved with the CSV filter, are written as CSV.
+    pub fn export_inventory(&self) {
+        let parent = self.widgets.stack.toplevel().and_then(|w| w.downcast::<gtk::Window>().ok());
+
+        let json = cascade! {
+            gtk::FileFilter::new();
+            ..set_name(Some(&*fl!("export-filter-json")));
+            ..add_pattern("*.json");
+        };
+
+        let csv = cascade! {
+            gtk::FileFilter::new();
+            ..set_name(Some(&*fl!("export-filter-csv")));
+            ..add_pattern("*.csv");
+        };
+
+        let chooser = cascade! {
+            gtk::FileChooserNative::new(
+                Some(&*fl!("export-title")),
+                parent.as_ref(),
+                gtk::FileChooserAction::Save,
+                Some(&*fl!("button-export")),
+                Some(&*fl!("button-cancel")),
+            );
+            ..set_do_overwrite_confirmation(true);
+            ..set_current_name("firmware-inventory.json");
+            ..add_filter(&json);
+            ..add_filter(&csv);
+        };
+
+        if chooser.run() != gtk::ResponseType::Accept {
+            return;
+        }
+
+        let path = match chooser.filename() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let format = InventoryFormat::from_path(&path).unwrap_or_else(|| {
+            if chooser.filter().as_ref() == Some(&csv) {
+                InventoryFormat::Csv
+            } else {
+                InventoryFormat::Json
+            }
+        });
+
+        let mut inventory = Inventory::new();
+        for (entity, entry) in self.components.inventory.iter() {
+            if self.entities.contains_key(entity) {
+                inventory.push(entry.clone());
+            }
+        }
+
+        if let Err(why) = inventory.export(&path, format) {
+            let error_message = [&*fl!("export-failed"), ": ", &why.to_string()].concat();
+            error!("{}", error_message);
+            self.widgets.info_bar.set_visible(true);
+            self.widgets.info_bar_label.set_text(error_message.as_str());
+        }
+    }
+
+    /// Shows why the fwupd remote metadata was not refreshed by the last scan, if it was deferred.
+    pub fn refresh_deferred(&self, deferral: Option<RefreshDeferral>) {
+        let message = match deferral {
+            Some(RefreshDeferral::Metered) => fl!("refresh-deferred-metered"),
+            Some(RefreshDeferral::LowBattery) => fl!("refresh-deferred-low-battery"),
+            None => {
+                self.widgets.refresh_bar.set_visible(false);
+                return;
+            }
+        };
+
+        self.widgets.refresh_bar_label.set_text(&message);
+        self.widgets.refresh_bar.set_visible(true);
+    }
+
+    /// An event that occurs when a device is found by the service.
+    ///
+    /// Devices which the service already returned when this widget attached are ignored.
+    pub fn device_found(&mut self, device: Device) {
+        if self.keys.contains_key(&device.device) {
+            return;
+        }
+
         self.create_device(move |state, entity| {
-            let FwupdSignal { info, device, upgradeable, releases } = signal;
-            let widget = if device.needs_reboot() {
+            state.components.inventory.insert(entity, InventoryEntry::from(&device));
+            let Device { device: key, info, backend, upgradeable, system, pending, .. } = device;
+
+            state.keys.insert(key, entity);
+            state.components.keys.insert(entity, key);
+
+            let widget = if system {
                 state.entities.associate_system(entity);
                 state.widgets.view_devices.system(&info)
             } else {
@@ -129,28 +356,75 @@ impl State {
             };
 
             widget.stack.hide();
+            state.connect_device(entity, &info, &widget);
 
-            if let Some(latest) = info.latest {
-                state.components.latest.insert(entity, latest);
-                state.components.fwupd.insert(entity, (device, releases));
-                if upgradeable {
-                    let sender = state.ui_sender.clone();
-                    widget.stack.show();
-                    widget.connect_upgrade_clicked(move || {
-                        let _ = sender.send(Event::Ui(UiEvent::Update(entity)));
-                    });
+            if backend == Backend::Fwupd {
+                state.components.verifiable.insert(entity, ());
+            }
+
+            match pending {
+                Some(pending) => {
+                    state.components.pending.insert(entity, pending);
+                    match pending {
+                        Pending::Action(action) => widget.set_pending(action, &info.current),
+                        Pending::Scheduled => widget.set_scheduled(),
+                    }
                 }
+                None if upgradeable => widget.stack.show(),
+                None if info.held => widget.stack.switch_to_held(),
+                None => (),
             }
 
-            let sender = state.ui_sender.clone();
-            widget.connect_clicked(move |_| {
-                let _ = sender.send(Event::Ui(UiEvent::Reveal(entity)));
-            });
+            if pending.is_some() || upgradeable {
+                let sender = state.ui_sender.clone();
+                widget.connect_upgrade_clicked(move || {
+                    let _ = sender.send(Event::Ui(UiEvent::Update(entity)));
+                });
+            }
+
+            if let Some(latest) = info.latest {
+                state.components.latest.insert(entity, latest);
+            }
 
             widget
         });
     }
 
+    /// The entity of the device which the service identifies with the given key.
+    pub fn entity(&self, key: u64) -> Option<Entity> { self.keys.get(&key).copied() }
+
+    /// Changes the holds of a device, and scans again so that the change takes effect.
+    pub fn hold(&mut self, entity: Entity, change: HoldChange) {
+        let id = match self.components.hold_ids.get(entity) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let result = Holds::load().and_then(|mut holds| {
+            holds.apply(id, change);
+            holds.save()
+        });
+
+        if let Err(why) = result {
+            let error_message = format_error(&why);
+            error!("{}", error_message);
+            self.widgets.info_bar.set_visible(true);
+            self.widgets.info_bar_label.set_text(error_message.as_str());
+            return;
+        }
+
+        let _ = self.sender.send(Request::Scan);
+    }
+
+    /// Asks fwupd to verify the firmware of a device, or to record it as trusted.
+    pub fn verify(&self, entity: Entity, trust: bool) {
+        if self.components.verifiable.contains_key(entity) {
+            if let Some(&key) = self.components.keys.get(entity) {
+                let _ = self.sender.send(Request::Verify(key, trust));
+            }
+        }
+    }
+
     /// Activates progress bar handling for the given widget.
     pub fn progress_activate(&self, progress: &gtk::ProgressBar) {
         let event = ActivateEvent::Activate(progress.clone());
@@ -167,161 +441,97 @@ impl State {
     /// revealed yet.
     pub fn reveal(&mut self, entity: Entity) {
         let widget = &self.components.device_widgets[entity];
-        let revealer = &widget.revealer;
-        let sender = &self.ui_sender;
-
-        if let Some((_, releases)) = self.components.fwupd.get(entity) {
-            reveal(revealer, sender, entity, move || {
-                let releases = &releases;
-                let log_entries = releases
-                    .iter()
-                    .rev()
-                    .map(|release| (release.version.as_ref(), release.description.as_ref()));
-
-                crate::changelog::generate_widget(log_entries).upcast::<gtk::Container>()
-            });
+        let changelog = self.components.changelog.get(entity).filter(|log| !log.is_empty());
 
-            return;
-        }
-
-        if let Some((_, changelog)) = self.components.system76.get(entity) {
-            reveal(revealer, &sender, entity, || {
-                let log_entries = changelog.versions.iter().map(|version| {
-                    (
-                        version.bios.as_ref(),
-                        version.description.as_ref().map_or_else(
-                            || fl!("not-applicable"),
-                            |desc| String::from(desc.as_ref()),
-                        ),
-                    )
-                });
+        reveal(&widget.revealer, &self.ui_sender, entity, || match changelog {
+            Some(changelog) => {
+                crate::changelog::generate_widget(changelog).upcast::<gtk::Container>()
+            }
+            // When changelog information is not available.
+            None => crate::changelog::generate_widget_none().upcast::<gtk::Container>(),
+        });
+    }
 
-                crate::changelog::generate_widget(log_entries).upcast::<gtk::Container>()
-            });
+    /// Asks the service for the host security attributes, which are displayed when received.
+    pub fn request_security(&mut self) {
+        self.security_requested = true;
+        let _ = self.sender.send(Request::Security);
+    }
 
+    /// Displays the host security attributes which were fetched from fwupd, if this widget
+    /// requested them.
+    pub fn security(&mut self, report: SecurityReport) {
+        if !std::mem::replace(&mut self.security_requested, false) {
             return;
         }
 
-        // When changelog information is not available.
-        reveal(revealer, &sender, entity, || {
-            crate::changelog::generate_widget_none().upcast::<gtk::Container>()
-        });
+        let view = &self.widgets.view_security;
+        view.set_report(&report);
+        self.widgets.stack.show();
+        self.widgets.stack.set_visible_child(view.as_ref());
     }
 
-    /// An event that occurs when System76 system firmware has been found.
-    pub fn system76_system(
-        &mut self,
-        info: FirmwareInfo,
-        downloaded: Option<(System76Digest, System76Changelog)>,
-    ) {
-        self.create_device(move |state, entity| {
-            let widget = state.widgets.view_devices.system(&info);
-            widget.stack.hide();
-            state.entities.associate_system(entity);
-
-            if let Some(latest) = info.latest {
-                if latest != info.current {
-                    widget.stack.show();
-                    let sender = state.ui_sender.clone();
-                    widget.connect_upgrade_clicked(move || {
-                        let _ = sender.send(Event::Ui(UiEvent::Update(entity)));
-                    });
-                }
-
-                state.components.latest.insert(entity, latest);
-                if let Some(data) = downloaded {
-                    state.components.system76.insert(entity, data);
-                }
-            }
-
-            let sender = state.ui_sender.clone();
-            widget.connect_clicked(move |_| {
-                let _ = sender.send(Event::Ui(UiEvent::Reveal(entity)));
-            });
-
-            widget
-        });
+    /// Displays the devices if the user was authorized to manage firmware, or lets them try
+    /// unlocking again.
+    pub fn unlocked(&mut self, authorization: Authorization) {
+        if authorization == Authorization::Authorized {
+            self.is_admin.set(true);
+            self.widgets.stack.set_visible_child(self.widgets.view_empty.as_ref());
+            let _ = self.sender.send(Request::Attach);
+        } else if let Some(ref view) = self.widgets.view_permission {
+            view.unlock_failed();
+        }
     }
 
-    /// An event that occurs when a Thelio I/O board was discovered.
-    pub fn thelio_io(&mut self, info: FirmwareInfo, digest: Option<System76Digest>) {
-        self.create_device(move |state, entity| {
-            let widget = state.widgets.view_devices.device(&info);
-
-            let sender = state.ui_sender.clone();
-            let mut upgradeable = false;
-
-            if let (Some(digest), Some(latest)) = (digest, info.latest) {
-                upgradeable = info.current.as_ref() != latest.as_ref();
-                widget.connect_upgrade_clicked(move || {
-                    let _ = sender.send(Event::Ui(UiEvent::Update(entity)));
-                });
+    /// Schedules the given firmware for an update, and show a dialog if it requires a reboot.
+    ///
+    /// Devices which are waiting to be activated or unlocked have that action performed instead,
+    /// and system firmware which is already scheduled has its update cancelled.
+    pub fn update(&mut self, entity: Entity) {
+        let key = match self.components.keys.get(entity) {
+            Some(&key) => key,
+            None => return,
+        };
 
-                state.components.latest.insert(entity, latest);
-                state.components.thelio.insert(entity, digest);
-            }
+        let widgets = &self.components.device_widgets[entity];
 
-            {
-                // When the device's widget is clicked.
-                let sender = state.ui_sender.clone();
-                widget.connect_clicked(move |_| {
-                    let _ = sender.send(Event::Ui(UiEvent::Reveal(entity)));
-                });
-            }
+        if self.components.pending.contains_key(entity) {
+            widgets.stack.switch_to_waiting();
+            let _ = self.sender.send(Request::Update(key));
+            return;
+        }
 
-            if upgradeable {
-                widget.stack.show();
-            } else {
-                widget.stack.hide();
+        let latest = match self.components.latest.get(entity) {
+            Some(latest) => latest,
+            None => {
+                error!(
+                    "attempted to update firmware for a device which did not have updated firmware"
+                );
+                return;
             }
+        };
 
-            widget
-        });
-    }
+        if self.entities.is_system(entity) {
+            let changelog = &self.components.changelog[entity];
+            let dialog = FirmwareUpdateDialog::new(latest, changelog, self.has_battery);
 
-    /// Schedules the given firmware for an update, and show a dialog if it requires a reboot.
-    pub fn update(&mut self, entity: Entity) {
-        if let Some(latest) = self.components.latest.get(entity) {
-            let widgets = &self.components.device_widgets[entity];
-
-            if let Some((device, releases)) = self.components.fwupd.get(entity) {
-                let dialog = FwupdDialog {
-                    device: &device,
-                    entity,
-                    has_battery: self.has_battery,
-                    latest: &latest,
-                    needs_reboot: self.entities.is_system(entity),
-                    releases: &releases,
-                    sender: &self.sender,
-                    widgets,
-                };
-
-                dialog.run();
+            let response = dialog.run();
+            dialog.close();
 
+            if response != gtk::ResponseType::Accept {
                 return;
             }
-
-            if let Some((digest, changelog)) = self.components.system76.get(entity) {
-                let dialog = System76Dialog {
-                    changelog: &changelog,
-                    digest: &digest,
-                    entity,
-                    has_battery: self.has_battery,
-                    latest: &latest,
-                    sender: &self.sender,
-                    widgets,
-                };
-
-                dialog.run();
-            } else if let Some(digest) = self.components.thelio.get(entity) {
-                // Exchange the button for a progress bar.
-                widgets.stack.switch_to_waiting();
-                self.progress_activate(&widgets.stack.progress);
-                let _ = self.sender.send(FirmwareEvent::ThelioIo(entity, digest.clone()));
-            }
-        } else {
-            error!("attempted to update firmware for a device which did not have updated firmware");
         }
+
+        // Exchange the button for a progress bar.
+        widgets.stack.switch_to_waiting();
+        self.components.requested.insert(entity, ());
+        let _ = self.sender.send(Request::Update(key));
+    }
+
+    /// System firmware was requested to be updated by this widget, rather than another frontend.
+    pub fn system_update_requested(&self) -> bool {
+        self.components.requested.keys().any(|entity| self.entities.is_system(entity))
     }
 }
 
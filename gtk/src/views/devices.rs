@@ -1,23 +1,28 @@
 use crate::{fl, traits::DynamicGtkResize, widgets::DeviceWidget};
-use firmware_manager::FirmwareInfo;
+use firmware_manager::{FirmwareInfo, Urgency};
 use gtk::prelude::*;
-use std::num::NonZeroU8;
+use std::{cell::RefCell, num::NonZeroU8};
 
 /// The devices view is displayed when devices are found.
 ///
 /// It consists of a collection of system firmware which requires a reboot to flash, and device
 /// firmware which does not. The `system()` and `device()` methods will create and add device
 /// widges to their appropriate list boxes, and will return the created device widget so that we
-/// can program its signals.
+/// can program its signals. Devices with the most urgent updates are listed first. Beneath the
+/// devices, a button leads to the security view, and another exports the firmware inventory.
 #[derive(Shrinkwrap)]
 pub struct DevicesView {
     #[shrinkwrap(main_field)]
     container: gtk::Container,
     device_firmware: gtk::ListBox,
     device_header: gtk::Label,
+    device_urgencies: RefCell<Vec<Urgency>>,
+    export: gtk::Button,
+    security: gtk::Button,
     sg: gtk::SizeGroup,
     system_firmware: gtk::ListBox,
     system_header: gtk::Label,
+    system_urgencies: RefCell<Vec<Urgency>>,
 }
 
 impl DevicesView {
@@ -107,6 +112,28 @@ impl DevicesView {
             ..set_xalign(0.0);
         };
 
+        let security = gtk::ButtonBuilder::new()
+            .label(&fl!("button-security"))
+            .halign(gtk::Align::Start)
+            .visible(true)
+            .build();
+
+        let export = gtk::ButtonBuilder::new()
+            .label(&fl!("button-export-inventory"))
+            .halign(gtk::Align::Start)
+            .visible(true)
+            .build();
+
+        let actions = cascade! {
+            gtk::BoxBuilder::new()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(12)
+                .visible(true)
+                .build();
+            ..add(&security);
+            ..add(&export);
+        };
+
         let layout: gtk::Box = cascade! {
             gtk::Box::new(gtk::Orientation::Vertical, 12);
             ..set_halign(gtk::Align::Center);
@@ -116,6 +143,7 @@ impl DevicesView {
             ..add(&system_firmware);
             ..add(&device_header);
             ..add(&device_firmware);
+            ..add(&actions);
             ..set_no_show_all(true);
         };
 
@@ -125,8 +153,8 @@ impl DevicesView {
             ..add_widget(&device_firmware);
         };
 
-        device_firmware.set_header_func(Some(Box::new(separator_header)));
-        system_firmware.set_header_func(Some(Box::new(separator_header)));
+        device_firmware.set_header_func(Some(Box::new(super::separator_header)));
+        system_firmware.set_header_func(Some(Box::new(super::separator_header)));
 
         let container = cascade! {
             gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
@@ -139,9 +167,13 @@ impl DevicesView {
             container: container.upcast(),
             device_firmware,
             device_header,
+            device_urgencies: RefCell::default(),
+            export,
+            security,
             sg: gtk::SizeGroup::new(gtk::SizeGroupMode::Vertical),
             system_firmware,
             system_header,
+            system_urgencies: RefCell::default(),
         }
     }
 
@@ -153,18 +185,31 @@ impl DevicesView {
         self.device_firmware.foreach(|x| unsafe {
             x.destroy();
         });
+
+        self.system_urgencies.borrow_mut().clear();
+        self.device_urgencies.borrow_mut().clear();
+    }
+
+    /// Activates when the button leading to the security view is clicked.
+    pub fn connect_security<F: Fn() + 'static>(&self, func: F) {
+        self.security.connect_clicked(move |_| func());
+    }
+
+    /// Activates when the button which exports the firmware inventory is clicked.
+    pub fn connect_export<F: Fn() + 'static>(&self, func: F) {
+        self.export.connect_clicked(move |_| func());
     }
 
     /// Creates and attaches a new device widget to the device section.
     pub fn device(&self, info: &FirmwareInfo) -> DeviceWidget {
         self.show_devices();
-        self.append(&self.device_firmware, info)
+        self.append(&self.device_firmware, &self.device_urgencies, info)
     }
 
     /// Creates and attaches a new device widget to the system section.
     pub fn system(&self, info: &FirmwareInfo) -> DeviceWidget {
         self.show_systems();
-        self.append(&self.system_firmware, info)
+        self.append(&self.system_firmware, &self.system_urgencies, info)
     }
 
     /// Hides the device section so that it does not appear to the end user.
@@ -193,19 +238,24 @@ impl DevicesView {
 
     /// Convenience method shared by both the device and system methods.
     ///
-    /// This is responsible for creating a device widget and assigning it to the given parent
-    /// container.
-    fn append(&self, parent: &impl gtk::traits::ContainerExt, info: &FirmwareInfo) -> DeviceWidget {
+    /// This is responsible for creating a device widget and inserting it into the given parent
+    /// list box, after every device with an update that is at least as urgent.
+    fn append(
+        &self,
+        parent: &gtk::ListBox,
+        urgencies: &RefCell<Vec<Urgency>>,
+        info: &FirmwareInfo,
+    ) -> DeviceWidget {
         let widget = DeviceWidget::new(info);
         self.sg.add_widget(&widget.event_box);
-        parent.add(widget.as_ref());
-        widget
-    }
-}
 
-/// Inserts a separator as a header between rows in a list box.
-fn separator_header(current: &gtk::ListBoxRow, before: Option<&gtk::ListBoxRow>) {
-    if before.is_some() {
-        current.set_header(Some(&gtk::Separator::new(gtk::Orientation::Horizontal)));
+        let urgency = info.urgency();
+        let mut urgencies = urgencies.borrow_mut();
+        let position =
+            urgencies.iter().position(|&other| other < urgency).unwrap_or(urgencies.len());
+        urgencies.insert(position, urgency);
+
+        parent.insert(widget.as_ref(), position as i32);
+        widget
     }
 }
@@ -1,4 +1,5 @@
 use crate::fl;
+use firmware_manager::{Backend, ScanReport, ScanStatus};
 use gtk::prelude::*;
 
 /// View displayed when scanning has completed, but no firmware was found.
@@ -12,17 +13,126 @@ impl EmptyView {
 }
 
 /// View displayed to users who lack administrative permissions.
+///
+/// Users who may authenticate as an administrator are offered a button to unlock the widget.
 #[derive(Shrinkwrap)]
-pub struct PermissionView(gtk::Container);
+pub struct PermissionView {
+    #[shrinkwrap(main_field)]
+    container: gtk::Container,
+    label: gtk::Label,
+    unlock: gtk::Button,
+}
 
 impl PermissionView {
+    pub fn new(can_unlock: bool) -> Self {
+        let label = error_label(&fl!("view-permission"));
+
+        let unlock = cascade! {
+            gtk::ButtonBuilder::new()
+                .label(&fl!("button-unlock"))
+                .halign(gtk::Align::Start)
+                .build();
+            ..style_context().add_class(&gtk::STYLE_CLASS_SUGGESTED_ACTION);
+        };
+
+        let content = cascade! {
+            gtk::Box::new(gtk::Orientation::Vertical, 12);
+            ..add(&label);
+            ..add(&unlock);
+        };
+
+        let container = error_view_with_content("system-lock-screen-symbolic", &content);
+
+        if !can_unlock {
+            unlock.set_no_show_all(true);
+            unlock.hide();
+        }
+
+        Self { container, label, unlock }
+    }
+
+    /// Activates when the unlock button is clicked.
+    ///
+    /// The button remains insensitive until `unlock_failed()` is called.
+    pub fn connect_unlock<F: Fn() + 'static>(&self, func: F) {
+        self.unlock.connect_clicked(move |button| {
+            button.set_sensitive(false);
+            func();
+        });
+    }
+
+    /// Informs the user that authorization was not granted, so that they may try again.
+    pub fn unlock_failed(&self) {
+        self.label.set_text(&[&*fl!("view-permission"), "\n\n", &*fl!("unlock-failed")].concat());
+        self.unlock.set_sensitive(true);
+    }
+}
+
+/// View displayed when no firmware was found, because firmware services are missing or failing.
+#[derive(Shrinkwrap)]
+pub struct UnavailableView {
+    #[shrinkwrap(main_field)]
+    container: gtk::Container,
+    label: gtk::Label,
+}
+
+impl UnavailableView {
     pub fn new() -> Self {
-        Self(error_view("system-lock-screen-symbolic", &fl!("view-permission")))
+        let label = error_label(&fl!("view-unavailable"));
+        let container = error_view_with_label("dialog-warning-symbolic", &label);
+        Self { container, label }
+    }
+
+    /// Explains which firmware services were not scanned, and how to enable them.
+    pub fn set_report(&self, report: &ScanReport) {
+        let mut text = fl!("view-unavailable");
+
+        for (backend, status) in report.iter() {
+            let reason = match (backend, status) {
+                (_, ScanStatus::Found(_)) | (_, ScanStatus::Incomplete(..)) => continue,
+                (backend, ScanStatus::Disabled) => {
+                    fl!("view-unavailable-disabled", service = backend.name())
+                }
+                (Backend::Fwupd, ScanStatus::Unavailable) => fl!("view-unavailable-fwupd"),
+                (Backend::System76, ScanStatus::Unavailable) => fl!("view-unavailable-system76"),
+                (backend, ScanStatus::Errored(why)) => {
+                    fl!("view-unavailable-errored", service = backend.name(), reason = why.as_ref())
+                }
+            };
+
+            text.push_str("\n\n");
+            text.push_str(&reason);
+        }
+
+        self.label.set_text(&text);
     }
 }
 
 /// Template for creating new error views.
 fn error_view(icon: &str, reason: &str) -> gtk::Container {
+    error_view_with_label(icon, &error_label(reason))
+}
+
+/// Creates the label which describes the reason for an error view.
+fn error_label(reason: &str) -> gtk::Label {
+    cascade! {
+        gtk::LabelBuilder::new()
+            .label(reason)
+            .wrap(true)
+            .xalign(0.0)
+            .yalign(0.0)
+            .build();
+        ..style_context().add_class(&gtk::STYLE_CLASS_DIM_LABEL);
+    }
+}
+
+/// Template for creating new error views with an existing label.
+fn error_view_with_label(icon: &str, label: &gtk::Label) -> gtk::Container {
+    error_view_with_content(icon, label)
+}
+
+/// Template for creating new error views with custom content beside the icon.
+fn error_view_with_content(icon: &str, content: &impl IsA<gtk::Widget>) -> gtk::Container {
     let container = cascade! {
         gtk::Box::new(gtk::Orientation::Horizontal, 24);
         ..set_halign(gtk::Align::Center);
@@ -34,15 +144,7 @@ fn error_view(icon: &str, reason: &str) -> gtk::Container {
                 .pixel_size(64)
                 .build()
         );
-        ..add(&cascade! {
-            gtk::LabelBuilder::new()
-                .label(reason)
-                .wrap(true)
-                .xalign(0.0)
-                .yalign(0.0)
-                .build();
-            ..style_context().add_class(&gtk::STYLE_CLASS_DIM_LABEL);
-        });
+        ..add(content);
         ..show_all();
     };
 
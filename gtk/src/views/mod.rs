@@ -2,8 +2,19 @@
 
 mod devices;
 mod error;
+mod security;
 
 pub use self::{
     devices::DevicesView,
-    error::{EmptyView, PermissionView},
+    error::{EmptyView, PermissionView, UnavailableView},
+    security::SecurityView,
 };
+
+use gtk::prelude::*;
+
+/// Inserts a separator as a header between rows in a list box.
+fn separator_header(current: &gtk::ListBoxRow, before: Option<&gtk::ListBoxRow>) {
+    if before.is_some() {
+        current.set_header(Some(&gtk::Separator::new(gtk::Orientation::Horizontal)));
+    }
+}
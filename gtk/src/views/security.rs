@@ -0,0 +1,151 @@
+use crate::{fl, traits::DynamicGtkResize};
+use firmware_manager::{SecurityAttribute, SecurityAttributeKind, SecurityReport};
+use gtk::prelude::*;
+use std::num::NonZeroU8;
+
+/// The security view lists the host security attributes which fwupd assessed.
+///
+/// Each attribute is displayed with whether it passed, and how to remedy it when it did not.
+#[derive(Shrinkwrap)]
+pub struct SecurityView {
+    #[shrinkwrap(main_field)]
+    container: gtk::Container,
+    attributes: gtk::ListBox,
+    back: gtk::Button,
+    host_security_id: gtk::Label,
+}
+
+impl SecurityView {
+    pub fn new() -> Self {
+        let back = gtk::Button::from_icon_name(Some("go-previous-symbolic"), gtk::IconSize::Button);
+
+        let header = gtk::LabelBuilder::new()
+            .label(&format!("<b>{}</b>", fl!("header-host-security")))
+            .use_markup(true)
+            .xalign(0.0)
+            .build();
+
+        let host_security_id = cascade! {
+            gtk::LabelBuilder::new().xalign(0.0).build();
+            ..style_context().add_class(&gtk::STYLE_CLASS_DIM_LABEL);
+        };
+
+        let attributes = cascade! {
+            gtk::ListBox::new();
+            ..set_selection_mode(gtk::SelectionMode::None);
+            ..set_header_func(Some(Box::new(super::separator_header)));
+        };
+
+        let layout = cascade! {
+            gtk::Box::new(gtk::Orientation::Vertical, 12);
+            ..set_halign(gtk::Align::Center);
+            ..set_margin_top(24);
+            ..set_margin_bottom(24);
+            ..add(&cascade! {
+                gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                ..add(&back);
+                ..add(&header);
+            });
+            ..add(&host_security_id);
+            ..add(&attributes);
+        };
+
+        let container = cascade! {
+            gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+            ..add(&layout);
+            ..show_all();
+            ..dynamic_resize(layout, NonZeroU8::new(66), None);
+        };
+
+        Self { container: container.upcast(), attributes, back, host_security_id }
+    }
+
+    /// Activates when the back button is clicked.
+    pub fn connect_back<F: Fn() + 'static>(&self, func: F) {
+        self.back.connect_clicked(move |_| func());
+    }
+
+    /// Replaces the displayed attributes with those of the report.
+    pub fn set_report(&self, report: &SecurityReport) {
+        self.host_security_id.set_text(&match report.host_security_id {
+            Some(ref id) => fl!("security-hsi", id = id.as_ref()),
+            None => fl!("security-hsi-unknown"),
+        });
+
+        self.attributes.foreach(|x| unsafe {
+            x.destroy();
+        });
+
+        for attribute in &report.attributes {
+            self.attributes.add(&attribute_row(attribute));
+        }
+
+        self.attributes.show_all();
+    }
+}
+
+/// Creates the row which displays an attribute and its state.
+fn attribute_row(attribute: &SecurityAttribute) -> gtk::Grid {
+    let (icon, state) = if attribute.passed {
+        ("emblem-ok-symbolic", fl!("security-pass"))
+    } else {
+        ("dialog-warning-symbolic", fl!("security-fail"))
+    };
+
+    let name = gtk::LabelBuilder::new().label(attribute.name.as_ref()).xalign(0.0).build();
+
+    let state = cascade! {
+        gtk::LabelBuilder::new().label(&state).halign(gtk::Align::End).hexpand(true).build();
+        ..style_context().add_class(&gtk::STYLE_CLASS_DIM_LABEL);
+    };
+
+    let grid = cascade! {
+        gtk::GridBuilder::new()
+            .column_spacing(12)
+            .row_spacing(3)
+            .border_width(12)
+            .build();
+        ..attach(&gtk::Image::from_icon_name(Some(icon), gtk::IconSize::Menu), 0, 0, 1, 1);
+        ..attach(&name, 1, 0, 1, 1);
+        ..attach(&state, 2, 0, 1, 1);
+    };
+
+    if !attribute.passed {
+        let mut text = glib::markup_escape_text(&remediation(&attribute.kind)).to_string();
+
+        if let Some(ref uri) = attribute.uri {
+            text.push(' ');
+            text.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                glib::markup_escape_text(uri),
+                fl!("security-more-information")
+            ));
+        }
+
+        let remediation = cascade! {
+            gtk::LabelBuilder::new()
+                .label(&text)
+                .use_markup(true)
+                .wrap(true)
+                .xalign(0.0)
+                .build();
+            ..style_context().add_class(&gtk::STYLE_CLASS_DIM_LABEL);
+        };
+
+        grid.attach(&remediation, 1, 1, 2, 1);
+    }
+
+    grid
+}
+
+/// How the user may remedy an attribute which did not pass.
+fn remediation(kind: &SecurityAttributeKind) -> String {
+    match kind {
+        SecurityAttributeKind::SecureBoot => fl!("security-fix-secure-boot"),
+        SecurityAttributeKind::Tpm => fl!("security-fix-tpm"),
+        SecurityAttributeKind::Iommu => fl!("security-fix-iommu"),
+        SecurityAttributeKind::BiosWriteProtection => fl!("security-fix-bios-write-protection"),
+        SecurityAttributeKind::ManagementEngine => fl!("security-fix-management-engine"),
+        SecurityAttributeKind::Other(_) => fl!("security-fix-other"),
+    }
+}
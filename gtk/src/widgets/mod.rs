@@ -1,5 +1,5 @@
 use crate::fl;
-use firmware_manager::FirmwareInfo;
+use firmware_manager::{FirmwareInfo, PendingAction, Urgency, Verification};
 use gtk::prelude::*;
 
 #[derive(Shrinkwrap)]
@@ -9,6 +9,7 @@ pub struct DeviceWidgetStack {
     pub button: gtk::Button,
     pub progress: gtk::ProgressBar,
     pub waiting: gtk::Label,
+    pub held: gtk::Label,
 }
 
 impl DeviceWidgetStack {
@@ -17,6 +18,11 @@ impl DeviceWidgetStack {
         self.progress.set_fraction(0.0);
     }
 
+    pub fn switch_to_held(&self) {
+        self.stack.set_visible_child(&self.held);
+        self.stack.show();
+    }
+
     pub fn switch_to_progress(&self, message: &str) {
         self.stack.set_visible_child(&self.progress);
         self.progress.set_text(message.into());
@@ -27,7 +33,8 @@ impl DeviceWidgetStack {
 /// A device widget which displays generic information about a managed device.
 ///
 /// The `connect_clicked()` and `connect_upgrade_clicked()` methods are used to program the actions
-/// that are triggered when either the widget or its upgrade button are clicked.
+/// that are triggered when either the widget or its upgrade button are clicked, and
+/// `connect_context_menu()` programs the action of a secondary click on the widget.
 #[derive(Shrinkwrap)]
 pub struct DeviceWidget {
     #[shrinkwrap(main_field)]
@@ -36,6 +43,7 @@ pub struct DeviceWidget {
     pub revealer: gtk::Revealer,
     pub label: gtk::Label,
     pub stack: DeviceWidgetStack,
+    pub verification: gtk::Label,
 }
 
 impl DeviceWidget {
@@ -47,6 +55,25 @@ impl DeviceWidget {
             .valign(gtk::Align::End)
             .build();
 
+        let verification = cascade! {
+            gtk::LabelBuilder::new().valign(gtk::Align::End).build();
+            ..set_no_show_all(true);
+        };
+
+        let security = cascade! {
+            gtk::LabelBuilder::new()
+                .label(&fl!("badge-security-update"))
+                .valign(gtk::Align::End)
+                .visible(info.security_update())
+                .build();
+            ..set_no_show_all(true);
+            ..style_context().add_class(if info.urgency() == Urgency::Critical {
+                &gtk::STYLE_CLASS_ERROR
+            } else {
+                &gtk::STYLE_CLASS_WARNING
+            });
+        };
+
         let label = cascade! {
             gtk::LabelBuilder::new()
                 .label(info.current.as_ref())
@@ -78,11 +105,17 @@ impl DeviceWidget {
 
         let waiting = gtk::LabelBuilder::new().label(&fl!("action-waiting")).build();
 
+        let held = cascade! {
+            gtk::LabelBuilder::new().label(&fl!("update-held")).halign(gtk::Align::End).build();
+            ..style_context().add_class(&gtk::STYLE_CLASS_DIM_LABEL);
+        };
+
         let stack = cascade! {
             gtk::Stack::new();
             ..add(&button);
             ..add(&progress);
             ..add(&waiting);
+            ..add(&held);
             ..set_visible_child(&button);
         };
 
@@ -122,7 +155,12 @@ impl DeviceWidget {
                     .row_spacing(3)
                     .build();
                 ..attach(&dropdown_image, 0, 0, 1, 2);
-                ..attach(&device, 1, 0, 1, 1);
+                ..attach(&cascade! {
+                    gtk::Box::new(gtk::Orientation::Horizontal, 6);
+                    ..add(&device);
+                    ..add(&security);
+                    ..add(&verification);
+                }, 1, 0, 1, 1);
                 ..attach(&label, 1, 1, 1, 1);
                 ..attach(&stack, 2, 0, 1, 2);
             });
@@ -142,19 +180,86 @@ impl DeviceWidget {
             event_box,
             label,
             revealer,
-            stack: DeviceWidgetStack { button, stack, progress, waiting },
+            stack: DeviceWidgetStack { button, stack, progress, waiting, held },
+            verification,
+        }
+    }
+
+    /// Displays the outcome of verifying the device's firmware as a badge beside its name.
+    pub fn set_verification(&self, verification: &Verification) {
+        let (text, reason) = match verification {
+            Verification::Verified => (fl!("verification-verified"), None),
+            Verification::Trusted => (fl!("verification-trusted"), None),
+            Verification::Unsupported => (fl!("verification-unsupported"), None),
+            Verification::Failed(why) => (fl!("verification-failed"), Some(why.as_ref())),
+        };
+
+        let style = self.verification.style_context();
+        if reason.is_some() {
+            style.remove_class(&gtk::STYLE_CLASS_DIM_LABEL);
+            style.add_class(&gtk::STYLE_CLASS_ERROR);
+        } else {
+            style.remove_class(&gtk::STYLE_CLASS_ERROR);
+            style.add_class(&gtk::STYLE_CLASS_DIM_LABEL);
         }
+
+        self.verification.set_text(&text);
+        self.verification.set_tooltip_text(reason);
+        self.verification.show();
+    }
+
+    /// Displays the action which the device is waiting on, and offers it in place of an update.
+    pub fn set_pending(&self, action: PendingAction, current: &str) {
+        let (status, button) = match action {
+            PendingAction::Activate => {
+                (fl!("pending-activation", version = current), fl!("button-activate"))
+            }
+            PendingAction::Unlock => {
+                (fl!("pending-unlock", version = current), fl!("button-unlock"))
+            }
+        };
+
+        self.label.set_text(&status);
+        self.stack.button.set_label(&button);
+        self.stack.stack.set_visible_child(&self.stack.button);
+        self.stack.show();
+    }
+
+    /// Displays that an update is scheduled for the next boot, and offers to cancel it.
+    pub fn set_scheduled(&self) {
+        self.label.set_text(&fl!("update-scheduled"));
+        self.stack.button.set_label(&fl!("button-unschedule"));
+        self.stack.stack.set_visible_child(&self.stack.button);
+        self.stack.show();
     }
 
     /// Activates when the widget's container is clicked.
+    ///
+    /// Secondary clicks are left to the handler of `connect_context_menu()`.
     pub fn connect_clicked<F: Fn(gtk::Revealer) + 'static>(&self, func: F) {
         let revealer = self.revealer.downgrade();
-        self.event_box.connect_button_press_event(move |_, _| {
+        self.event_box.connect_button_press_event(move |_, event| {
+            if event.button() == gdk::BUTTON_SECONDARY {
+                return gtk::Inhibit(false);
+            }
+
             func(revealer.upgrade().expect("revealer for device did not exist"));
             gtk::Inhibit(true)
         });
     }
 
+    /// Activates when the widget's container is clicked with the secondary button.
+    pub fn connect_context_menu<F: Fn() + 'static>(&self, func: F) {
+        self.event_box.connect_button_press_event(move |_, event| {
+            if event.button() != gdk::BUTTON_SECONDARY {
+                return gtk::Inhibit(false);
+            }
+
+            func();
+            gtk::Inhibit(true)
+        });
+    }
+
     /// Activates when the widget's container's button is clicked.
     pub fn connect_upgrade_clicked<F: Fn() + 'static>(&self, func: F) {
         self.stack.button.connect_clicked(move |_| func());
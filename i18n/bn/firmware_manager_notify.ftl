@@ -1,4 +1,3 @@
 summary = ফার্মওয়্যার আপডেট উপলব্ধ।
 body = এগুলি ইনস্টল করতে এখানে ক্লিক করুন।
 
-error-fwupd = fwupd রিমোটগুলি আপডেট করতে ব্যর্থ।
\ No newline at end of file
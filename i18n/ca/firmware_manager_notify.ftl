@@ -1,4 +1,3 @@
 summary = Hi ha actualitzacions de firmware disponibles.
 body = Clica aquí per instalar-les.
 
-error-fwupd = Error al actualitzar el fwupd remot
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = Firmwareaktualisierungen sind vefügbar.
 body = Klicken Sie hier, um sie zu installieren.
 
-error-fwupd = Aktualisierung der fwupd-Remotes fehlgeschlagen.
@@ -0,0 +1,64 @@
+about = Manage system and device firmware from the command line
+about-list = List devices and their firmware
+about-refresh = Refresh the firmware metadata, and list the updates which are available
+about-update = Update the firmware of a device
+about-changelog = Show the changelog of a device's firmware
+about-history = Show the firmware updates which have been attempted
+
+arg-device = The ID or name of the device, as shown by the list subcommand
+arg-all = Update every device which has an update available
+
+exit-codes =
+    Exit codes:
+        0    success
+        1    an update failed, or no firmware service could be scanned
+        2    invalid arguments
+        3    the device was not found, or has no update available
+        100  updates are available (refresh only)
+
+header-id = ID
+header-name = Device
+header-current = Current
+header-latest = Latest
+header-status = Status
+
+status-up-to-date = Up to date
+status-available = Update available
+status-held = Held
+status-scheduled = Scheduled for next boot
+status-activation = Activation pending
+status-locked = Locked
+
+changelog-unavailable = No changelog is available.
+changelog-urgency = Urgency: {$urgency}
+changelog-fixes = Fixes: {$issues}
+
+urgency-low = low
+urgency-medium = medium
+urgency-high = high
+urgency-critical = critical
+
+no-devices = No firmware devices were found.
+no-history = No firmware updates have been attempted.
+no-updates = No firmware updates are available.
+
+history-updated = updated
+history-scheduled = scheduled
+history-failed = failed: {$reason}
+history-unattended = (automatic)
+
+progress-downloading = Downloading
+progress-flashing = {$device}: flashing firmware
+progress-updated = {$device}: updated to {$version}
+progress-scheduled = {$device}: {$version} will be installed on the next boot
+
+error-config = Invalid configuration
+error-history = Failed to read the update history
+error-not-found = No device matches {$device}
+error-no-update = {$device} has no update available
+error-scan = No firmware service could be scanned
+error-stopped = The firmware manager stopped unexpectedly
+error-update = {$device}: failed to update
+
+warning-refresh-metered = Firmware metadata was not refreshed, as the network connection is metered. Run the refresh subcommand to refresh it anyway.
+warning-refresh-low-battery = Firmware metadata was not refreshed, as the battery is low. Run the refresh subcommand to refresh it anyway.
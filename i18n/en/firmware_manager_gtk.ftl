@@ -3,8 +3,17 @@ action-scheduling = Scheduling
 action-flashing = Flashing
 action-waiting = Waiting
 
+badge-security-update = Security Update
+
+button-activate = Activate
 button-cancel = Cancel
+button-export = Export
+button-export-inventory = Export Inventory…
 button-reboot-and-install = Reboot and Install
+button-refresh-anyway = Refresh Anyway
+button-security = Host Security
+button-unlock = Unlock
+button-unschedule = Cancel Update
 button-update = Update
 
 changelog = Changelog
@@ -13,15 +22,68 @@ changelog-unavailable = No changelog available
 header-system-firmware = System Firmware
 header-device-firmware = Device Firmware
 header-firmware-update = Firmware Update
+header-host-security = Host Security
+
+export-title = Export Firmware Inventory
+export-filter-csv = CSV
+export-filter-json = JSON
+export-failed = Failed to export the firmware inventory
+
+device-verify = Verify Firmware
+device-verify-update = Trust Installed Firmware
+
+hold-current = Hold at Current Version
+hold-ignore = Ignore Version {$version}
+hold-release = Release Hold
+hold-unignore = Stop Ignoring Version {$version}
 
 not-applicable = N/A
 
+refresh-deferred-metered = Firmware metadata was not refreshed, as the network connection is metered.
+refresh-deferred-low-battery = Firmware metadata was not refreshed, as the battery is low.
+
+pending-activation = {$version} · Activation pending
+pending-unlock = {$version} · Locked
+
+security-hsi = Host Security ID: {$id}
+security-hsi-unknown = The Host Security ID could not be determined.
+security-pass = Pass
+security-fail = Fail
+security-more-information = More information
+security-fix-secure-boot = Enable Secure Boot in the firmware setup utility.
+security-fix-tpm = Enable the TPM in the firmware setup utility. If the system has no TPM 2.0, ask the manufacturer whether a firmware update provides one.
+security-fix-iommu = Enable VT-d or AMD-Vi in the firmware setup utility, and ensure that the kernel has not disabled the IOMMU.
+security-fix-bios-write-protection = The system firmware is not protected from being overwritten. Ask the manufacturer for a firmware update which locks it.
+security-fix-management-engine = The Management Engine is not in a secure state. Ask the manufacturer for a firmware update which locks it.
+security-fix-other = This attribute is not in a secure state.
+
+trust-header = Release Trust
+trust-payload-signed = The firmware is signed.
+trust-payload-unsigned = The firmware is not signed.
+trust-metadata-signed = The release information is signed.
+trust-metadata-unsigned = The release information is not signed.
+trust-remote = Provided by the {$remote} remote.
+trust-remote-lvfs = Provided by the stable remote of the Linux Vendor Firmware Service.
+trust-warning-unsigned = The firmware of this release is not signed, so it can not be confirmed that it came from the vendor. Only install it if you trust its source.
+trust-warning-testing = This release is from the lvfs-testing remote, and has not been approved for general use. Only install it if the vendor asked you to test it.
+
+unlock-failed = Authorization was not granted.
+
 update-available = Firmware version {$version} is available.
 
+update-held = Held
+
+update-scheduled = Scheduled for next boot
+
 update-connect-to-ac = Connect your computer to power. <b>USB Type-C</b> charging is not supported for firmware updates.
 
 update-guide = After the firmware update is complete, it may be necessary to press the power button more than once. See {$url_tag_start}this support article{$url_tag_end} for more information.
 
+verification-verified = Verified
+verification-trusted = Trusted
+verification-unsupported = Cannot Verify
+verification-failed = Verification Failed
+
 view-empty = Managed Firmware Unavailable
 
  No devices supporting automatic firmware updates detected.
@@ -29,3 +91,15 @@ view-empty = Managed Firmware Unavailable
 view-permission = Permission Required
 
  Only administrator accounts may update firmware.
+
+view-unavailable = Firmware Services Unavailable
+
+ No service which manages firmware could be found on this system.
+
+view-unavailable-fwupd = The fwupd service is not running, or is not installed. Install the fwupd package, and ensure that its service is enabled, to receive device firmware from the Linux Vendor Firmware Service.
+
+view-unavailable-system76 = The system76-firmware service is not running, or is not installed. On System76 hardware, install the system76-firmware-daemon package, and ensure that its service is enabled, to receive system firmware.
+
+view-unavailable-errored = The {$service} service failed to list its devices: {$reason}
+
+view-unavailable-disabled = The {$service} service is disabled by the Firmware Manager configuration.
@@ -1,4 +1,27 @@
-summary = Firmware updates are available.
+summary = { $count ->
+    [one] A firmware update is available.
+   *[other] {$count} firmware updates are available.
+}
 body = Click here to install them.
+update = {$device}: {$current} → {$latest}
+update-security = {$device}: {$current} → {$latest} (security update)
 
-error-fwupd = Failed to update fwupd remotes
\ No newline at end of file
+action-update-now = Update now
+action-remind-later = Remind me later
+action-skip = { $count ->
+    [one] Skip this version
+   *[other] Skip these versions
+}
+
+update-now-summary = Firmware was updated
+update-now-failed = Firmware could not be updated
+
+auto-update-summary = Firmware was updated automatically
+auto-update-updated = {$device} was updated to {$version}.
+auto-update-failed = {$device} could not be updated to {$version}: {$reason}
+
+error-service = Failed to check for firmware updates
+error-config = Invalid configuration
+error-argument = Invalid argument
+error-preflight = Skipped automatic firmware updates
+error-holds = Failed to skip firmware versions
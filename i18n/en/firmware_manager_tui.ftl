@@ -0,0 +1,49 @@
+title = Firmware Manager
+
+keys = ↑/↓ select · Enter changelog · u update · r scan · q quit
+keys-dialog = Enter reboot and install · Esc cancel
+
+header-system-firmware = System Firmware
+header-device-firmware = Device Firmware
+header-firmware-update = Firmware Update
+
+view-scanning = Scanning for firmware…
+view-empty = No devices supporting automatic firmware updates detected.
+view-unavailable = No firmware service could be reached. Ensure that fwupd or system76-firmware-daemon is installed and running.
+
+action-downloading = Downloading
+action-flashing = Flashing
+action-scheduling = Scheduling
+action-updated = Updated
+action-waiting = Waiting
+
+badge-security-update = Security Update
+
+status-up-to-date = Up to date
+status-available = Update available: {$version}
+status-held = Held
+status-scheduled = Scheduled for next boot
+status-activation = Activation pending
+status-locked = Locked
+
+changelog = Changelog
+changelog-unavailable = No changelog available
+
+trust-header = Release Trust
+trust-payload-signed = The firmware is signed.
+trust-payload-unsigned = The firmware is not signed.
+trust-metadata-signed = The release information is signed.
+trust-metadata-unsigned = The release information is not signed.
+trust-remote = Provided by the {$remote} remote.
+trust-remote-lvfs = Provided by the stable remote of the Linux Vendor Firmware Service.
+trust-warning-unsigned = The firmware of this release is not signed, so it can not be confirmed that it came from the vendor. Only install it if you trust its source.
+trust-warning-testing = This release is from the lvfs-testing remote, and has not been approved for general use. Only install it if the vendor asked you to test it.
+
+update-available = Firmware version {$version} is available. Installing it reboots the system.
+update-connect-to-ac = Connect your computer to power. USB Type-C charging is not supported for firmware updates.
+update-guide = After the firmware update is complete, it may be necessary to press the power button more than once. See {$url} for more information.
+
+rebooting = Rebooting to install the firmware update
+
+error-config = Failed to load the configuration
+error-reboot = Failed to reboot
@@ -1,4 +1,3 @@
 summary = Las actualizaciones del firmware están disponibles.
 body = Cliquee aquí para instalar.
 
-error-fwupd = Falla al actualizar el fwupd remoto
\ No newline at end of file
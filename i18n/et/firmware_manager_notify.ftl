@@ -1,4 +1,3 @@
 summary = Püsivara uuendused on saadaval.
 body = Klõpsa siia nende paigaldamiseks.
 
-error-fwupd = Püsivara allika fwupd uuendamine ebaõnnestus
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = Laiteohjelmiston päivityksiä saatavilla.
 body = Paina tästä asentaaksesi päivityksen.
 
-error-fwupd = Fwupd ohjelmiston päivitys epäonnistui
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = Des mises à jour du micrologiciel sont disponibles.
 body = Cliquez ici pour les installer.
 
-error-fwupd = Échec lors de la mise à jour des répertoires fwupd
\ No newline at end of file
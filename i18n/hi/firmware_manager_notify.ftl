@@ -1,4 +1,3 @@
 summary = फर्मवेयर अपडेट उपलब्ध हैं।
 body = इंस्टॉल करने के लिए यहां क्लिक करें।
 
-error-fwupd = fwupd रिमोट अपडेट करने में विफल
\ No newline at end of file
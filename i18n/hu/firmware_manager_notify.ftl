@@ -1,4 +1,3 @@
 summary = Firmware-frissítés elérhető.
 body = Kattints ide a telepítéshez.
 
-error-fwupd = Nem sikerült a fwupd források frissítése.
@@ -1,4 +1,3 @@
 summary = Pembaruan Firmware tersedia.
 body = Klik untuk memasang.
 
-error-fwupd = Gagal untuk memperbarui fwupd remotes
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = 펌웨어 업데이트가 있습니다.
 body = 여기를 눌러 설치 하세요.
 
-error-fwupd = fwupd remotes 오류로 인해 업데이트 실패
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = ഫേംവെയർ അപ്‌ഡേറ്റുകൾ ലഭ്യമാണ്.
 body = ഇൻസ്റ്റാൾ ചെയ്യാൻ ഇവിടെ ക്ലിക്കുചെയ്യുക.
 
-error-fwupd = Fwupd റിമോറ്റുകൾ അപ്‌ഡേറ്റ് ചെയ്യുന്നതിൽ പരാജയപ്പെട്ടു.
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = Aktualizacje Oprogramowania Wbudowanego są dostępne.
 body = Kliknij tutaj aby je zainstalować.
 
-error-fwupd = Błąd aktualizacji sterowników fwupd
\ No newline at end of file
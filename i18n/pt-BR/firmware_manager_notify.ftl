@@ -1,4 +1,3 @@
 summary = Atualizações de firmware estão disponíveis.
 body = Clique aqui para instalar.
 
-error-fwupd = Falha ao atualizar fwupd remoto
\ No newline at end of file
@@ -1,4 +1,3 @@
 summary = Na voljo so posodobitve vdelane programske opreme.
 body = Kliknite tukaj, da jih namestite.
 
-error-fwupd = Daljinskih upravljalnikov fwupd ni bilo mogoče posodobiti
@@ -1,5 +1,4 @@
 summary = Firmver ažuriranja su dostupna.
 body = Kliknite ovde da biste ih instalirali.
 
-error-fwupd = Greška pri ažuriranju fwupd listi
 
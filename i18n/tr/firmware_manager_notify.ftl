@@ -1,4 +1,3 @@
 summary = Aygıt yazılımı güncellemeleri mevcut.
 body = Kurmak için tıklayın.
 
-error-fwupd = fwupd güncellemeleri alınamadı
@@ -15,6 +15,23 @@ fn service(description: &str, appid: &str, exec: &str) -> String {
     )
 }
 
+fn daemon(description: &str, appid: &str, exec: &str) -> String {
+    fomat!(
+        "[Unit]\n"
+        "Description=" (description) "\n"
+        "Conflicts=" (appid) ".timer " (appid) ".service\n"
+        "PartOf=graphical-session.target\n"
+        "After=graphical-session.target\n"
+        "\n"
+        "[Service]\n"
+        "ExecStart=" (exec) " --daemon\n"
+        "Restart=on-failure\n"
+        "\n"
+        "[Install]\n"
+        "WantedBy=graphical-session.target\n"
+    )
+}
+
 fn timer(description: &str, appid: &str, minutes: u16) -> String {
     fomat!(
         "[Unit]\n"
@@ -37,13 +54,19 @@ fn main() {
 
     let timer_path = ["../target/", &appid, ".timer"].concat();
     let service_path = ["../target/", &appid, ".service"].concat();
+    let daemon_path = ["../target/", &appid, ".Daemon.service"].concat();
     let exec = [&prefix, "/bin/", &appid].concat();
 
-    let timer = timer("Checks for new firmware every day", &appid, 1440);
+    // The notifier skips checks until the interval of its configuration has passed, so the timer
+    // only needs to wake it up regularly.
+    let timer = timer("Checks for new firmware at the configured interval", &appid, 60);
 
     let service =
         service("Check for firmware updates, and display a notification if found", &appid, &exec);
 
+    // The daemon is opt-in, and checks for updates itself, so starting it stops the timer.
+    let daemon = daemon("Check for firmware updates when devices are attached", &appid, &exec);
+
     File::create(timer_path)
         .expect("failed to create timer service")
         .write_all(timer.to_string().as_bytes())
@@ -53,4 +76,9 @@ fn main() {
         .expect("failed to create service service")
         .write_all(service.to_string().as_bytes())
         .expect("failed to write service service");
+
+    File::create(daemon_path)
+        .expect("failed to create daemon service")
+        .write_all(daemon.to_string().as_bytes())
+        .expect("failed to write daemon service");
 }
@@ -1,94 +1,620 @@
 mod localize;
 
-use firmware_manager::{get_client, FirmwareSignal, FwupdError, FwupdSignal};
+use firmware_manager::{
+    format_error, metadata_refresh_due, notification_check_due, notification_checked,
+    notification_required, notification_shown, notification_snooze, notification_snoozed,
+    preflight_check, usb_hotplug_event_loop, Config, FirmwareInfo, HoldChange, Holds, ScanReport,
+    Urgency,
+};
+use firmware_manager_service::{Client, Device, Signal};
 use i18n_embed::DesktopLanguageRequester;
-use notify_rust::{Notification, Timeout};
+use notify_rust::{Notification, Timeout, Urgency as NotificationUrgency};
 use std::{
+    env,
     path::Path,
     process::{exit, Command},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 const UPDATES_FOUND: i32 = 3;
 
+/// How long the notifier waits for the firmware manager service to scan for devices.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long the notifier waits for the firmware manager service to update a device.
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// How often the daemon wakes to refresh metadata, and to check for updates when a check is due.
+const DAEMON_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long the daemon waits for USB devices to stop appearing before checking for updates, as
+/// a dock attaches several devices at once, and firmware services need time to discover them.
+const HOTPLUG_SETTLE: Duration = Duration::from_secs(5);
+
 const GNOME_CONTROL_CENTER: &str = "/usr/share/applications/gnome-firmware-panel.desktop";
 
-use firmware_manager::{
-    fwupd_scan, fwupd_updates, s76_firmware_is_active, s76_scan, FwupdClient, System76Client,
-};
+const NOTIFICATIONS_SERVICE: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// An update which was found for a device, and is listed by the notification.
+struct Update {
+    id: Box<str>,
+    name: Box<str>,
+    current: Box<str>,
+    latest: Box<str>,
+    security: bool,
+    urgency: Urgency,
+}
+
+impl Update {
+    fn new(info: &FirmwareInfo) -> Self {
+        Self {
+            id: info.id.clone(),
+            name: info.name.clone(),
+            current: info.current.clone(),
+            latest: info.latest.clone().unwrap_or_default(),
+            security: info.security_update(),
+            urgency: info.urgency(),
+        }
+    }
+}
+
+/// The updates which a notification offered.
+struct Offer {
+    updates: Vec<Update>,
+    /// Updates which may be installed from the notification, as they do not require a reboot.
+    installable: Vec<Update>,
+}
+
+/// The outcome of an update which was installed from the notifier.
+struct Installed {
+    name: Box<str>,
+    version: Box<str>,
+    /// Why the update failed, if it did.
+    failure: Option<Box<str>>,
+}
+
+/// A connection to the firmware manager service, which scans for devices and updates them on
+/// behalf of the notifier.
+struct Service {
+    client: Client,
+    signals: Receiver<Signal>,
+}
+
+impl Service {
+    fn connect() -> Result<Self, dbus::Error> {
+        let client = Client::new()?;
+        let (sender, signals) = mpsc::channel();
+        client.watch(move |signal| {
+            let _ = sender.send(signal);
+        })?;
+
+        Ok(Self { client, signals })
+    }
+
+    /// Asks the service to scan, and collects the devices which it finds.
+    ///
+    /// Devices sent before the scan begins belong to a scan which was already in progress, and
+    /// are ignored.
+    fn scan(&self) -> Result<(Vec<Device>, ScanReport), dbus::Error> {
+        self.client.scan()?;
+
+        let deadline = Instant::now() + SCAN_TIMEOUT;
+        let mut devices = Vec::new();
+        let mut scanning = false;
+
+        loop {
+            match self.next(deadline)? {
+                Signal::Scanning => {
+                    scanning = true;
+                    devices.clear();
+                }
+                Signal::DeviceFound(device) if scanning => devices.push(*device),
+                Signal::ScanningComplete(report) if scanning => return Ok((devices, report)),
+                _ => (),
+            }
+        }
+    }
+
+    /// The devices which the service has found, scanning for them if it has not yet.
+    ///
+    /// The service exits while idle, so the devices of an earlier scan may have been forgotten.
+    fn devices(&self) -> Result<Vec<Device>, dbus::Error> {
+        let list = self.client.devices()?;
+        if list.scanned {
+            Ok(list.devices)
+        } else {
+            self.scan().map(|(devices, _)| devices)
+        }
+    }
+
+    /// Asks the service to update each device in turn, waiting for the outcome of each.
+    ///
+    /// Unattended updates are recorded as such in the update history.
+    fn install(&self, devices: &[Device], unattended: bool) -> Vec<Installed> {
+        devices
+            .iter()
+            .map(|device| Installed {
+                name: device.info.name.clone(),
+                version: device.info.latest.clone().unwrap_or_default(),
+                failure: self.install_device(device.device, unattended),
+            })
+            .collect()
+    }
+
+    /// Asks the service to update the device, and returns why the update failed, if it did.
+    fn install_device(&self, device: u64, unattended: bool) -> Option<Box<str>> {
+        let requested =
+            if unattended { self.client.auto_update(device) } else { self.client.update(device) };
+
+        if let Err(why) = requested {
+            return Some(format_error(&why).into());
+        }
+
+        let deadline = Instant::now() + UPDATE_TIMEOUT;
+
+        loop {
+            match self.next(deadline) {
+                Ok(Signal::DeviceUpdated(key)) if key == device => return None,
+                Ok(Signal::Error(Some(key), why)) if key == device => return Some(why),
+                Ok(_) => (),
+                Err(why) => return Some(format_error(&why).into()),
+            }
+        }
+    }
+
+    /// Waits for the next signal of the service, until the deadline has passed.
+    fn next(&self, deadline: Instant) -> Result<Signal, dbus::Error> {
+        loop {
+            if let Ok(signal) = self.signals.try_recv() {
+                return Ok(signal);
+            }
+
+            let remaining = deadline.checked_duration_since(Instant::now()).ok_or_else(|| {
+                dbus::Error::new_custom(
+                    "org.freedesktop.DBus.Error.Timeout",
+                    "timed out waiting for the firmware manager service",
+                )
+            })?;
+
+            self.client.process(remaining)?;
+        }
+    }
+}
+
+/// Shows notifications about updates, and sends the response of the user to the notifier.
+///
+/// Only the latest notification about updates is shown, so the notification before it is closed.
+#[derive(Clone)]
+struct Notifier {
+    wake: Sender<Wake>,
+    /// The ID of the notification about updates which is shown, if any.
+    shown: Arc<Mutex<Option<u32>>>,
+}
+
+impl Notifier {
+    fn new(wake: Sender<Wake>) -> Self { Self { wake, shown: Arc::default() } }
+}
+
+/// Wakes the notifier while it waits for USB devices, or for the user to respond.
+enum Wake {
+    /// A USB device was attached.
+    Hotplug,
+    /// The user chose an action from the notification about an offer.
+    Response(Response, Offer),
+}
+
+/// The action which the user chose from the notification.
+enum Response {
+    /// Open the Firmware panel of GNOME Settings, or the firmware manager.
+    Open,
+    /// Install the updates which do not require a reboot.
+    UpdateNow,
+    /// Notify about the updates again once the snooze has passed.
+    RemindLater,
+    /// Ignore the versions which were offered.
+    Skip,
+    /// The notification was closed without choosing an action.
+    Dismissed,
+}
 
 fn main() {
     translate();
 
-    if !firmware_manager::user_is_admin() {
+    let daemon = match env::args().nth(1).as_deref() {
+        None => false,
+        Some("--daemon") => true,
+        Some(argument) => {
+            eprintln!("{}: {}", fl!("error-argument"), argument);
+            exit(2);
+        }
+    };
+
+    let config = Config::load().unwrap_or_else(|why| {
+        eprintln!("{}: {}", fl!("error-config"), format_error(&why));
+        Config::default()
+    });
+
+    if !firmware_manager::user_is_admin(&config) {
+        return;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+
+    if daemon {
+        run_daemon(&config, sender, receiver);
+    }
+
+    if !notification_check_due(&config) {
         return;
     }
 
-    let s76 = get_client("system76", s76_firmware_is_active, System76Client::new);
+    let notifier = Notifier::new(sender);
+    let found = check(&config, &notifier, true);
 
-    let fwupd = get_client::<_, _, FwupdError>(
-        "fwupd",
-        || true,
-        || {
-            let client = FwupdClient::new()?;
-            client.ping()?;
-            Ok(client)
-        },
-    );
+    // Waits for the user to respond to the notification, if one is shown.
+    drop(notifier);
+    while let Ok(Wake::Response(response, offer)) = receiver.recv() {
+        respond(response, offer);
+    }
 
-    let event_handler = |event: FirmwareSignal| match event {
-        FirmwareSignal::Fwupd(FwupdSignal { upgradeable, .. }) => {
-            if upgradeable {
-                notify();
+    if found {
+        exit(UPDATES_FOUND);
+    }
+}
+
+/// Stays resident, checking for updates whenever a USB device is attached, in addition to the
+/// checks at the configured interval.
+///
+/// Metadata is refreshed at the configured interval, rather than only when a check is due, by
+/// asking the firmware manager service to scan once a refresh is due.
+fn run_daemon(config: &Config, sender: Sender<Wake>, receiver: Receiver<Wake>) -> ! {
+    let _hotplug = usb_hotplug_event_loop({
+        let sender = sender.clone();
+        move || {
+            let _ = sender.send(Wake::Hotplug);
+        }
+    });
+
+    let notifier = Notifier::new(sender);
+
+    loop {
+        if notification_check_due(config) {
+            check(config, &notifier, true);
+        } else if metadata_refresh_due(config) {
+            if let Err(why) = Service::connect().and_then(|service| service.scan()) {
+                eprintln!("{}: {}", fl!("error-service"), format_error(&why));
             }
         }
-        FirmwareSignal::S76System(info, ..) | FirmwareSignal::ThelioIo(info, ..) => {
-            if info.latest.as_ref().map_or(false, |latest| latest.as_ref() != info.current.as_ref())
-            {
-                notify();
+
+        match receiver.recv_timeout(DAEMON_INTERVAL) {
+            Ok(Wake::Hotplug) => {
+                while let Ok(wake) = receiver.recv_timeout(HOTPLUG_SETTLE) {
+                    if let Wake::Response(response, offer) = wake {
+                        respond(response, offer);
+                    }
+                }
+
+                // Updates which were already notified about are not notified about again, so
+                // attached devices may be checked without waiting for the next check, unless
+                // the user asked to be reminded later.
+                if !notification_snoozed(config) {
+                    check(config, &notifier, false);
+                }
             }
+            Ok(Wake::Response(response, offer)) => respond(response, offer),
+            Err(_) => (),
+        }
+    }
+}
+
+/// Checks for updates, installing those which the auto-update policy allows, and notifies about
+/// the rest.
+///
+/// The firmware manager service scans for the devices, refreshing the fwupd remote metadata if it
+/// is due. A scheduled check is recorded if a firmware service was scanned, so that a check which
+/// failed is attempted again before the next interval. Checks for attached devices are not
+/// scheduled, and do not delay the next scheduled check. Returns `true` if updates were found.
+///
+/// The response to the notification is sent through the notifier, once the user has chosen one.
+fn check(config: &Config, notifier: &Notifier, scheduled: bool) -> bool {
+    let service = match Service::connect() {
+        Ok(service) => service,
+        Err(why) => {
+            eprintln!("{}: {}", fl!("error-service"), format_error(&why));
+            return false;
+        }
+    };
+
+    let (devices, report) = match service.scan() {
+        Ok(scan) => scan,
+        Err(why) => {
+            eprintln!("{}: {}", fl!("error-service"), format_error(&why));
+            return false;
         }
-        _ => (),
     };
 
-    if let Some(ref client) = s76 {
-        s76_scan(client, &event_handler);
+    if scheduled && report.any_scanned() {
+        notification_checked();
+    }
+
+    // Every update is collected, so that the notification lists each device. Held devices, and
+    // system firmware which is scheduled to be installed on the next boot, are not upgradeable,
+    // so they are never notified about.
+    let mut updates = Vec::new();
+    let mut auto_updates = Vec::new();
+    // Updates which may be installed from the notification, as they do not require a reboot.
+    let mut installable = Vec::new();
+
+    for device in devices.into_iter().filter(|device| device.upgradeable) {
+        if device.auto_update_eligible(&config.auto_update) {
+            auto_updates.push(device);
+            continue;
+        }
+
+        if device.update_now_eligible() {
+            installable.push(Update::new(&device.info));
+        }
+
+        updates.push(Update::new(&device.info));
+    }
+
+    if !auto_updates.is_empty() {
+        match preflight_check() {
+            Ok(()) => notify_auto_updated(&service.install(&auto_updates, true)),
+            Err(why) => {
+                // Devices which could not be updated are notified about instead.
+                eprintln!("{}: {}", fl!("error-preflight"), why);
+                updates.extend(auto_updates.iter().map(|device| Update::new(&device.info)));
+                installable.extend(auto_updates.iter().map(|device| Update::new(&device.info)));
+            }
+        }
+    }
+
+    if updates.is_empty() {
+        return false;
     }
 
-    if let Some(ref client) = fwupd {
-        if let Err(why) = fwupd_updates(client) {
-            eprintln!("{}: {}", fl!("error-fwupd"), why);
+    // The same updates are not notified about again until the reminder interval has passed.
+    if !notification_required(config, &notified(&updates)) {
+        return true;
+    }
+
+    notify(Offer { updates, installable }, notifier.clone());
+    true
+}
+
+/// Performs the action which the user chose from the notification about an offer.
+fn respond(response: Response, offer: Offer) {
+    match response {
+        Response::Open => open_firmware_manager(),
+        Response::UpdateNow => update_now(&offer.installable),
+        Response::RemindLater => notification_snooze(),
+        Response::Skip => skip(&offer.updates),
+        Response::Dismissed => (),
+    }
+}
+
+/// The device ID and offered version of each update, as they are recorded once notified about.
+fn notified(updates: &[Update]) -> Vec<(&str, &str)> {
+    updates.iter().map(|update| (&*update.id, &*update.latest)).collect()
+}
+
+/// Summarizes the updates which were installed without asking the user.
+fn notify_auto_updated(installed: &[Installed]) {
+    notify_updated(&fl!("auto-update-summary"), installed);
+}
+
+/// Installs the updates which do not require a reboot, and reports their outcome.
+///
+/// The devices are found again by their IDs, as the firmware manager service may have exited
+/// since the updates were offered. Devices whose offered version is no longer the latest are not
+/// updated.
+fn update_now(updates: &[Update]) {
+    if let Err(why) = preflight_check() {
+        show(&fl!("update-now-failed"), &format_error(&why));
+        return;
+    }
+
+    let installed = Service::connect().and_then(|service| {
+        let devices: Vec<Device> = service
+            .devices()?
+            .into_iter()
+            .filter(|device| device.update_now_eligible())
+            .filter(|device| {
+                updates.iter().any(|update| {
+                    update.id == device.info.id
+                        && Some(&update.latest) == device.info.latest.as_ref()
+                })
+            })
+            .collect();
+
+        Ok(service.install(&devices, false))
+    });
+
+    match installed {
+        Ok(installed) => notify_updated(&fl!("update-now-summary"), &installed),
+        Err(why) => show(&fl!("update-now-failed"), &format_error(&why)),
+    }
+}
+
+/// Ignores the version offered for each device, so that later scans do not offer it again.
+fn skip(updates: &[Update]) {
+    let mut holds = match Holds::load() {
+        Ok(holds) => holds,
+        Err(why) => {
+            eprintln!("{}: {}", fl!("error-holds"), format_error(&why));
+            return;
         }
+    };
+
+    for update in updates {
+        holds.apply(&update.id, HoldChange::Ignore(update.latest.clone()));
+    }
 
-        fwupd_scan(client, &event_handler);
+    if let Err(why) = holds.save() {
+        eprintln!("{}: {}", fl!("error-holds"), format_error(&why));
     }
 }
 
-fn notify() {
-    Notification::new()
-        .summary(&fl!("summary"))
-        .body(&fl!("body"))
+/// Summarizes the outcome of each update which was installed from the notifier.
+fn notify_updated(summary: &str, installed: &[Installed]) {
+    let body = installed
+        .iter()
+        .map(|installed| {
+            let device = installed.name.as_ref();
+            let version = installed.version.as_ref();
+            match installed.failure {
+                Some(ref reason) => {
+                    let reason = reason.as_ref();
+                    fl!("auto-update-failed", device = device, version = version, reason = reason)
+                }
+                None => fl!("auto-update-updated", device = device, version = version),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    show(summary, &body);
+}
+
+/// Shows a notification which has no actions.
+fn show(summary: &str, body: &str) {
+    let result = Notification::new()
+        .summary(summary)
+        .body(body)
+        .icon("firmware-manager")
+        .appname("firmware-manager")
+        .show();
+
+    if let Err(why) = result {
+        eprintln!("failed to show desktop notification: {}", why);
+    }
+}
+
+/// Notifies that updates were found, listing each device with the most urgent updates first.
+///
+/// The urgency of the notification follows the highest urgency of the releases which were found.
+/// Installing the updates from the notification is only offered if some may be installed without
+/// a reboot.
+///
+/// The notification is shown from another thread, which waits for the user to respond, so that
+/// the daemon continues to check for updates meanwhile. The notification which the daemon showed
+/// before is closed, as this one replaces it. The updates are recorded as notified about once the
+/// notification is shown.
+fn notify(mut offer: Offer, notifier: Notifier) {
+    let updates = &mut offer.updates;
+    updates.sort_by(|a, b| b.urgency.cmp(&a.urgency).then_with(|| a.name.cmp(&b.name)));
+
+    let urgency = match updates.iter().map(|update| update.urgency).max() {
+        Some(Urgency::Critical) => NotificationUrgency::Critical,
+        Some(Urgency::Low) => NotificationUrgency::Low,
+        _ => NotificationUrgency::Normal,
+    };
+
+    let mut body = updates
+        .iter()
+        .map(|update| {
+            let device = update.name.as_ref();
+            let current = update.current.as_ref();
+            let latest = update.latest.as_ref();
+            if update.security {
+                fl!("update-security", device = device, current = current, latest = latest)
+            } else {
+                fl!("update", device = device, current = current, latest = latest)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    body.push_str("\n\n");
+    body.push_str(&fl!("body"));
+
+    let mut notification = Notification::new();
+    notification
+        .summary(&fl!("summary", count = updates.len()))
+        .body(&body)
         .icon("firmware-manager")
         .appname("firmware-manager")
-        .action("default", "default")
+        .urgency(urgency)
+        .action("default", "default");
+
+    if !offer.installable.is_empty() {
+        notification.action("update-now", &fl!("action-update-now"));
+    }
+
+    notification
+        .action("remind-later", &fl!("action-remind-later"))
+        .action("skip", &fl!("action-skip", count = updates.len()))
         // .hint(NotificationHint::Resident(true))
-        .timeout(Timeout::Never)
-        .show()
-        .expect("failed to show desktop notification")
-        .wait_for_action(|action| match action {
-            "default" => {
-                let (cmd, args): (&str, &[&str]) = if Path::new(GNOME_CONTROL_CENTER).exists() {
-                    ("gnome-control-center", &["firmware"])
-                } else {
-                    ("com.system76.FirmwareManager", &[])
-                };
-
-                let _ = Command::new(cmd).args(args).status();
+        .timeout(Timeout::Never);
+
+    if let Some(id) = notifier.shown.lock().expect("notification mutex poisoned").take() {
+        close_notification(id);
+    }
+
+    thread::spawn(move || {
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(why) => {
+                eprintln!("failed to show desktop notification: {}", why);
+                return;
+            }
+        };
+
+        let id = handle.id();
+        *notifier.shown.lock().expect("notification mutex poisoned") = Some(id);
+        notification_shown(&notified(&offer.updates));
+
+        let mut response = Response::Dismissed;
+        handle.wait_for_action(|action| {
+            response = match action {
+                "default" => Response::Open,
+                "update-now" => Response::UpdateNow,
+                "remind-later" => Response::RemindLater,
+                "skip" => Response::Skip,
+                _ => Response::Dismissed,
             }
-            "__closed" => (),
-            _ => (),
         });
 
-    exit(UPDATES_FOUND);
+        // The notification was closed, unless another has replaced it since.
+        let mut shown = notifier.shown.lock().expect("notification mutex poisoned");
+        if *shown == Some(id) {
+            *shown = None;
+        }
+
+        drop(shown);
+        let _ = notifier.wake.send(Wake::Response(response, offer));
+    });
+}
+
+/// Closes a notification which is shown. Closing it sends its waiting thread a dismissal.
+fn close_notification(id: u32) {
+    let result = dbus::blocking::Connection::new_session().and_then(|connection| {
+        connection
+            .with_proxy(NOTIFICATIONS_SERVICE, NOTIFICATIONS_PATH, Duration::from_secs(5))
+            .method_call(NOTIFICATIONS_SERVICE, "CloseNotification", (id,))
+    });
+
+    if let Err(why) = result {
+        eprintln!("failed to close desktop notification: {}", why);
+    }
+}
+
+/// Opens the Firmware panel of GNOME Settings if it is installed, or the firmware manager.
+fn open_firmware_manager() {
+    let (cmd, args): (&str, &[&str]) = if Path::new(GNOME_CONTROL_CENTER).exists() {
+        ("gnome-control-center", &["firmware"])
+    } else {
+        ("com.system76.FirmwareManager", &[])
+    };
+
+    let _ = Command::new(cmd).args(args).status();
 }
 
 fn translate() {
@@ -0,0 +1,31 @@
+use fomat_macros::fomat;
+use std::{env, fs::File, io::Write};
+
+fn dbus_service(name: &str, exec: &str) -> String {
+    fomat!(
+        "[D-BUS Service]\n"
+        "Name=" (name) "\n"
+        "Exec=" (exec) "\n"
+    )
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=APPID");
+    println!("cargo:rerun-if-env-changed=prefix");
+
+    // The library is also built for frontends which attach to the service, which do not install
+    // the service, and so do not set these.
+    let (appid, prefix) = match (env::var("APPID"), env::var("prefix")) {
+        (Ok(appid), Ok(prefix)) => (appid, prefix),
+        _ => return,
+    };
+
+    let service_path = ["../target/", &appid, ".dbus.service"].concat();
+    let exec = [&prefix, "/bin/firmware-manager-service"].concat();
+
+    // Frontends start the service on demand by making a request to its bus name.
+    File::create(service_path)
+        .expect("failed to create dbus service")
+        .write_all(dbus_service(&appid, &exec).as_bytes())
+        .expect("failed to write dbus service");
+}
@@ -0,0 +1,864 @@
+#![deny(missing_docs)]
+
+//! # Firmware Manager Service
+//!
+//! A session service which hosts the firmware manager core, so that every frontend in a session
+//! shares one event loop and one connection to each firmware service. Frontends attach to the
+//! service with a [`Client`], or with [`event_loop`], and are sent the same signals, so that an
+//! update started in one frontend is shown in the others.
+//!
+//! Devices are identified by a key which the service assigns when a device is first found, and
+//! which it keeps while later scans find the device again. Keys are never zero, so zero is sent
+//! in place of a device when a signal is not associated with one.
+//!
+//! The service exits once it has been idle for a while, and no frontend is attached to it.
+
+use dbus::{
+    blocking::{Connection, Proxy},
+    channel::MatchingReceiver,
+    message::{MatchRule, MessageType},
+    Message,
+};
+use firmware_manager::{
+    format_error, AutoUpdateConfig, Backend, Changelog, ChangelogEntry, FirmwareInfo, HistoryEntry,
+    InventoryEntry, PendingAction, RefreshDeferral, ReleaseTrust, ScanReport, ScanStatus,
+    SecurityAttribute, SecurityAttributeKind, SecurityReport, UpdateOutcome, Urgency,
+    Verification,
+};
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+/// The well-known bus name of the service on the session bus.
+///
+/// This is distinct from the application ID of the GTK application, which owns its own name.
+pub const SERVICE: &str = "com.system76.FirmwareManager.Service";
+
+/// The object path of the service.
+pub const PATH: &str = "/com/system76/FirmwareManager/Service";
+
+/// The interface of the service's methods and signals.
+pub const IFACE: &str = "com.system76.FirmwareManager.Service";
+
+/// The error returned by `Update` when the device has no update which may be installed.
+pub const NO_UPDATE_ERROR: &str = "com.system76.FirmwareManager.Service.Error.NoUpdate";
+
+/// The error returned by `Verify` when the device is not managed by fwupd.
+pub const NO_VERIFY_ERROR: &str = "com.system76.FirmwareManager.Service.Error.NoVerify";
+
+/// The error returned by `Update` and `Verify` when the service does not know the device, as the
+/// last scan did not find it again.
+pub const UNKNOWN_DEVICE_ERROR: &str = "com.system76.FirmwareManager.Service.Error.UnknownDevice";
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the thread which receives signals for `event_loop` waits on the bus before it checks
+/// whether the loop has returned.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The arguments which describe a device in the `DeviceFound` signal and `Devices` method.
+pub type DeviceArgs =
+    (u64, InfoArgs, String, String, bool, bool, String, Vec<ChangelogArgs>, Vec<String>);
+
+/// The arguments which describe the firmware of a device.
+pub type InfoArgs = (String, String, String, String, bool, u32);
+
+/// The arguments which describe a release in the changelog of a device.
+pub type ChangelogArgs = (String, u64, String, u32, Vec<String>, bool, bool, bool, String);
+
+/// The arguments which describe an update in the `History` method.
+pub type HistoryArgs = (u64, String, String, String, String, String, String, bool);
+
+/// The arguments which describe a host security attribute in the `Security` signal.
+type AttributeArgs = (String, String, u32, u32, bool, String);
+
+/// The arguments which describe the outcome of scanning a backend in `ScanningComplete`.
+type StatusArgs = (String, String, String, u32);
+
+/// What a device is waiting on, which `Update` performs in place of installing an update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pending {
+    /// fwupd must activate or unlock the device.
+    Action(PendingAction),
+    /// System firmware is scheduled to be installed on the next boot, and may be cancelled.
+    Scheduled,
+}
+
+impl Pending {
+    fn as_str(self) -> &'static str {
+        match self {
+            Pending::Action(PendingAction::Activate) => "activate",
+            Pending::Action(PendingAction::Unlock) => "unlock",
+            Pending::Scheduled => "scheduled",
+        }
+    }
+
+    fn from_str(pending: &str) -> Option<Self> {
+        match pending {
+            "activate" => Some(Pending::Action(PendingAction::Activate)),
+            "unlock" => Some(Pending::Action(PendingAction::Unlock)),
+            "scheduled" => Some(Pending::Scheduled),
+            _ => None,
+        }
+    }
+}
+
+/// The devices which the service has found, as returned by `Devices`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceList {
+    /// The devices found by the last scan, or the devices found so far by a scan in progress.
+    pub devices: Vec<Device>,
+    /// The service has scanned for devices, or is scanning for them.
+    pub scanned: bool,
+}
+
+/// A device which the service has discovered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    /// The key of the device, which is kept while scans find the device again.
+    pub device: u64,
+    /// The device and its current and latest firmware, with its changelog.
+    pub info: FirmwareInfo,
+    /// The vendor of the device.
+    pub vendor: Box<str>,
+    /// The firmware service which manages the device.
+    pub backend: Backend,
+    /// The latest version may be installed with `Update`.
+    pub upgradeable: bool,
+    /// The device is system firmware, which is installed by rebooting.
+    pub system: bool,
+    /// What the device is waiting on, if anything.
+    pub pending: Option<Pending>,
+    /// The GUIDs of a fwupd device, which the auto-update policy may allow.
+    pub guids: Vec<Box<str>>,
+}
+
+impl Device {
+    /// A device which is not upgradeable, and is not waiting on anything.
+    pub fn new(device: u64, info: FirmwareInfo, vendor: &str, backend: Backend) -> Self {
+        Self {
+            device,
+            info,
+            vendor: vendor.into(),
+            backend,
+            upgradeable: false,
+            system: false,
+            pending: None,
+            guids: Vec::new(),
+        }
+    }
+
+    /// The highest urgency of the releases which are newer than the installed firmware.
+    pub fn urgency(&self) -> Urgency { self.info.urgency() }
+
+    /// Checks if the device may be updated without opening a frontend.
+    ///
+    /// Only upgradeable fwupd devices which do not require a reboot or a pending action are
+    /// eligible.
+    pub fn update_now_eligible(&self) -> bool {
+        self.backend == Backend::Fwupd && self.upgradeable && !self.system && self.pending.is_none()
+    }
+
+    /// Checks if the device may be updated without asking the user.
+    ///
+    /// Only devices which may be updated without opening a frontend, and which the allowlist of
+    /// the policy permits, are eligible.
+    pub fn auto_update_eligible(&self, config: &AutoUpdateConfig) -> bool {
+        config.enabled && self.update_now_eligible() && config.allows(&self.vendor, &self.guids[..])
+    }
+
+    /// Converts the device into the arguments that it is sent on the bus with.
+    pub fn to_args(&self) -> DeviceArgs {
+        let info = &self.info;
+        (
+            self.device,
+            (
+                info.id.to_string(),
+                info.name.to_string(),
+                info.current.to_string(),
+                info.latest.as_deref().unwrap_or_default().to_owned(),
+                info.held,
+                info.install_duration,
+            ),
+            self.vendor.to_string(),
+            self.backend.service().to_owned(),
+            self.upgradeable,
+            self.system,
+            self.pending.map_or("", Pending::as_str).to_owned(),
+            info.changelog.entries.iter().map(changelog_to_args).collect(),
+            self.guids.iter().map(|guid| guid.to_string()).collect(),
+        )
+    }
+
+    /// Reads a device from the arguments that it was sent on the bus with.
+    pub fn from_args(args: DeviceArgs) -> Option<Self> {
+        let (device, info, vendor, backend, upgradeable, system, pending, changelog, guids) = args;
+        let (id, name, current, latest, held, install_duration) = info;
+
+        Some(Self {
+            device,
+            info: FirmwareInfo {
+                id: id.into(),
+                name: name.into(),
+                current: current.into(),
+                latest: if latest.is_empty() { None } else { Some(latest.into()) },
+                install_duration,
+                held,
+                changelog: Changelog {
+                    entries: changelog.into_iter().map(changelog_from_args).collect(),
+                },
+            },
+            vendor: vendor.into(),
+            backend: Backend::from_service(&backend)?,
+            upgradeable,
+            system,
+            pending: Pending::from_str(&pending),
+            guids: guids.into_iter().map(Box::from).collect(),
+        })
+    }
+}
+
+/// The device as it is written to an exported inventory.
+impl From<&Device> for InventoryEntry {
+    fn from(device: &Device) -> Self { Self::new(&device.info, &device.vendor, device.backend) }
+}
+
+fn changelog_to_args(entry: &ChangelogEntry) -> ChangelogArgs {
+    let trust = entry.trust.as_ref();
+    (
+        entry.version.to_string(),
+        entry.date.unwrap_or(0),
+        entry.description.as_deref().unwrap_or_default().to_owned(),
+        entry.urgency as u32,
+        entry.cves.iter().map(|cve| cve.to_string()).collect(),
+        trust.is_some(),
+        trust.map_or(false, |trust| trust.signed_payload),
+        trust.map_or(false, |trust| trust.signed_metadata),
+        trust.map_or("", |trust| &trust.remote).to_owned(),
+    )
+}
+
+fn changelog_from_args(args: ChangelogArgs) -> ChangelogEntry {
+    let (version, date, description, urgency, cves, trusted, payload, metadata, remote) = args;
+    ChangelogEntry {
+        version: version.into(),
+        date: if date == 0 { None } else { Some(date) },
+        description: if description.is_empty() { None } else { Some(description.into()) },
+        urgency: Urgency::from(urgency),
+        cves: cves.into_iter().map(Box::from).collect(),
+        trust: if trusted {
+            Some(ReleaseTrust {
+                signed_payload: payload,
+                signed_metadata: metadata,
+                remote: remote.into(),
+            })
+        } else {
+            None
+        },
+    }
+}
+
+/// Converts an entry of the update history into the arguments that it is sent on the bus with.
+pub fn history_to_args(entry: &HistoryEntry) -> HistoryArgs {
+    let (outcome, reason) = match entry.outcome {
+        UpdateOutcome::Updated => ("updated", ""),
+        UpdateOutcome::Scheduled => ("scheduled", ""),
+        UpdateOutcome::Failed(ref reason) => ("failed", &**reason),
+    };
+
+    (
+        entry.time,
+        entry.id.to_string(),
+        entry.name.to_string(),
+        entry.from.to_string(),
+        entry.to.to_string(),
+        outcome.to_owned(),
+        reason.to_owned(),
+        entry.unattended,
+    )
+}
+
+/// Reads an entry of the update history from the arguments that it was sent on the bus with.
+pub fn history_from_args(args: HistoryArgs) -> Option<HistoryEntry> {
+    let (time, id, name, from, to, outcome, reason, unattended) = args;
+    let outcome = match outcome.as_str() {
+        "updated" => UpdateOutcome::Updated,
+        "scheduled" => UpdateOutcome::Scheduled,
+        "failed" => UpdateOutcome::Failed(reason.into()),
+        _ => return None,
+    };
+
+    Some(HistoryEntry {
+        time,
+        id: id.into(),
+        name: name.into(),
+        from: from.into(),
+        to: to.into(),
+        outcome,
+        unattended,
+    })
+}
+
+/// The variants of `SecurityResult` are numbered as fwupd numbers them, so they are sent as such.
+fn attribute_to_args(attribute: &SecurityAttribute) -> AttributeArgs {
+    (
+        attribute.id.to_string(),
+        attribute.name.to_string(),
+        attribute.level,
+        attribute.result as u32,
+        attribute.passed,
+        attribute.uri.as_deref().unwrap_or_default().to_owned(),
+    )
+}
+
+fn attribute_from_args(args: AttributeArgs) -> SecurityAttribute {
+    let (id, name, level, result, passed, uri) = args;
+    SecurityAttribute {
+        kind: SecurityAttributeKind::from_appstream_id(&id),
+        id: id.into(),
+        name: name.into(),
+        level,
+        result: result.into(),
+        passed,
+        uri: if uri.is_empty() { None } else { Some(uri.into()) },
+    }
+}
+
+/// Signals that the service sends to every attached frontend.
+///
+/// These mirror the `FirmwareSignal`s of the core, with devices identified by their keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    /// A firmware service became available after it was lost or failed to connect.
+    BackendAvailable(Backend),
+    /// A firmware service left the system bus.
+    BackendLost(Backend),
+    /// A device has initiated the flashing process.
+    DeviceFlashing(u64),
+    /// A device was discovered.
+    DeviceFound(Box<Device>),
+    /// A device was updated.
+    DeviceUpdated(u64),
+    /// Signals that the device's firmware is being downloaded, with its size.
+    DownloadBegin(u64, u64),
+    /// Signals completion of a device's firmware download.
+    DownloadComplete(u64),
+    /// Progress updates on firmware downloads.
+    DownloadUpdate(u64, u64),
+    /// An error occurred, which may be associated with a device.
+    Error(Option<u64>, Box<str>),
+    /// Devices are being scanned.
+    Scanning,
+    /// Scanning has completed, with the outcome of scanning each firmware service.
+    ScanningComplete(ScanReport),
+    /// The host security attributes were fetched from fwupd.
+    Security(SecurityReport),
+    /// System firmware was scheduled for installation.
+    SystemScheduled,
+    /// The installed firmware of a device was verified, or recorded as trusted.
+    Verification(u64, Verification),
+}
+
+impl Signal {
+    /// Creates the message which sends this signal on the bus.
+    pub fn to_message(&self) -> Message {
+        let signal =
+            |member: &str| Message::new_signal(PATH, IFACE, member).expect("invalid signal member");
+
+        match *self {
+            Signal::BackendAvailable(backend) => {
+                signal("BackendAvailable").append1(backend.service())
+            }
+            Signal::BackendLost(backend) => signal("BackendLost").append1(backend.service()),
+            Signal::DeviceFlashing(device) => signal("DeviceFlashing").append1(device),
+            Signal::DeviceFound(ref device) => signal("DeviceFound").append1(device.to_args()),
+            Signal::DeviceUpdated(device) => signal("DeviceUpdated").append1(device),
+            Signal::DownloadBegin(device, size) => signal("DownloadBegin").append2(device, size),
+            Signal::DownloadComplete(device) => signal("DownloadComplete").append1(device),
+            Signal::DownloadUpdate(device, progress) => {
+                signal("DownloadUpdate").append2(device, progress)
+            }
+            Signal::Error(device, ref why) => signal("Error").append2(device.unwrap_or(0), &**why),
+            Signal::Scanning => signal("Scanning"),
+            Signal::ScanningComplete(ref report) => {
+                let statuses: Vec<StatusArgs> = report
+                    .iter()
+                    .map(|(backend, status)| {
+                        let (status, why, devices) = match *status {
+                            ScanStatus::Disabled => ("disabled", "", 0),
+                            ScanStatus::Unavailable => ("unavailable", "", 0),
+                            ScanStatus::Errored(ref why) => ("errored", &**why, 0),
+                            ScanStatus::Found(devices) => ("found", "", devices as u32),
+                            ScanStatus::Incomplete(devices, ref why) => {
+                                ("incomplete", &**why, devices as u32)
+                            }
+                        };
+
+                        (backend.service().to_owned(), status.to_owned(), why.to_owned(), devices)
+                    })
+                    .collect();
+
+                let deferred = match report.refresh_deferred {
+                    Some(RefreshDeferral::Metered) => "metered",
+                    Some(RefreshDeferral::LowBattery) => "low-battery",
+                    None => "",
+                };
+
+                signal("ScanningComplete").append2(statuses, deferred)
+            }
+            Signal::Security(ref report) => {
+                let attributes: Vec<AttributeArgs> =
+                    report.attributes.iter().map(attribute_to_args).collect();
+
+                signal("Security")
+                    .append2(report.host_security_id.as_deref().unwrap_or_default(), attributes)
+            }
+            Signal::SystemScheduled => signal("SystemScheduled"),
+            Signal::Verification(device, ref verification) => {
+                let (outcome, reason) = match *verification {
+                    Verification::Verified => ("verified", ""),
+                    Verification::Trusted => ("trusted", ""),
+                    Verification::Unsupported => ("unsupported", ""),
+                    Verification::Failed(ref reason) => ("failed", &**reason),
+                };
+
+                signal("Verification").append3(device, outcome, reason)
+            }
+        }
+    }
+
+    /// Reads a signal which was sent by the service, if the message is one.
+    pub fn from_message(message: &Message) -> Option<Self> {
+        if message.msg_type() != MessageType::Signal || &*message.interface()? != IFACE {
+            return None;
+        }
+
+        let backend = || message.read1::<&str>().ok().and_then(Backend::from_service);
+        let device = || message.read1::<u64>().ok();
+
+        let signal = match &*message.member()? {
+            "BackendAvailable" => Signal::BackendAvailable(backend()?),
+            "BackendLost" => Signal::BackendLost(backend()?),
+            "DeviceFlashing" => Signal::DeviceFlashing(device()?),
+            "DeviceFound" => {
+                Signal::DeviceFound(Box::new(Device::from_args(message.read1().ok()?)?))
+            }
+            "DeviceUpdated" => Signal::DeviceUpdated(device()?),
+            "DownloadBegin" => {
+                let (device, size) = message.read2().ok()?;
+                Signal::DownloadBegin(device, size)
+            }
+            "DownloadComplete" => Signal::DownloadComplete(device()?),
+            "DownloadUpdate" => {
+                let (device, progress) = message.read2().ok()?;
+                Signal::DownloadUpdate(device, progress)
+            }
+            "Error" => {
+                let (device, why): (u64, &str) = message.read2().ok()?;
+                Signal::Error(if device == 0 { None } else { Some(device) }, why.into())
+            }
+            "Scanning" => Signal::Scanning,
+            "ScanningComplete" => {
+                let (statuses, deferred): (Vec<StatusArgs>, &str) = message.read2().ok()?;
+
+                let refresh_deferred = match deferred {
+                    "metered" => Some(RefreshDeferral::Metered),
+                    "low-battery" => Some(RefreshDeferral::LowBattery),
+                    _ => None,
+                };
+
+                let mut report = ScanReport { refresh_deferred, ..ScanReport::default() };
+
+                for (backend, status, why, devices) in statuses {
+                    let status = match status.as_str() {
+                        "disabled" => ScanStatus::Disabled,
+                        "errored" => ScanStatus::Errored(why.into()),
+                        "found" => ScanStatus::Found(devices as usize),
+                        "incomplete" => ScanStatus::Incomplete(devices as usize, why.into()),
+                        _ => ScanStatus::Unavailable,
+                    };
+
+                    match Backend::from_service(&backend) {
+                        Some(Backend::Fwupd) => report.fwupd = status,
+                        Some(Backend::System76) => report.system76 = status,
+                        None => (),
+                    }
+                }
+
+                Signal::ScanningComplete(report)
+            }
+            "Security" => {
+                let (id, attributes): (String, Vec<AttributeArgs>) = message.read2().ok()?;
+                Signal::Security(SecurityReport {
+                    host_security_id: if id.is_empty() { None } else { Some(id.into()) },
+                    attributes: attributes.into_iter().map(attribute_from_args).collect(),
+                })
+            }
+            "SystemScheduled" => Signal::SystemScheduled,
+            "Verification" => {
+                let (device, outcome, reason): (u64, &str, &str) = message.read3().ok()?;
+                let verification = match outcome {
+                    "verified" => Verification::Verified,
+                    "trusted" => Verification::Trusted,
+                    "unsupported" => Verification::Unsupported,
+                    "failed" => Verification::Failed(reason.into()),
+                    _ => return None,
+                };
+
+                Signal::Verification(device, verification)
+            }
+            _ => return None,
+        };
+
+        Some(signal)
+    }
+}
+
+/// A connection to the service, through which a frontend makes requests and receives signals.
+pub struct Client {
+    connection: Connection,
+}
+
+impl Client {
+    /// Connects to the session bus.
+    ///
+    /// The service is started by the bus when a request is first made to it.
+    pub fn new() -> Result<Self, dbus::Error> {
+        Connection::new_session().map(|connection| Self { connection })
+    }
+
+    /// Asks the service to scan for devices.
+    ///
+    /// The devices are sent to every frontend with `Signal::DeviceFound`.
+    pub fn scan(&self) -> Result<(), dbus::Error> { self.proxy().method_call(IFACE, "Scan", ()) }
+
+    /// Asks the service to refresh the fwupd remote metadata, and then to scan for devices.
+    ///
+    /// The metadata is refreshed even if it is not due, or the network connection is metered.
+    pub fn refresh(&self) -> Result<(), dbus::Error> {
+        self.proxy().method_call(IFACE, "Refresh", ())
+    }
+
+    /// Asks the service to update the firmware of the device to its latest version.
+    ///
+    /// A device which is waiting on an action has that action performed instead, and system
+    /// firmware which is scheduled to be installed has its update cancelled. Devices with neither
+    /// are refused with `NO_UPDATE_ERROR`.
+    pub fn update(&self, device: u64) -> Result<(), dbus::Error> {
+        self.proxy().method_call(IFACE, "Update", (device,))
+    }
+
+    /// Asks the service to update a fwupd device without asking the user, which is recorded as
+    /// such in the update history.
+    ///
+    /// The frontend checks the auto-update policy and the preflight checks beforehand. Devices
+    /// which have no fwupd update are refused with `NO_UPDATE_ERROR`.
+    pub fn auto_update(&self, device: u64) -> Result<(), dbus::Error> {
+        self.proxy().method_call(IFACE, "AutoUpdate", (device,))
+    }
+
+    /// Asks the service to verify the firmware of a fwupd device, or to record it as trusted.
+    ///
+    /// The outcome is sent to every frontend with `Signal::Verification`. Devices which are not
+    /// managed by fwupd are refused with `NO_VERIFY_ERROR`.
+    pub fn verify(&self, device: u64, trust: bool) -> Result<(), dbus::Error> {
+        self.proxy().method_call(IFACE, "Verify", (device, trust))
+    }
+
+    /// Asks the service to fetch the host security attributes.
+    ///
+    /// The report is sent to every frontend with `Signal::Security`.
+    pub fn security(&self) -> Result<(), dbus::Error> {
+        self.proxy().method_call(IFACE, "Security", ())
+    }
+
+    /// The devices which the service has found, and whether it has scanned for them.
+    ///
+    /// While a scan is in progress, the remaining devices are sent with `Signal::DeviceFound`.
+    pub fn devices(&self) -> Result<DeviceList, dbus::Error> {
+        let (devices, scanned): (Vec<DeviceArgs>, bool) =
+            self.proxy().method_call(IFACE, "Devices", ())?;
+
+        let devices = devices.into_iter().filter_map(Device::from_args).collect();
+        Ok(DeviceList { devices, scanned })
+    }
+
+    /// Attaches to the service, which then keeps the keys of its devices until this connection
+    /// leaves the bus.
+    ///
+    /// Frontends which hold on to keys between requests should attach, as the service exits
+    /// while idle, and a new instance assigns new keys.
+    pub fn attach(&self) -> Result<(), dbus::Error> {
+        self.proxy().method_call(IFACE, "Attach", ())
+    }
+
+    /// The history of the firmware updates which have been attempted, from oldest to newest.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, dbus::Error> {
+        let (entries,): (Vec<HistoryArgs>,) = self.proxy().method_call(IFACE, "History", ())?;
+        Ok(entries.into_iter().filter_map(history_from_args).collect())
+    }
+
+    /// Makes a request of the service.
+    pub fn request(&self, request: Request) -> Result<(), dbus::Error> {
+        match request {
+            Request::Attach => self.attach(),
+            Request::Refresh => self.refresh(),
+            Request::Scan => self.scan(),
+            Request::Security => self.security(),
+            Request::Update(device) => self.update(device),
+            Request::Verify(device, trust) => self.verify(device, trust),
+            Request::Stop => Ok(()),
+        }
+    }
+
+    /// Calls the function with each signal that the service sends.
+    ///
+    /// Signals are received while `process` is being called.
+    pub fn watch<F: FnMut(Signal) + Send + 'static>(&self, mut func: F) -> Result<(), dbus::Error> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_path(PATH)
+            .with_interface(IFACE)
+            .static_clone();
+
+        self.connection.add_match_no_cb(&rule.match_str())?;
+        self.connection.start_receive(
+            rule,
+            Box::new(move |message, _| {
+                if let Some(signal) = Signal::from_message(&message) {
+                    func(signal);
+                }
+
+                true
+            }),
+        );
+
+        Ok(())
+    }
+
+    /// Waits for messages from the bus for up to the given duration, and dispatches them.
+    pub fn process(&self, timeout: Duration) -> Result<bool, dbus::Error> {
+        self.connection.process(timeout)
+    }
+
+    fn proxy(&self) -> Proxy<'_, &Connection> { self.connection.with_proxy(SERVICE, PATH, TIMEOUT) }
+}
+
+/// A request which a frontend makes of the service through `event_loop`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    /// Attach to the service, and receive the devices which it has already found. Devices are
+    /// only scanned for if the service has not found any yet.
+    Attach,
+    /// Refresh the fwupd remote metadata, and then scan for devices.
+    Refresh,
+    /// Fetch the host security attributes.
+    Security,
+    /// Scan for devices.
+    Scan,
+    /// Stop the event loop of the frontend. The service continues to run.
+    Stop,
+    /// Update a device, or perform the action that it is waiting on.
+    Update(u64),
+    /// Verify the firmware of a device, or record it as trusted.
+    Verify(u64, bool),
+}
+
+impl Request {
+    /// The device which the request is made for, if any.
+    pub fn device(self) -> Option<u64> {
+        match self {
+            Request::Update(device) | Request::Verify(device, _) => Some(device),
+            _ => None,
+        }
+    }
+}
+
+/// Attaches to the service in place of hosting the core, in the manner of the core's event loop.
+///
+/// Requests received from the frontend are made of the service, and the function is called with
+/// every signal which the service sends. A request which the service refused is reported as
+/// `Signal::Error`. Returns once `Request::Stop` is received, or the frontend hangs up.
+///
+/// The loop blocks until a request or a signal is received, as signals are received on a
+/// background thread, which sends them through the same channel as the requests.
+pub fn event_loop<F: FnMut(Signal)>(
+    requests: Receiver<Request>,
+    mut func: F,
+) -> Result<(), dbus::Error> {
+    let client = Client::new()?;
+
+    let (wake, events) = mpsc::channel();
+    let watching = Arc::new(());
+    watch_signals(Client::new()?, Arc::downgrade(&watching), wake.clone())?;
+    forward_requests(requests, wake);
+
+    for event in events {
+        match event {
+            Wake::Request(Request::Stop) => break,
+            Wake::Request(Request::Attach) => {
+                if let Err(why) = attach(&client, &mut func) {
+                    func(Signal::Error(None, format_error(&why).into()));
+                }
+            }
+            Wake::Request(request) => {
+                if let Err(why) = client.request(request) {
+                    func(Signal::Error(request.device(), format_error(&why).into()));
+                }
+            }
+            Wake::Signal(signal) => func(signal),
+            Wake::Failed(why) => return Err(why),
+        }
+    }
+
+    Ok(())
+}
+
+/// What wakes `event_loop`.
+enum Wake {
+    /// The frontend made a request.
+    Request(Request),
+    /// The service sent a signal.
+    Signal(Signal),
+    /// The connection which receives signals failed.
+    Failed(dbus::Error),
+}
+
+/// Receives the signals of the service on a background thread, and sends them to `event_loop`.
+///
+/// The thread exits once the loop has returned, and `alive` can no longer be upgraded.
+fn watch_signals(client: Client, alive: Weak<()>, wake: Sender<Wake>) -> Result<(), dbus::Error> {
+    let signals = wake.clone();
+    client.watch(move |signal| {
+        let _ = signals.send(Wake::Signal(signal));
+    })?;
+
+    thread::spawn(move || {
+        while alive.strong_count() > 0 {
+            if let Err(why) = client.process(WATCH_TIMEOUT) {
+                let _ = wake.send(Wake::Failed(why));
+                return;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Forwards the requests of a frontend to `event_loop`, on a background thread.
+///
+/// The loop is stopped once the frontend drops its sender.
+fn forward_requests(requests: Receiver<Request>, wake: Sender<Wake>) {
+    thread::spawn(move || {
+        for request in requests {
+            if wake.send(Wake::Request(request)).is_err() {
+                return;
+            }
+        }
+
+        let _ = wake.send(Wake::Request(Request::Stop));
+    });
+}
+
+/// Attaches to the service, and sends the devices which it has already found as
+/// `Signal::DeviceFound`, scanning only if the service has not scanned yet.
+///
+/// A service which found no devices is asked to scan again, as the frontend is only told why with
+/// `Signal::ScanningComplete`. Signals which the service sends while the devices are requested
+/// are received afterwards, so they may describe devices which were already sent.
+fn attach<F: FnMut(Signal)>(client: &Client, func: &mut F) -> Result<(), dbus::Error> {
+    client.attach()?;
+
+    let list = client.devices()?;
+    let found = !list.devices.is_empty();
+    for device in list.devices {
+        func(Signal::DeviceFound(Box::new(device)));
+    }
+
+    if !list.scanned || !found {
+        client.scan()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_are_read_as_sent() {
+        let changelog = Changelog {
+            entries: vec![
+                ChangelogEntry {
+                    version: "1.1".into(),
+                    date: Some(1_600_000_000),
+                    description: Some("<p>Fixes a hang on resume.</p>".into()),
+                    urgency: Urgency::High,
+                    cves: vec!["CVE-2021-0001".into()],
+                    trust: Some(ReleaseTrust {
+                        signed_payload: true,
+                        signed_metadata: false,
+                        remote: "lvfs".into(),
+                    }),
+                },
+                ChangelogEntry { version: "1.0".into(), ..ChangelogEntry::default() },
+            ],
+        };
+
+        let info = FirmwareInfo {
+            id: "system76-system".into(),
+            name: "System76 Thelio".into(),
+            current: "1.0".into(),
+            latest: Some("1.1".into()),
+            install_duration: 1,
+            held: false,
+            changelog,
+        };
+
+        let mut device = Device::new(1 << 32 | 3, info, "System76", Backend::System76);
+        device.upgradeable = true;
+        device.system = true;
+        device.pending = Some(Pending::Scheduled);
+        device.guids = vec!["9d131a0c-a606-580f-8eda-80587250b8d6".into()];
+
+        let signals = vec![
+            Signal::Scanning,
+            Signal::DeviceFound(Box::new(device)),
+            Signal::DownloadUpdate(7, 1024),
+            Signal::Error(None, "failed to connect".into()),
+            Signal::ScanningComplete(ScanReport {
+                fwupd: ScanStatus::Errored("timed out".into()),
+                system76: ScanStatus::Found(2),
+                refresh_deferred: None,
+            }),
+            Signal::ScanningComplete(ScanReport {
+                fwupd: ScanStatus::Found(3),
+                system76: ScanStatus::Incomplete(1, "failed to list boards".into()),
+                refresh_deferred: Some(RefreshDeferral::Metered),
+            }),
+            Signal::Security(SecurityReport {
+                host_security_id: Some("HSI:1".into()),
+                attributes: vec![SecurityAttribute {
+                    id: "org.fwupd.hsi.Uefi.SecureBoot".into(),
+                    kind: SecurityAttributeKind::SecureBoot,
+                    name: "UEFI secure boot".into(),
+                    level: 1,
+                    result: 1.into(),
+                    passed: true,
+                    uri: None,
+                }],
+            }),
+            Signal::Verification(7, Verification::Failed("checksum mismatch".into())),
+        ];
+
+        for signal in signals {
+            assert_eq!(Signal::from_message(&signal.to_message()), Some(signal));
+        }
+    }
+}
@@ -0,0 +1,442 @@
+//! Hosts the firmware manager core on the session bus.
+
+use dbus::{
+    blocking::{stdintf::org_freedesktop_dbus::RequestNameReply, SyncConnection},
+    channel::MatchingReceiver,
+    message::MatchRule,
+    Message, MethodErr,
+};
+use firmware_manager::{
+    event_loop, format_error, history, Entities, Entity, FirmwareEvent, FirmwareInfo,
+    FirmwareSignal, FoundDevice, PendingUpdate, UpdateStatus,
+};
+use firmware_manager_service::{
+    history_to_args, Device, DeviceArgs, HistoryArgs, Pending, Signal, IFACE, NO_UPDATE_ERROR,
+    NO_VERIFY_ERROR, SERVICE, UNKNOWN_DEVICE_ERROR,
+};
+use slotmap::{KeyData, SecondaryMap, SparseSecondaryMap};
+use std::{
+    collections::{HashMap, HashSet},
+    process::exit,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The name and interface of the bus daemon, which announces when frontends leave the bus.
+const DBUS_IFACE: &str = "org.freedesktop.DBus";
+
+/// How long the service waits without a request or a signal before it exits, once no frontend is
+/// attached.
+///
+/// The bus starts the service again on the next request.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+fn main() {
+    if let Err(why) = run() {
+        eprintln!("firmware manager service: {}", format_error(&why));
+        exit(1);
+    }
+}
+
+fn run() -> Result<(), dbus::Error> {
+    let connection = Arc::new(SyncConnection::new_session()?);
+
+    // Another instance owns the name, or will own it once it is released, so this one exits.
+    if connection.request_name(SERVICE, false, true, true)? != RequestNameReply::PrimaryOwner {
+        return Err(dbus::Error::new_custom(
+            "org.freedesktop.DBus.Error.AddressInUse",
+            &format!("{} is already owned by another process", SERVICE),
+        ));
+    }
+
+    let (events, receiver) = mpsc::channel();
+    let (sender, signals) = mpsc::channel();
+
+    let background = thread::spawn(move || {
+        event_loop(receiver, |signal| {
+            let _ = sender.send(signal);
+        })
+    });
+
+    let service = Arc::new(Mutex::new(Service::new(events)));
+
+    // Method calls are answered as they are received.
+    connection.start_receive(MatchRule::new_method_call(), {
+        let service = service.clone();
+        Box::new(move |call, connection| {
+            if let Some(reply) = lock(&service).call(&call) {
+                let _ = connection.channel().send(reply);
+            }
+
+            true
+        })
+    });
+
+    // Attached frontends are detached once they leave the bus.
+    let rule = MatchRule::new_signal(DBUS_IFACE, "NameOwnerChanged").with_sender(DBUS_IFACE);
+    connection.add_match(rule, {
+        let service = service.clone();
+        move |(name, _, owner): (String, String, String), _, _| {
+            if owner.is_empty() {
+                lock(&service).detach(&name);
+            }
+
+            true
+        }
+    })?;
+
+    // Signals of the core are forwarded as they are sent, until the event loop stops.
+    let forward = thread::spawn({
+        let connection = connection.clone();
+        let service = service.clone();
+        move || {
+            for signal in signals {
+                let mut service = lock(&service);
+                let _ = connection.channel().send(service.signal(signal).to_message());
+            }
+        }
+    });
+
+    loop {
+        let remaining = lock(&service).idle_remaining();
+        match remaining {
+            Some(timeout) => {
+                connection.process(timeout)?;
+            }
+            None => break,
+        }
+    }
+
+    // Requests made from here on start a new instance.
+    let _ = connection.release_name(SERVICE);
+    lock(&service).stop();
+
+    let _ = background.join();
+    let _ = forward.join();
+    Ok(())
+}
+
+/// Locks the state of the service, which is shared by the bus and the forwarding thread.
+fn lock(service: &Mutex<Service>) -> MutexGuard<Service> {
+    service.lock().expect("service state mutex poisoned")
+}
+
+/// Whether devices have been scanned for, which frontends check before asking for a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    NotScanned,
+    Scanning,
+    Scanned,
+}
+
+/// The devices which were found, which requests of frontends refer to.
+///
+/// A device keeps its key when it is found again by a later scan, so that requests which a
+/// frontend makes while another frontend scans still refer to it. Devices which a scan does not
+/// find again are removed once it completes.
+struct Service {
+    entities: Entities,
+    devices: SecondaryMap<Entity, Device>,
+    found: SecondaryMap<Entity, FoundDevice>,
+    /// The entity of each device, by its ID and name.
+    ///
+    /// Thelio I/O boards share an ID, so devices are also told apart by their name.
+    ids: HashMap<(Box<str>, Box<str>), Entity>,
+    /// The devices which were found by the current, or the last, scan.
+    scanned: SparseSecondaryMap<Entity, ()>,
+    /// Devices whose firmware is being downloaded or flashed.
+    flashing: SparseSecondaryMap<Entity, ()>,
+    /// The unique bus names of the frontends which hold on to the keys of the devices.
+    attached: HashSet<String>,
+    scan: ScanState,
+    /// When the last request was made, or signal was sent.
+    active_at: Instant,
+    events: Sender<FirmwareEvent>,
+}
+
+impl Service {
+    fn new(events: Sender<FirmwareEvent>) -> Self {
+        Self {
+            entities: Entities::default(),
+            devices: SecondaryMap::new(),
+            found: SecondaryMap::new(),
+            ids: HashMap::new(),
+            scanned: SparseSecondaryMap::new(),
+            flashing: SparseSecondaryMap::new(),
+            attached: HashSet::new(),
+            scan: ScanState::NotScanned,
+            active_at: Instant::now(),
+            events,
+        }
+    }
+
+    /// How long the service may wait before it is idle, or `None` once it is.
+    ///
+    /// The service is never idle while a frontend is attached, while it scans, or while a device
+    /// is being flashed.
+    fn idle_remaining(&self) -> Option<Duration> {
+        let busy = self.scan == ScanState::Scanning || !self.flashing.is_empty();
+        if busy || !self.attached.is_empty() {
+            return Some(IDLE_TIMEOUT);
+        }
+
+        IDLE_TIMEOUT
+            .checked_sub(self.active_at.elapsed())
+            .filter(|remaining| *remaining > Duration::from_secs(0))
+    }
+
+    /// Forgets a frontend which left the bus.
+    fn detach(&mut self, name: &str) {
+        if self.attached.remove(name) {
+            self.active_at = Instant::now();
+        }
+    }
+
+    /// Stops the event loop of the core.
+    fn stop(&self) {
+        let _ = self.events.send(FirmwareEvent::Stop);
+    }
+
+    /// Answers a method call of a frontend.
+    fn call(&mut self, call: &Message) -> Option<Message> {
+        if call.interface().map_or(true, |iface| &*iface != IFACE) {
+            return Some(MethodErr::no_interface(&call.interface()?).to_message(call));
+        }
+
+        self.active_at = Instant::now();
+
+        let reply = match &*call.member()? {
+            "Attach" => {
+                if let Some(sender) = call.sender() {
+                    self.attached.insert(sender.to_string());
+                }
+
+                call.method_return()
+            }
+            "AutoUpdate" => match call.read1::<u64>() {
+                Ok(device) => match self.device(device) {
+                    Some(entity) => match self.found[entity].update {
+                        Some(PendingUpdate::Fwupd(ref device, ref release)) => {
+                            let event = FirmwareEvent::FwupdUnattended(
+                                entity,
+                                device.clone(),
+                                release.clone(),
+                            );
+
+                            let _ = self.events.send(event);
+                            call.method_return()
+                        }
+                        _ => MethodErr::from((NO_UPDATE_ERROR, "the device has no fwupd update"))
+                            .to_message(call),
+                    },
+                    None => unknown_device(call),
+                },
+                Err(why) => MethodErr::from(why).to_message(call),
+            },
+            // The devices of a scan which is in progress are those found so far, which are
+            // followed by the remaining `DeviceFound` signals.
+            "Devices" => {
+                let devices: Vec<DeviceArgs> = self
+                    .devices
+                    .iter()
+                    .filter(|(entity, _)| self.scanned.contains_key(*entity))
+                    .map(|(_, device)| device.to_args())
+                    .collect();
+
+                call.method_return().append2(devices, self.scan != ScanState::NotScanned)
+            }
+            "History" => match history() {
+                Ok(entries) => {
+                    let entries: Vec<HistoryArgs> = entries.iter().map(history_to_args).collect();
+                    call.method_return().append1(entries)
+                }
+                Err(why) => MethodErr::failed(&format_error(&why)).to_message(call),
+            },
+            "Refresh" => {
+                let _ = self.events.send(FirmwareEvent::Refresh);
+                call.method_return()
+            }
+            "Scan" => {
+                let _ = self.events.send(FirmwareEvent::Scan);
+                call.method_return()
+            }
+            "Security" => {
+                let _ = self.events.send(FirmwareEvent::Security);
+                call.method_return()
+            }
+            "Update" => match call.read1::<u64>() {
+                Ok(device) => match self.device(device) {
+                    Some(entity) => match self.found[entity].event(entity) {
+                        Some(event) => {
+                            let _ = self.events.send(event);
+                            call.method_return()
+                        }
+                        None => MethodErr::from((NO_UPDATE_ERROR, "the device has no update"))
+                            .to_message(call),
+                    },
+                    None => unknown_device(call),
+                },
+                Err(why) => MethodErr::from(why).to_message(call),
+            },
+            "Verify" => match call.read2::<u64, bool>() {
+                Ok((device, trust)) => match self.device(device) {
+                    Some(entity) => match self.verify(entity, trust) {
+                        Some(event) => {
+                            let _ = self.events.send(event);
+                            call.method_return()
+                        }
+                        None => {
+                            MethodErr::from((NO_VERIFY_ERROR, "the device is not managed by fwupd"))
+                                .to_message(call)
+                        }
+                    },
+                    None => unknown_device(call),
+                },
+                Err(why) => MethodErr::from(why).to_message(call),
+            },
+            member => MethodErr::no_method(member).to_message(call),
+        };
+
+        Some(reply)
+    }
+
+    /// The entity of the device with the given key, if the device is still known.
+    fn device(&self, device: u64) -> Option<Entity> {
+        let entity = Entity::from(KeyData::from_ffi(device));
+        if self.found.contains_key(entity) {
+            Some(entity)
+        } else {
+            None
+        }
+    }
+
+    /// The event which verifies the firmware of a fwupd device, or records it as trusted.
+    fn verify(&self, entity: Entity, trust: bool) -> Option<FirmwareEvent> {
+        let device = self.found.get(entity)?.fwupd.clone()?;
+
+        Some(if trust {
+            FirmwareEvent::VerifyUpdate(entity, device)
+        } else {
+            FirmwareEvent::Verify(entity, device)
+        })
+    }
+
+    /// Converts a signal of the core into the signal of the service, recording the devices found.
+    fn signal(&mut self, signal: FirmwareSignal) -> Signal {
+        self.active_at = Instant::now();
+
+        match signal {
+            FirmwareSignal::BackendAvailable(backend) => Signal::BackendAvailable(backend),
+            FirmwareSignal::BackendLost(backend) => Signal::BackendLost(backend),
+            FirmwareSignal::DeviceFlashing(entity) => {
+                self.flashing.insert(entity, ());
+                Signal::DeviceFlashing(key(entity))
+            }
+            FirmwareSignal::DeviceUpdated(entity) => {
+                self.flashing.remove(entity);
+                Signal::DeviceUpdated(key(entity))
+            }
+            FirmwareSignal::DownloadBegin(entity, size) => {
+                self.flashing.insert(entity, ());
+                Signal::DownloadBegin(key(entity), size)
+            }
+            FirmwareSignal::DownloadComplete(entity) => Signal::DownloadComplete(key(entity)),
+            FirmwareSignal::DownloadUpdate(entity, progress) => {
+                Signal::DownloadUpdate(key(entity), progress as u64)
+            }
+            FirmwareSignal::Error(entity, why) => {
+                if let Some(entity) = entity {
+                    self.flashing.remove(entity);
+                }
+
+                Signal::Error(entity.map(key), format_error(&why).into())
+            }
+            FirmwareSignal::Scanning => {
+                self.scan = ScanState::Scanning;
+                self.scanned.clear();
+                Signal::Scanning
+            }
+            FirmwareSignal::ScanningComplete(report) => {
+                self.scan = ScanState::Scanned;
+                self.remove_unscanned();
+                Signal::ScanningComplete(report)
+            }
+            FirmwareSignal::Security(report) => Signal::Security(report),
+            FirmwareSignal::SystemScheduled => Signal::SystemScheduled,
+            FirmwareSignal::Verification(entity, verification) => {
+                Signal::Verification(key(entity), verification)
+            }
+            signal => match FoundDevice::from_signal(signal) {
+                Some(found) => Signal::DeviceFound(Box::new(self.found(found))),
+                None => unreachable!("every other signal describes a device"),
+            },
+        }
+    }
+
+    /// Records a device which was found, along with the update which may be performed.
+    ///
+    /// A device which was found before keeps its entity.
+    fn found(&mut self, found: FoundDevice) -> Device {
+        let entity = match self.ids.get(&identity(&found.info)) {
+            Some(&entity) => entity,
+            None => {
+                let entity = self.entities.create();
+                self.ids.insert(identity(&found.info), entity);
+                entity
+            }
+        };
+
+        let info = found.info.clone();
+        let mut device = Device::new(key(entity), info, found.vendor(), found.backend());
+        device.upgradeable = found.upgradeable();
+        device.system = found.system;
+        device.guids = found
+            .fwupd
+            .iter()
+            .flat_map(|device| device.guid.iter())
+            .map(|guid| guid.to_string().into())
+            .collect();
+        device.pending = match found.status {
+            UpdateStatus::Pending(action) => Some(Pending::Action(action)),
+            UpdateStatus::Scheduled => Some(Pending::Scheduled),
+            _ => None,
+        };
+
+        self.scanned.insert(entity, ());
+        self.found.insert(entity, found);
+        self.devices.insert(entity, device.clone());
+        device
+    }
+
+    /// Removes the devices which the last scan did not find again.
+    fn remove_unscanned(&mut self) {
+        let scanned = &self.scanned;
+        let removed: Vec<Entity> =
+            self.entities.keys().filter(|&entity| !scanned.contains_key(entity)).collect();
+
+        for entity in removed {
+            self.entities.entities.remove(entity);
+            self.devices.remove(entity);
+            self.found.remove(entity);
+        }
+
+        let entities = &self.entities;
+        self.ids.retain(|_, entity| entities.contains_key(*entity));
+    }
+}
+
+/// What identifies a device across scans.
+fn identity(info: &FirmwareInfo) -> (Box<str>, Box<str>) { (info.id.clone(), info.name.clone()) }
+
+/// The reply to a request for a device which the service does not know, such as one which was
+/// not found again by the last scan.
+fn unknown_device(call: &Message) -> Message {
+    MethodErr::from((UNKNOWN_DEVICE_ERROR, "the device is not known; scan again")).to_message(call)
+}
+
+/// The key of an entity, as it is known to frontends.
+fn key(entity: Entity) -> u64 { KeyData::from(entity).as_ffi() }
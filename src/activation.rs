@@ -0,0 +1,69 @@
+//! Devices which must be activated or unlocked before their firmware takes effect or may be
+//! updated.
+//!
+//! fwupd flags Thunderbolt controllers with firmware that is staged but not yet running as
+//! needing activation, and devices such as docks that refuse updates as locked.
+
+use crate::fwupd_device_call;
+use fwupd_dbus::Device as FwupdDevice;
+use std::time::Duration;
+
+/// Activation restarts the device with its new firmware, which may take some time.
+const ACTIVATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The device flag which fwupd sets on devices which are locked.
+const FLAG_LOCKED: u64 = 1 << 4;
+
+/// The device flag which fwupd sets on devices whose new firmware is waiting to be activated.
+const FLAG_NEEDS_ACTIVATION: u64 = 1 << 20;
+
+/// An action which a device is waiting on before it can be used or updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PendingAction {
+    /// Firmware was installed, but the device must be activated for it to take effect.
+    Activate,
+    /// The device is locked, and must be unlocked before it can be updated.
+    Unlock,
+}
+
+impl PendingAction {
+    fn from_flags(flags: u64) -> Option<Self> {
+        if flags & FLAG_NEEDS_ACTIVATION != 0 {
+            Some(PendingAction::Activate)
+        } else if flags & FLAG_LOCKED != 0 {
+            Some(PendingAction::Unlock)
+        } else {
+            None
+        }
+    }
+}
+
+/// The action which a fwupd device is waiting on, if any.
+pub fn fwupd_pending_action(device: &FwupdDevice) -> Option<PendingAction> {
+    PendingAction::from_flags(device.flags.bits())
+}
+
+/// Activates the firmware which was installed to a device.
+pub fn fwupd_activate(device: &FwupdDevice) -> Result<(), dbus::Error> {
+    fwupd_device_call(device, "Activate", ACTIVATION_TIMEOUT)
+}
+
+/// Unlocks a device so that its firmware may be updated.
+pub fn fwupd_unlock(device: &FwupdDevice) -> Result<(), dbus::Error> {
+    fwupd_device_call(device, "Unlock", ACTIVATION_TIMEOUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_action_from_flags() {
+        assert_eq!(PendingAction::from_flags(1 << 1 | 1 << 5), None);
+        assert_eq!(PendingAction::from_flags(1 << 4 | 1 << 5), Some(PendingAction::Unlock));
+        assert_eq!(PendingAction::from_flags(1 << 20), Some(PendingAction::Activate));
+        assert_eq!(PendingAction::from_flags(1 << 4 | 1 << 20), Some(PendingAction::Activate));
+    }
+}
@@ -0,0 +1,102 @@
+//! The urgency, security advisories, and trust of fwupd releases.
+//!
+//! `fwupd_dbus::Release` does not carry these, so the releases of a device are requested from
+//! fwupd again to read them.
+
+use crate::{
+    services::{FWUPD_IFACE, FWUPD_PATH, FWUPD_SERVICE},
+    ReleaseTrust, Urgency,
+};
+use dbus::{
+    arg::{prop_cast, PropMap, RefArg},
+    blocking::Connection,
+};
+use fwupd_dbus::Device as FwupdDevice;
+use std::time::Duration;
+
+/// fwupd answers from its metadata cache, so this should not take long.
+const ADVISORIES_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The trust flag which fwupd sets when the payload of a release is signed.
+const TRUST_PAYLOAD: u64 = 1 << 0;
+
+/// The trust flag which fwupd sets when the metadata of a release is signed.
+const TRUST_METADATA: u64 = 1 << 1;
+
+/// The urgency of a release, the security issues which it fixes, and how far it is trusted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Advisory {
+    pub version: Box<str>,
+    pub urgency: Urgency,
+    pub cves: Vec<Box<str>>,
+    pub trust: ReleaseTrust,
+}
+
+impl Advisory {
+    fn from_props(props: &PropMap) -> Option<Self> {
+        let version = prop_cast::<String>(props, "Version")?;
+        let trust_flags = prop_cast::<u64>(props, "TrustFlags").copied().unwrap_or(0);
+
+        let cves = props
+            .get("Issues")
+            .and_then(|issues| issues.0.as_iter())
+            .into_iter()
+            .flatten()
+            .filter_map(|issue| issue.as_str().map(Box::from))
+            .collect();
+
+        Some(Self {
+            version: Box::from(&**version),
+            urgency: prop_cast::<u32>(props, "Urgency").copied().unwrap_or(0).into(),
+            cves,
+            trust: ReleaseTrust {
+                signed_payload: trust_flags & TRUST_PAYLOAD != 0,
+                signed_metadata: trust_flags & TRUST_METADATA != 0,
+                remote: prop_cast::<String>(props, "RemoteId")
+                    .map(|id| Box::from(id.as_str()))
+                    .unwrap_or_default(),
+            },
+        })
+    }
+}
+
+/// Fetches the urgency and security issues of each release of a device.
+pub(crate) fn fwupd_advisories(
+    connection: &Connection,
+    device: &FwupdDevice,
+) -> Result<Vec<Advisory>, dbus::Error> {
+    let (releases,): (Vec<PropMap>,) = connection
+        .with_proxy(FWUPD_SERVICE, FWUPD_PATH, ADVISORIES_TIMEOUT)
+        .method_call(FWUPD_IFACE, "GetReleases", (&**device.device_id,))?;
+
+    Ok(releases.iter().filter_map(Advisory::from_props).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dbus::arg::Variant;
+
+    #[test]
+    fn advisory_from_props() {
+        let mut props = PropMap::new();
+        let mut insert = |key: &str, value: Box<dyn RefArg>| {
+            props.insert(key.into(), Variant(value));
+        };
+
+        insert("Version", Box::new(String::from("1.0.4")));
+        insert("Urgency", Box::new(4u32));
+        insert("Issues", Box::new(vec![String::from("CVE-2021-0001")]));
+        insert("TrustFlags", Box::new(2u64));
+        insert("RemoteId", Box::new(String::from("lvfs-testing")));
+
+        let advisory = Advisory::from_props(&props).unwrap();
+        assert_eq!(&*advisory.version, "1.0.4");
+        assert_eq!(advisory.urgency, Urgency::Critical);
+        assert_eq!(advisory.cves, vec![Box::from("CVE-2021-0001")]);
+        assert!(!advisory.trust.signed_payload && advisory.trust.signed_metadata);
+        assert!(advisory.trust.is_testing() && advisory.trust.is_unsigned());
+
+        assert_eq!(Advisory::from_props(&PropMap::new()), None);
+    }
+}
@@ -19,3 +19,11 @@ pub fn cache<P: AsRef<Path>>(file: P) -> Result<PathBuf, Error> {
         .place_cache_file(file)
         .map_err(Error::Place)
 }
+
+/// Fetches the XDG cache directory for com.system76.FirmwareManager, creating it if necessary.
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    xdg::BaseDirectories::with_prefix("com.system76.FirmwareManager")
+        .map_err(Error::BaseDirectory)?
+        .create_cache_directory("")
+        .map_err(Error::Place)
+}
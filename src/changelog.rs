@@ -0,0 +1,360 @@
+//! A changelog model which is shared by every firmware service.
+//!
+//! Each firmware service describes its releases differently, so they are converted into a
+//! `Changelog` that frontends may render without knowing which service a device belongs to.
+
+use crate::advisories::Advisory;
+use fwupd_dbus::Release as FwupdRelease;
+use std::cmp::Ordering;
+use system76_firmware_daemon::Changelog as System76Changelog;
+
+/// How urgently a release should be installed, as rated by its vendor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Urgency {
+    /// The vendor did not rate the release.
+    Unknown,
+    /// The release may be installed at any time.
+    Low,
+    /// The release should be installed when convenient.
+    Medium,
+    /// The release should be installed soon.
+    High,
+    /// The release should be installed immediately.
+    Critical,
+}
+
+impl Default for Urgency {
+    fn default() -> Self { Urgency::Unknown }
+}
+
+/// Converts the urgency of a release, as numbered by fwupd.
+impl From<u32> for Urgency {
+    fn from(urgency: u32) -> Self {
+        match urgency {
+            1 => Urgency::Low,
+            2 => Urgency::Medium,
+            3 => Urgency::High,
+            4 => Urgency::Critical,
+            _ => Urgency::Unknown,
+        }
+    }
+}
+
+/// The fwupd remote which provides the stable releases of the LVFS.
+const LVFS_REMOTE: &str = "lvfs";
+
+/// The fwupd remote which provides releases of the LVFS which are still being tested.
+const LVFS_TESTING_REMOTE: &str = "lvfs-testing";
+
+/// How far a release may be trusted, as determined by fwupd.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReleaseTrust {
+    /// The firmware payload is signed.
+    pub signed_payload: bool,
+    /// The metadata which describes the release is signed.
+    pub signed_metadata: bool,
+    /// The ID of the remote which provides the release, such as `lvfs`.
+    pub remote: Box<str>,
+}
+
+impl ReleaseTrust {
+    /// The release is provided by the stable remote of the LVFS.
+    ///
+    /// Releases of other remotes, such as those which vendors install locally, may be just as
+    /// trustworthy, so this only identifies the remote.
+    pub fn is_lvfs(&self) -> bool { &*self.remote == LVFS_REMOTE }
+
+    /// The release is provided by the testing remote of the LVFS.
+    pub fn is_testing(&self) -> bool { &*self.remote == LVFS_TESTING_REMOTE }
+
+    /// The firmware payload of the release is not signed, even if its metadata is.
+    pub fn is_unsigned(&self) -> bool { !self.signed_payload }
+
+    /// The statements which describe how far the release may be trusted, in the order that
+    /// frontends show them.
+    pub fn details(&self) -> Vec<TrustDetail<'_>> {
+        let mut details = vec![
+            if self.signed_payload {
+                TrustDetail::PayloadSigned
+            } else {
+                TrustDetail::PayloadUnsigned
+            },
+            if self.signed_metadata {
+                TrustDetail::MetadataSigned
+            } else {
+                TrustDetail::MetadataUnsigned
+            },
+        ];
+
+        if self.is_lvfs() {
+            details.push(TrustDetail::LvfsRemote);
+        } else if !self.remote.is_empty() {
+            details.push(TrustDetail::Remote(&self.remote));
+        }
+
+        details
+    }
+
+    /// The warning which should be shown before the release is installed, if any.
+    pub fn warning(&self) -> Option<TrustWarning> {
+        if self.is_unsigned() {
+            Some(TrustWarning::Unsigned)
+        } else if self.is_testing() {
+            Some(TrustWarning::Testing)
+        } else {
+            None
+        }
+    }
+}
+
+/// A statement about how far a release may be trusted, which frontends describe to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustDetail<'a> {
+    /// The firmware payload is signed.
+    PayloadSigned,
+    /// The firmware payload is not signed.
+    PayloadUnsigned,
+    /// The metadata which describes the release is signed.
+    MetadataSigned,
+    /// The metadata which describes the release is not signed.
+    MetadataUnsigned,
+    /// The release is provided by the stable remote of the LVFS.
+    LvfsRemote,
+    /// The release is provided by the remote with the given ID.
+    Remote(&'a str),
+}
+
+/// A reason to be cautious about installing a release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustWarning {
+    /// The firmware payload is not signed.
+    Unsigned,
+    /// The release is still being tested.
+    Testing,
+}
+
+/// A release of firmware, as described by a changelog.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChangelogEntry {
+    /// The version of the release.
+    pub version: Box<str>,
+    /// When the release was published, in seconds since the Unix epoch, if known.
+    pub date: Option<u64>,
+    /// A description of the changes in the release, which may contain markup.
+    pub description: Option<Box<str>>,
+    /// How urgently the release should be installed.
+    pub urgency: Urgency,
+    /// The identifiers of the security issues which the release fixes, such as CVEs.
+    pub cves: Vec<Box<str>>,
+    /// How far the release may be trusted, if the firmware service reports it.
+    pub trust: Option<ReleaseTrust>,
+}
+
+/// The changelog of a device, ordered from the newest release to the oldest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Changelog {
+    /// Each release in the changelog.
+    pub entries: Vec<ChangelogEntry>,
+}
+
+impl Changelog {
+    /// The newest release in the changelog.
+    pub fn latest(&self) -> Option<&ChangelogEntry> { self.entries.first() }
+
+    /// Checks if the changelog has no releases.
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// The releases which are newer than the given version, from the newest to the oldest.
+    pub fn newer_than<'a>(
+        &'a self,
+        version: &'a str,
+    ) -> impl Iterator<Item = &'a ChangelogEntry> + 'a {
+        self.entries
+            .iter()
+            .take_while(move |entry| human_sort::compare(version, &entry.version) == Ordering::Less)
+    }
+
+    /// The highest urgency of the releases which are newer than the given version.
+    pub fn urgency_since(&self, version: &str) -> Urgency {
+        self.newer_than(version).map(|entry| entry.urgency).max().unwrap_or_default()
+    }
+
+    /// Checks if any release which is newer than the given version fixes security issues.
+    pub fn security_fixes_since(&self, version: &str) -> bool {
+        self.newer_than(version).any(|entry| !entry.cves.is_empty())
+    }
+
+    /// Records the urgency, security issues, and trust of the releases which they describe.
+    pub(crate) fn apply_advisories(&mut self, advisories: Vec<Advisory>) {
+        for advisory in advisories {
+            if let Some(entry) = self.entries.iter_mut().find(|e| e.version == advisory.version) {
+                entry.urgency = advisory.urgency;
+                entry.cves = advisory.cves;
+                entry.trust = Some(advisory.trust);
+            }
+        }
+    }
+
+    /// Creates a changelog from versions and their descriptions, in the order given.
+    fn from_versions<'a, I: Iterator<Item = (&'a str, Option<&'a str>)>>(versions: I) -> Self {
+        let entries = versions
+            .map(|(version, description)| ChangelogEntry {
+                version: version.into(),
+                description: description.and_then(non_empty),
+                ..ChangelogEntry::default()
+            })
+            .collect();
+
+        Self { entries }
+    }
+}
+
+/// Converts releases which are sorted from the oldest to the newest, as fwupd releases are after
+/// a scan.
+impl From<&[FwupdRelease]> for Changelog {
+    fn from(releases: &[FwupdRelease]) -> Self {
+        let entries = releases
+            .iter()
+            .rev()
+            .map(|release| ChangelogEntry {
+                version: release.version.clone(),
+                date: Some(release.created).filter(|&created| created != 0),
+                description: non_empty(&release.description),
+                ..ChangelogEntry::default()
+            })
+            .collect();
+
+        Self { entries }
+    }
+}
+
+/// System76 changelogs are already sorted from the newest version to the oldest.
+impl From<&System76Changelog> for Changelog {
+    fn from(changelog: &System76Changelog) -> Self {
+        Self::from_versions(changelog.versions.iter().map(|version| {
+            (version.bios.as_ref(), version.description.as_ref().map(|desc| desc.as_ref()))
+        }))
+    }
+}
+
+fn non_empty(text: &str) -> Option<Box<str>> {
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(version: &str, urgency: Urgency, cves: &[&str]) -> ChangelogEntry {
+        ChangelogEntry {
+            version: version.into(),
+            urgency,
+            cves: cves.iter().map(|&cve| Box::from(cve)).collect(),
+            ..ChangelogEntry::default()
+        }
+    }
+
+    #[test]
+    fn urgency_of_newer_releases() {
+        let changelog = Changelog {
+            entries: vec![
+                entry("1.10", Urgency::Medium, &[]),
+                entry("1.9", Urgency::Critical, &["CVE-2021-0001"]),
+                entry("1.8", Urgency::High, &["CVE-2020-0001"]),
+            ],
+        };
+
+        assert_eq!(changelog.urgency_since("1.8"), Urgency::Critical);
+        assert!(changelog.security_fixes_since("1.8"));
+
+        assert_eq!(changelog.urgency_since("1.9"), Urgency::Medium);
+        assert!(!changelog.security_fixes_since("1.9"));
+
+        assert_eq!(changelog.urgency_since("1.10"), Urgency::Unknown);
+    }
+
+    #[test]
+    fn from_fwupd_releases() {
+        let release = |version: &str, created, description: &str| FwupdRelease {
+            version: version.into(),
+            created,
+            description: description.into(),
+            ..FwupdRelease::default()
+        };
+
+        let releases = [
+            release("1.8", 0, ""),
+            release("1.9", 1_600_000_000, "<p>Fixes a hang on resume.</p>"),
+        ];
+
+        let changelog = Changelog::from(&releases[..]);
+        let versions: Vec<&str> = changelog.entries.iter().map(|e| &*e.version).collect();
+        assert_eq!(versions, ["1.9", "1.8"]);
+
+        let newest = &changelog.entries[0];
+        assert_eq!(newest.date, Some(1_600_000_000));
+        assert_eq!(newest.description.as_deref(), Some("<p>Fixes a hang on resume.</p>"));
+
+        let oldest = &changelog.entries[1];
+        assert_eq!(oldest.date, None);
+        assert_eq!(oldest.description, None);
+    }
+
+    #[test]
+    fn from_system76_versions() {
+        let versions = vec![
+            ("2021-04-19_b3b4b4d", Some("Improve fan curve")),
+            ("2021-01-28_7e3e6f6", Some("")),
+            ("2020-11-13_b7c3c43", None),
+        ];
+
+        let changelog = Changelog::from_versions(versions.into_iter());
+        let versions: Vec<&str> = changelog.entries.iter().map(|e| &*e.version).collect();
+        assert_eq!(versions, ["2021-04-19_b3b4b4d", "2021-01-28_7e3e6f6", "2020-11-13_b7c3c43"]);
+
+        let descriptions: Vec<Option<&str>> =
+            changelog.entries.iter().map(|e| e.description.as_deref()).collect();
+        assert_eq!(descriptions, [Some("Improve fan curve"), None, None]);
+
+        assert!(changelog.entries.iter().all(|entry| entry.date.is_none()));
+    }
+
+    #[test]
+    fn trust_details_and_warnings() {
+        let trust = ReleaseTrust {
+            signed_payload: true,
+            signed_metadata: true,
+            remote: LVFS_REMOTE.into(),
+        };
+
+        assert_eq!(
+            trust.details(),
+            vec![TrustDetail::PayloadSigned, TrustDetail::MetadataSigned, TrustDetail::LvfsRemote]
+        );
+        assert_eq!(trust.warning(), None);
+
+        let trust = ReleaseTrust {
+            signed_payload: true,
+            signed_metadata: false,
+            remote: LVFS_TESTING_REMOTE.into(),
+        };
+
+        assert_eq!(trust.details()[2], TrustDetail::Remote(LVFS_TESTING_REMOTE));
+        assert_eq!(trust.warning(), Some(TrustWarning::Testing));
+
+        let trust = ReleaseTrust { remote: LVFS_TESTING_REMOTE.into(), ..ReleaseTrust::default() };
+        assert_eq!(trust.details().len(), 3);
+        assert_eq!(trust.warning(), Some(TrustWarning::Unsigned));
+        assert_eq!(ReleaseTrust::default().details().len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! Connections to the firmware services, which are re-established when a service restarts.
+
+use crate::{Backend, Error, FirmwareSignal};
+use std::time::{Duration, Instant};
+
+/// The delay before the first reconnection attempt.
+const BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+
+/// The longest delay between reconnection attempts.
+const BACKOFF_LIMIT: Duration = Duration::from_secs(60);
+
+/// A lazily-connected client to a firmware service.
+///
+/// When a connection fails, the next attempt is delayed with an exponential backoff. The
+/// connection is dropped when the service leaves the bus, and re-established when it returns.
+/// Services which the bus activates on demand, such as fwupd, may exit while idle, and are
+/// reconnected to when they are next required.
+pub(crate) struct ServiceClient<T> {
+    backend: Backend,
+    connect: fn() -> Result<T, Error>,
+    client: Option<T>,
+    /// The bus starts the service on demand, so it may be connected to after it has exited.
+    activatable: bool,
+    /// Failed connection attempts since the service was last seen starting.
+    failures: u32,
+    /// When the next connection attempt is due. `None` when the service is not available.
+    retry_at: Option<Instant>,
+}
+
+impl<T> ServiceClient<T> {
+    /// Creates a client for the backend, which connects immediately if it is available.
+    pub fn new(backend: Backend, available: bool, connect: fn() -> Result<T, Error>) -> Self {
+        let mut client = Self {
+            backend,
+            connect,
+            client: None,
+            activatable: false,
+            failures: 0,
+            retry_at: None,
+        };
+
+        if available {
+            client.try_connect();
+        }
+
+        client
+    }
+
+    /// The client, or an error stating that the backend is unavailable.
+    ///
+    /// If an activatable service has exited, one attempt is made to connect to it, which starts
+    /// the service again.
+    pub fn require(&mut self) -> Result<&T, Error> {
+        if self.client.is_none() && self.activatable {
+            info!("starting the {} service on demand", self.backend.name());
+            self.try_connect();
+        }
+
+        self.client.as_ref().ok_or(Error::Unavailable(self.backend))
+    }
+
+    /// Attempts to reconnect if an attempt is due.
+    ///
+    /// Returns `true` if the client was connected, in which case the frontend is notified that
+    /// the backend is available.
+    pub fn retry<F: Fn(FirmwareSignal)>(&mut self, sender: &F) -> bool {
+        let due = self.retry_at.map_or(false, |at| Instant::now() >= at);
+
+        if self.client.is_none() && due && self.try_connect() {
+            info!("{} service is available", self.backend.name());
+            sender(FirmwareSignal::BackendAvailable(self.backend));
+            return true;
+        }
+
+        false
+    }
+
+    /// How long until the next reconnection attempt is due, if one is scheduled.
+    pub fn retry_in(&self) -> Option<Duration> {
+        match self.client {
+            Some(_) => None,
+            None => self.retry_at.map(|at| at.saturating_duration_since(Instant::now())),
+        }
+    }
+
+    /// The service has acquired its name on the bus, so connect at the next opportunity.
+    pub fn started(&mut self) {
+        self.failures = 0;
+        self.retry_at = Some(Instant::now());
+    }
+
+    /// The service has left the bus, so the connection to it is dropped.
+    ///
+    /// An activatable service has only exited while idle, so its devices may still be managed,
+    /// and the frontend is not told that it was lost.
+    pub fn stopped<F: Fn(FirmwareSignal)>(&mut self, sender: &F, activatable: bool) {
+        self.retry_at = None;
+        self.activatable = activatable;
+
+        if self.client.take().is_some() {
+            if activatable {
+                info!("{} service exited, and will be started on demand", self.backend.name());
+            } else {
+                info!("{} service was lost", self.backend.name());
+                sender(FirmwareSignal::BackendLost(self.backend));
+            }
+        }
+    }
+
+    fn try_connect(&mut self) -> bool {
+        match (self.connect)() {
+            Ok(client) => {
+                self.client = Some(client);
+                self.failures = 0;
+                self.retry_at = None;
+                true
+            }
+            Err(why) => {
+                let delay = backoff(self.failures);
+                self.failures = self.failures.saturating_add(1);
+                self.retry_at = Some(Instant::now() + delay);
+
+                error!(
+                    "{} client error: {}; retrying in {} seconds",
+                    self.backend.name(),
+                    crate::format_error(&why),
+                    delay.as_secs()
+                );
+
+                false
+            }
+        }
+    }
+}
+
+/// The delay to wait before the next connection attempt, after `failures` failed attempts.
+fn backoff(failures: u32) -> Duration {
+    BACKOFF_INITIAL
+        .checked_mul(1 << failures.min(16))
+        .map_or(BACKOFF_LIMIT, |delay| delay.min(BACKOFF_LIMIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        assert_eq!(super::backoff(0), Duration::from_secs(1));
+        assert_eq!(super::backoff(1), Duration::from_secs(2));
+        assert_eq!(super::backoff(5), Duration::from_secs(32));
+        assert_eq!(super::backoff(6), BACKOFF_LIMIT);
+        assert_eq!(super::backoff(u32::MAX), BACKOFF_LIMIT);
+    }
+
+    #[test]
+    fn failed_connections_are_retried_later() {
+        let mut client: ServiceClient<()> =
+            ServiceClient::new(Backend::Fwupd, true, || Err(Error::Unavailable(Backend::Fwupd)));
+
+        assert!(client.client.is_none());
+        assert!(client.retry_at.map_or(false, |at| at > Instant::now()));
+        assert!(!client.retry(&|_| panic!("no signal expected")));
+
+        client.started();
+        assert_eq!(client.retry_in(), Some(Duration::from_secs(0)));
+        assert_eq!(client.failures, 0);
+        assert!(!client.retry(&|_| panic!("no signal expected")));
+        assert_eq!(client.failures, 1);
+    }
+
+    #[test]
+    fn reconnection_is_signaled() {
+        let mut client: ServiceClient<()> = ServiceClient::new(Backend::System76, false, || Ok(()));
+        assert!(client.client.is_none());
+
+        client.started();
+        let signals = std::cell::RefCell::new(Vec::new());
+        assert!(client.retry(&|signal| signals.borrow_mut().push(signal)));
+        assert!(client.client.is_some());
+
+        client.stopped(&|signal| signals.borrow_mut().push(signal), false);
+        assert!(client.client.is_none());
+        assert!(client.require().is_err());
+
+        assert!(matches!(
+            signals.borrow()[..],
+            [FirmwareSignal::BackendAvailable(Backend::System76), FirmwareSignal::BackendLost(_)]
+        ));
+    }
+
+    #[test]
+    fn activatable_services_are_started_on_demand() {
+        let mut client: ServiceClient<()> = ServiceClient::new(Backend::Fwupd, true, || Ok(()));
+        assert!(client.client.is_some());
+
+        client.stopped(&|_| panic!("an activatable service is not lost"), true);
+        assert!(client.client.is_none());
+        assert!(client.require().is_ok());
+        assert!(client.client.is_some());
+    }
+}
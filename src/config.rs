@@ -0,0 +1,428 @@
+//! Runtime configuration of the firmware manager.
+//!
+//! The configuration is read from `$XDG_CONFIG_HOME/com.system76.FirmwareManager/config.toml`,
+//! or from `/etc/com.system76.FirmwareManager/config.toml` if the user has not created one.
+//! Keys which are not defined take their default values. Intervals may be at most one year.
+//!
+//! ```toml
+//! # Hours between refreshes of the fwupd remote metadata.
+//! refresh-interval-hours = 24
+//!
+//! # Minutes between checks for updates by firmware-manager-notify.
+//! notification-interval-minutes = 1440
+//!
+//! # Minutes until firmware-manager-notify reminds the user after "Remind me later".
+//! notification-snooze-minutes = 240
+//!
+//! # Hours until firmware-manager-notify notifies about updates which it already notified about.
+//! notification-reminder-hours = 168
+//!
+//! # Members of these groups are administrators when polkit is not available.
+//! admin-groups = ["adm", "sudo", "wheel"]
+//!
+//! # Firmware services to manage firmware from.
+//! [backends]
+//! fwupd = true
+//! system76 = true
+//!
+//! # Devices which firmware-manager-notify updates without asking. Only devices which do not
+//! # require a reboot, from one of these vendors or with one of these GUIDs, are updated.
+//! [auto-update]
+//! enabled = false
+//! vendors = []
+//! guids = []
+//! ```
+
+use crate::Backend;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::{value::Table, Value};
+
+/// The name of the configuration file.
+const CONFIG_FILE: &str = "config.toml";
+
+/// The directory of the system-wide configuration file.
+const SYSTEM_CONFIG_DIR: &str = "/etc/com.system76.FirmwareManager";
+
+/// The longest interval which may be configured in hours, which is one year.
+const MAX_HOURS: u64 = 365 * 24;
+
+/// The longest interval which may be configured in minutes, which is one year.
+const MAX_MINUTES: u64 = MAX_HOURS * 60;
+
+/// An error that may occur when loading the configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// The XDG base directories could not be determined.
+    #[error("failed to get XDG base directory")]
+    BaseDirectory(#[from] xdg::BaseDirectoriesError),
+    /// The configuration file exists, but could not be read.
+    #[error("failed to read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    /// The configuration file is not valid TOML.
+    #[error("{} is not valid TOML", .0.display())]
+    Syntax(PathBuf, #[source] toml::de::Error),
+    /// The configuration file contains an invalid key.
+    #[error("invalid configuration in {}", .0.display())]
+    Invalid(PathBuf, #[source] InvalidKey),
+}
+
+/// Describes a key in the configuration file which is invalid.
+#[derive(Debug, Error, PartialEq)]
+pub enum InvalidKey {
+    /// The key is not known to the firmware manager.
+    #[error("unknown key `{0}`")]
+    Unknown(String),
+    /// The value of the key is of the wrong type.
+    #[error("`{0}` must be {1}")]
+    Type(String, &'static str),
+    /// The value of the key must not be zero.
+    #[error("`{0}` must be greater than zero")]
+    Zero(String),
+    /// The value of the key must not exceed the given maximum.
+    #[error("`{0}` must be at most {1}")]
+    TooLarge(String, u64),
+}
+
+/// Firmware services which are enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendsConfig {
+    /// Manage firmware from the fwupd service.
+    pub fwupd: bool,
+    /// Manage firmware from the system76-firmware service.
+    pub system76: bool,
+}
+
+impl Default for BackendsConfig {
+    fn default() -> Self { Self { fwupd: true, system76: true } }
+}
+
+/// The policy for updating firmware without asking the user.
+///
+/// System firmware, and other firmware which requires a reboot, is never updated automatically.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoUpdateConfig {
+    /// Update allowed devices automatically.
+    pub enabled: bool,
+    /// Vendors whose devices may be updated automatically.
+    pub vendors: Vec<String>,
+    /// GUIDs of devices which may be updated automatically.
+    pub guids: Vec<String>,
+}
+
+impl AutoUpdateConfig {
+    /// Checks if a device from the given vendor, with the given GUIDs, is in the allowlist.
+    ///
+    /// Vendors and GUIDs are compared case-insensitively.
+    pub fn allows<S: AsRef<str>>(&self, vendor: &str, guids: &[S]) -> bool {
+        self.vendors.iter().any(|allowed| allowed.eq_ignore_ascii_case(vendor))
+            || guids.iter().any(|guid| {
+                self.guids.iter().any(|allowed| allowed.eq_ignore_ascii_case(guid.as_ref()))
+            })
+    }
+}
+
+/// The runtime configuration shared by the core and its frontends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Hours between refreshes of the fwupd remote metadata.
+    pub refresh_interval_hours: u64,
+    /// Minutes between checks for updates by the notifier.
+    pub notification_interval_minutes: u64,
+    /// Minutes until the notifier reminds the user of updates which they snoozed.
+    pub notification_snooze_minutes: u64,
+    /// Hours until the notifier notifies about updates which it already notified about.
+    pub notification_reminder_hours: u64,
+    /// Members of these groups are administrators when polkit is not available.
+    pub admin_groups: Vec<String>,
+    /// Firmware services which are enabled.
+    pub backends: BackendsConfig,
+    /// The policy for updating firmware without asking the user.
+    pub auto_update: AutoUpdateConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            refresh_interval_hours: 24,
+            notification_interval_minutes: 1440,
+            notification_snooze_minutes: 240,
+            notification_reminder_hours: 168,
+            admin_groups: include_str!("admin-groups")
+                .lines()
+                .filter(|group| !group.is_empty())
+                .map(String::from)
+                .collect(),
+            backends: BackendsConfig::default(),
+            auto_update: AutoUpdateConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration of the user, or the system-wide configuration if the user has not
+    /// created one.
+    ///
+    /// If neither exists, the default configuration is returned.
+    pub fn load() -> Result<Self, ConfigError> {
+        let user = xdg::BaseDirectories::with_prefix("com.system76.FirmwareManager")?
+            .get_config_file(CONFIG_FILE);
+
+        let system = Path::new(SYSTEM_CONFIG_DIR).join(CONFIG_FILE);
+
+        match [user, system].iter().find(|path| path.exists()) {
+            Some(path) => Self::load_from(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Loads the configuration, or the default configuration if it is invalid.
+    ///
+    /// Errors are logged.
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|why| {
+            error!("{}; using the default configuration", crate::format_error(&why));
+            Self::default()
+        })
+    }
+
+    /// Loads the configuration from the file at the given path.
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let source =
+            fs::read_to_string(path).map_err(|why| ConfigError::Read(path.to_owned(), why))?;
+
+        let value =
+            source.parse::<Value>().map_err(|why| ConfigError::Syntax(path.to_owned(), why))?;
+
+        Self::from_value(value).map_err(|why| ConfigError::Invalid(path.to_owned(), why))
+    }
+
+    /// Checks if a backend is enabled.
+    pub fn backend_enabled(&self, backend: Backend) -> bool {
+        match backend {
+            Backend::Fwupd => self.backends.fwupd,
+            Backend::System76 => self.backends.system76,
+        }
+    }
+
+    /// The interval between refreshes of the fwupd remote metadata.
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_interval_hours.saturating_mul(60 * 60))
+    }
+
+    /// The interval between checks for updates by the notifier.
+    pub fn notification_interval(&self) -> Duration {
+        Duration::from_secs(self.notification_interval_minutes.saturating_mul(60))
+    }
+
+    /// The interval until the notifier reminds the user of updates which they snoozed.
+    pub fn notification_snooze(&self) -> Duration {
+        Duration::from_secs(self.notification_snooze_minutes.saturating_mul(60))
+    }
+
+    /// The interval until the notifier notifies about updates which it already notified about.
+    pub fn notification_reminder(&self) -> Duration {
+        Duration::from_secs(self.notification_reminder_hours.saturating_mul(60 * 60))
+    }
+
+    fn from_value(value: Value) -> Result<Self, InvalidKey> {
+        let mut config = Self::default();
+
+        for (key, value) in into_table(value, "")? {
+            match key.as_str() {
+                "refresh-interval-hours" => {
+                    config.refresh_interval_hours = positive_integer(&key, value, MAX_HOURS)?
+                }
+                "notification-interval-minutes" => {
+                    config.notification_interval_minutes =
+                        positive_integer(&key, value, MAX_MINUTES)?
+                }
+                "notification-snooze-minutes" => {
+                    config.notification_snooze_minutes = positive_integer(&key, value, MAX_MINUTES)?
+                }
+                "notification-reminder-hours" => {
+                    config.notification_reminder_hours = positive_integer(&key, value, MAX_HOURS)?
+                }
+                "admin-groups" => config.admin_groups = string_array(&key, value)?,
+                "backends" => {
+                    for (name, value) in into_table(value, "backends")? {
+                        let key = ["backends.", &name].concat();
+                        match name.as_str() {
+                            "fwupd" => config.backends.fwupd = boolean(&key, value)?,
+                            "system76" => config.backends.system76 = boolean(&key, value)?,
+                            _ => return Err(InvalidKey::Unknown(key)),
+                        }
+                    }
+                }
+                "auto-update" => {
+                    for (name, value) in into_table(value, "auto-update")? {
+                        let key = ["auto-update.", &name].concat();
+                        let auto_update = &mut config.auto_update;
+                        match name.as_str() {
+                            "enabled" => auto_update.enabled = boolean(&key, value)?,
+                            "vendors" => auto_update.vendors = string_array(&key, value)?,
+                            "guids" => auto_update.guids = string_array(&key, value)?,
+                            _ => return Err(InvalidKey::Unknown(key)),
+                        }
+                    }
+                }
+                _ => return Err(InvalidKey::Unknown(key)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn into_table(value: Value, key: &str) -> Result<Table, InvalidKey> {
+    match value {
+        Value::Table(table) => Ok(table),
+        _ => Err(InvalidKey::Type(key.into(), "a table")),
+    }
+}
+
+fn boolean(key: &str, value: Value) -> Result<bool, InvalidKey> {
+    value.as_bool().ok_or_else(|| InvalidKey::Type(key.into(), "true or false"))
+}
+
+/// A positive integer which is at most `max`, so that it may be converted to seconds.
+fn positive_integer(key: &str, value: Value, max: u64) -> Result<u64, InvalidKey> {
+    match value.as_integer() {
+        Some(0) => Err(InvalidKey::Zero(key.into())),
+        Some(integer) if integer > 0 && integer as u64 <= max => Ok(integer as u64),
+        Some(integer) if integer > 0 => Err(InvalidKey::TooLarge(key.into(), max)),
+        _ => Err(InvalidKey::Type(key.into(), "a positive integer")),
+    }
+}
+
+fn string_array(key: &str, value: Value) -> Result<Vec<String>, InvalidKey> {
+    let invalid = || InvalidKey::Type(key.into(), "an array of strings");
+
+    match value {
+        Value::Array(array) => array
+            .into_iter()
+            .map(|value| match value {
+                Value::String(string) if !string.is_empty() => Ok(string),
+                _ => Err(invalid()),
+            })
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Config, InvalidKey> {
+        Config::from_value(source.parse::<Value>().unwrap())
+    }
+
+    #[test]
+    fn defaults() {
+        let config = parse("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.refresh_interval(), Duration::from_secs(24 * 60 * 60));
+        assert_eq!(config.admin_groups, vec!["adm", "sudo", "wheel"]);
+        assert!(config.backend_enabled(Backend::Fwupd));
+        assert!(config.backend_enabled(Backend::System76));
+    }
+
+    #[test]
+    fn valid_keys() {
+        let config = parse(
+            "refresh-interval-hours = 12\n\
+             notification-interval-minutes = 60\n\
+             notification-snooze-minutes = 30\n\
+             notification-reminder-hours = 48\n\
+             admin-groups = [\"wheel\"]\n\
+             [backends]\n\
+             system76 = false\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.refresh_interval_hours, 12);
+        assert_eq!(config.notification_interval(), Duration::from_secs(60 * 60));
+        assert_eq!(config.notification_snooze(), Duration::from_secs(30 * 60));
+        assert_eq!(config.notification_reminder(), Duration::from_secs(48 * 60 * 60));
+        assert_eq!(config.admin_groups, vec!["wheel"]);
+        assert!(config.backend_enabled(Backend::Fwupd));
+        assert!(!config.backend_enabled(Backend::System76));
+    }
+
+    #[test]
+    fn auto_update_allowlist() {
+        let config = parse(
+            "[auto-update]\n\
+             enabled = true\n\
+             vendors = [\"Logitech\"]\n\
+             guids = [\"9d131a0c-a606-580f-8eda-80587250b8d6\"]\n",
+        )
+        .unwrap();
+
+        let auto_update = &config.auto_update;
+        assert!(auto_update.enabled);
+        assert!(auto_update.allows("logitech", &[] as &[&str]));
+        assert!(auto_update.allows("Realtek", &["9D131A0C-A606-580F-8EDA-80587250B8D6"]));
+        assert!(!auto_update.allows("Realtek", &["2082b5e0-7a64-478a-b1b2-e3404fab6dad"]));
+        assert!(!Config::default().auto_update.allows("Logitech", &[] as &[&str]));
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert_eq!(parse("refresh = 12"), Err(InvalidKey::Unknown("refresh".into())));
+
+        assert_eq!(
+            parse("[backends]\nlvfs = true"),
+            Err(InvalidKey::Unknown("backends.lvfs".into()))
+        );
+
+        assert_eq!(
+            parse("refresh-interval-hours = 0"),
+            Err(InvalidKey::Zero("refresh-interval-hours".into()))
+        );
+
+        assert_eq!(
+            parse("notification-interval-minutes = \"daily\""),
+            Err(InvalidKey::Type("notification-interval-minutes".into(), "a positive integer"))
+        );
+
+        assert_eq!(
+            parse("admin-groups = [\"\"]"),
+            Err(InvalidKey::Type("admin-groups".into(), "an array of strings"))
+        );
+
+        assert_eq!(parse("backends = true"), Err(InvalidKey::Type("backends".into(), "a table")));
+    }
+
+    #[test]
+    fn interval_limits() {
+        let config = parse(
+            "refresh-interval-hours = 8760\n\
+             notification-snooze-minutes = 525600\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.refresh_interval(), Duration::from_secs(365 * 24 * 60 * 60));
+        assert_eq!(config.notification_snooze(), Duration::from_secs(365 * 24 * 60 * 60));
+
+        assert_eq!(
+            parse("refresh-interval-hours = 8761"),
+            Err(InvalidKey::TooLarge("refresh-interval-hours".into(), MAX_HOURS))
+        );
+
+        assert_eq!(
+            parse("notification-interval-minutes = 9223372036854775807"),
+            Err(InvalidKey::TooLarge("notification-interval-minutes".into(), MAX_MINUTES))
+        );
+    }
+
+    #[test]
+    fn intervals_saturate() {
+        let config = Config { notification_reminder_hours: u64::MAX, ..Config::default() };
+        assert_eq!(config.notification_reminder(), Duration::from_secs(u64::MAX));
+    }
+}
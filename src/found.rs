@@ -0,0 +1,262 @@
+//! Devices which were found by a scan, and the update which a frontend performs for them.
+//!
+//! Every frontend decides whether a device may be updated in the same way, so the signals which
+//! describe devices are converted into a `FoundDevice` which makes that decision.
+
+use crate::{
+    Backend, Entity, FirmwareEvent, FirmwareInfo, FirmwareSignal, FwupdDevice, FwupdRelease,
+    FwupdSignal, PendingAction, S76SystemUpdate, ThelioIoUpdate, SYSTEM76_VENDOR,
+};
+use std::sync::Arc;
+
+/// The state of a device's firmware, as it was found by a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateStatus {
+    /// The latest firmware is installed, or no newer firmware may be installed.
+    UpToDate,
+    /// Newer firmware may be installed.
+    Available,
+    /// Newer firmware is available, but a hold withholds it.
+    Held,
+    /// System firmware is scheduled to be installed on the next boot.
+    Scheduled,
+    /// The device must be activated or unlocked.
+    Pending(PendingAction),
+}
+
+impl UpdateStatus {
+    /// The status of a device, which may be given by its firmware service.
+    ///
+    /// A device which is waiting on an action, or which is scheduled, is reported as such.
+    /// Otherwise, newer firmware is available if its update may be installed, and is not held.
+    fn select(info: &FirmwareInfo, installable: bool, status: Option<UpdateStatus>) -> Self {
+        let newer = info.latest.as_ref().map_or(false, |latest| *latest != info.current);
+
+        if let Some(status) = status {
+            status
+        } else if !newer {
+            UpdateStatus::UpToDate
+        } else if info.held {
+            UpdateStatus::Held
+        } else if installable {
+            UpdateStatus::Available
+        } else {
+            UpdateStatus::UpToDate
+        }
+    }
+}
+
+/// What is performed when a device is updated.
+#[derive(Debug, Clone)]
+pub enum PendingUpdate {
+    /// Activate the new firmware of a fwupd device.
+    Activate(Arc<FwupdDevice>),
+    /// Install the latest release of a fwupd device.
+    Fwupd(Arc<FwupdDevice>, Arc<FwupdRelease>),
+    /// Schedule System76 system firmware to be installed on the next boot.
+    System(S76SystemUpdate),
+    /// Flash a Thelio I/O board.
+    ThelioIo(ThelioIoUpdate),
+    /// Unlock a fwupd device.
+    Unlock(Arc<FwupdDevice>),
+    /// Cancel the System76 system firmware which is scheduled for the next boot.
+    Unschedule,
+}
+
+impl PendingUpdate {
+    /// The event which performs this update for the given entity.
+    pub fn event(&self, entity: Entity) -> FirmwareEvent {
+        match self {
+            PendingUpdate::Activate(device) => FirmwareEvent::Activate(entity, device.clone()),
+            PendingUpdate::Fwupd(device, release) => {
+                FirmwareEvent::Fwupd(entity, device.clone(), release.clone())
+            }
+            PendingUpdate::System(update) => FirmwareEvent::S76System(entity, update.clone()),
+            PendingUpdate::ThelioIo(update) => FirmwareEvent::ThelioIo(entity, update.clone()),
+            PendingUpdate::Unlock(device) => FirmwareEvent::Unlock(entity, device.clone()),
+            PendingUpdate::Unschedule => FirmwareEvent::Unschedule(entity),
+        }
+    }
+}
+
+/// A device which was found by a scan, with the update which may be performed for it.
+#[derive(Debug)]
+pub struct FoundDevice {
+    /// Generic information about the firmware.
+    pub info: FirmwareInfo,
+    /// The state of the device's firmware.
+    pub status: UpdateStatus,
+    /// The firmware is flashed on reboot, so updating it should be confirmed.
+    pub system: bool,
+    /// The fwupd device, if fwupd manages the device.
+    pub fwupd: Option<Arc<FwupdDevice>>,
+    /// What is performed when the device is updated, if anything.
+    pub update: Option<PendingUpdate>,
+}
+
+impl FoundDevice {
+    fn new(
+        info: FirmwareInfo,
+        system: bool,
+        fwupd: Option<Arc<FwupdDevice>>,
+        update: Option<PendingUpdate>,
+        status: Option<UpdateStatus>,
+    ) -> Self {
+        let status = UpdateStatus::select(&info, update.is_some(), status);
+
+        let update = match status {
+            UpdateStatus::Available => update,
+            UpdateStatus::Pending(PendingAction::Activate) => {
+                fwupd.clone().map(PendingUpdate::Activate)
+            }
+            UpdateStatus::Pending(PendingAction::Unlock) => {
+                fwupd.clone().map(PendingUpdate::Unlock)
+            }
+            UpdateStatus::Scheduled => Some(PendingUpdate::Unschedule),
+            UpdateStatus::UpToDate | UpdateStatus::Held => None,
+        };
+
+        Self { info, status, system, fwupd, update }
+    }
+
+    /// The device which a signal describes, if it describes one.
+    pub fn from_signal(signal: FirmwareSignal) -> Option<Self> {
+        let device = match signal {
+            FirmwareSignal::Fwupd(FwupdSignal { info, device, upgradeable, releases, pending }) => {
+                let device = Arc::new(device);
+                let update = releases
+                    .last()
+                    .filter(|_| upgradeable)
+                    .map(|release| PendingUpdate::Fwupd(device.clone(), Arc::new(release.clone())));
+
+                let system = device.needs_reboot();
+                Self::new(info, system, Some(device), update, pending.map(UpdateStatus::Pending))
+            }
+            FirmwareSignal::S76System(info, digest, scheduled) => {
+                let status = if scheduled { Some(UpdateStatus::Scheduled) } else { None };
+                let update = digest.map(|digest| {
+                    PendingUpdate::System(S76SystemUpdate {
+                        name: info.name.clone(),
+                        current: info.current.clone(),
+                        digest,
+                        version: info.latest.clone().unwrap_or_default(),
+                    })
+                });
+
+                Self::new(info, true, None, update, status)
+            }
+            FirmwareSignal::ThelioIo(info, update) => {
+                Self::new(info, false, None, update.map(PendingUpdate::ThelioIo), None)
+            }
+            _ => return None,
+        };
+
+        Some(device)
+    }
+
+    /// The firmware service which manages the device.
+    pub fn backend(&self) -> Backend {
+        if self.fwupd.is_some() {
+            Backend::Fwupd
+        } else {
+            Backend::System76
+        }
+    }
+
+    /// The vendor of the device.
+    pub fn vendor(&self) -> &str {
+        self.fwupd.as_ref().map_or(SYSTEM76_VENDOR, |device| &device.vendor)
+    }
+
+    /// Newer firmware may be installed.
+    pub fn upgradeable(&self) -> bool { self.status == UpdateStatus::Available }
+
+    /// The event which updates the device, or performs the action it is waiting on, if any.
+    pub fn event(&self, entity: Entity) -> Option<FirmwareEvent> {
+        self.update.as_ref().map(|update| update.event(entity))
+    }
+
+    /// Records that the latest firmware was installed.
+    pub fn updated(&mut self) {
+        if let Some(latest) = self.info.latest.clone() {
+            self.info.current = latest;
+        }
+
+        self.status = UpdateStatus::UpToDate;
+        self.update = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(current: &str, latest: Option<&str>, held: bool) -> FirmwareInfo {
+        FirmwareInfo {
+            id: "device".into(),
+            name: "Device".into(),
+            current: current.into(),
+            latest: latest.map(Box::from),
+            install_duration: 0,
+            held,
+            changelog: Default::default(),
+        }
+    }
+
+    #[test]
+    fn status_selection() {
+        let select = UpdateStatus::select;
+        let activate = UpdateStatus::Pending(PendingAction::Activate);
+
+        assert_eq!(select(&info("1.0", Some("1.1"), false), true, None), UpdateStatus::Available);
+        assert_eq!(select(&info("1.1", Some("1.1"), false), true, None), UpdateStatus::UpToDate);
+        assert_eq!(select(&info("1.0", None, false), true, None), UpdateStatus::UpToDate);
+        assert_eq!(select(&info("1.0", Some("1.1"), true), true, None), UpdateStatus::Held);
+        assert_eq!(select(&info("1.0", Some("1.1"), false), false, None), UpdateStatus::UpToDate);
+        assert_eq!(select(&info("1.0", Some("1.1"), true), true, Some(activate)), activate);
+        assert_eq!(
+            select(&info("1.1", Some("1.1"), false), false, Some(UpdateStatus::Scheduled)),
+            UpdateStatus::Scheduled
+        );
+    }
+
+    // Which update a device has does not matter here, so one which needs no device is used.
+    #[test]
+    fn updates_follow_status() {
+        let scheduled = FoundDevice::new(
+            info("1.0", Some("1.1"), false),
+            true,
+            None,
+            None,
+            Some(UpdateStatus::Scheduled),
+        );
+
+        assert!(!scheduled.upgradeable());
+        assert!(matches!(scheduled.update, Some(PendingUpdate::Unschedule)));
+
+        let held = FoundDevice::new(
+            info("1.0", Some("1.1"), true),
+            true,
+            None,
+            Some(PendingUpdate::Unschedule),
+            None,
+        );
+
+        assert_eq!(held.status, UpdateStatus::Held);
+        assert!(held.update.is_none());
+
+        let mut available = FoundDevice::new(
+            info("1.0", Some("1.1"), false),
+            false,
+            None,
+            Some(PendingUpdate::Unschedule),
+            None,
+        );
+
+        assert!(available.upgradeable());
+        available.updated();
+        assert_eq!(&*available.info.current, "1.1");
+        assert_eq!(available.status, UpdateStatus::UpToDate);
+        assert!(available.update.is_none());
+    }
+}
@@ -1,8 +1,16 @@
 //! Functions specific to working with fwupd firmware.
 
-use crate::{FirmwareInfo, FirmwareSignal};
+use crate::{
+    advisories::fwupd_advisories,
+    fwupd_pending_action, history,
+    refresh::{refresh_conditions, MetadataRefresh},
+    services::{FWUPD_IFACE, FWUPD_PATH, FWUPD_SERVICE},
+    Changelog, FirmwareInfo, FirmwareSignal, HistoryEntry, Holds, PendingAction, ScanStatus,
+    UpdateOutcome,
+};
+use dbus::blocking::Connection;
 use fwupd_dbus::{Client as FwupdClient, Device as FwupdDevice, Release as FwupdRelease};
-use std::cmp::Ordering;
+use std::{cmp::Ordering, time::Duration};
 
 /// A signal sent when a fwupd-compatible device has been discovered.
 #[derive(Debug)]
@@ -12,92 +20,201 @@ pub struct FwupdSignal {
     /// Information specific to fwupd devices.
     pub device: FwupdDevice,
     /// Tracks whether the firmware is upgradeable or not.
+    ///
+    /// Firmware which is withheld by a hold is not upgradeable.
     pub upgradeable: bool,
     /// All releases that were found for the firmware.
     pub releases: Vec<FwupdRelease>,
+    /// The device must be activated or unlocked before it can be used or updated.
+    pub pending: Option<PendingAction>,
 }
 
 /// Scan for supported devices from the fwupd DBus daemon.
-pub fn fwupd_scan<F: Fn(FirmwareSignal)>(fwupd: &FwupdClient, sender: F) {
+///
+/// Devices whose latest release is withheld by the given holds are not upgradeable.
+pub fn fwupd_scan<F: Fn(FirmwareSignal)>(
+    fwupd: &FwupdClient,
+    holds: &Holds,
+    sender: F,
+) -> ScanStatus {
     info!("scanning fwupd devices");
 
     let devices = match fwupd.devices() {
         Ok(devices) => devices,
         Err(why) => {
+            let status = ScanStatus::Errored(crate::format_error(&why).into());
             sender(FirmwareSignal::Error(None, why.into()));
-            return;
+            return status;
         }
     };
 
+    // Used to read the urgency and security advisories of releases, which the client omits.
+    let connection = Connection::new_system()
+        .map_err(|why| warn!("failed to connect to fwupd for release advisories: {}", why))
+        .ok();
+
+    let mut found = 0;
+
     for device in devices {
-        if device.is_supported() {
-            match fwupd.releases(&device) {
-                Ok(mut releases) => {
-                    crate::sort_versions(&mut releases);
-
-                    let latest = releases.iter().last().expect("no releases");
-                    let upgradeable = is_newer(&device.version, &latest.version);
-
-                    sender(FirmwareSignal::Fwupd(FwupdSignal {
-                        info: FirmwareInfo {
-                            name: [&device.vendor, " ", &device.name].concat().into(),
-                            current: device.version.clone(),
-                            latest: Some(latest.version.clone()),
-                            install_duration: latest.install_duration,
-                        },
-                        device,
-                        upgradeable,
-                        releases,
-                    }));
-                }
-                Err(why) => {
-                    error!(
-                        "failure to get fwupd releases for {}: {}",
-                        device.name,
-                        super::format_error(why)
-                    );
+        let pending = fwupd_pending_action(&device);
+
+        // Locked devices, and devices awaiting activation, are shown even when they can not be
+        // updated, so that the pending action may be performed.
+        if !device.is_supported() && pending.is_none() {
+            continue;
+        }
+
+        let mut releases = match fwupd.releases(&device) {
+            Ok(releases) => releases,
+            Err(_) if pending.is_some() => Vec::new(),
+            Err(why) => {
+                error!(
+                    "failure to get fwupd releases for {}: {}",
+                    device.name,
+                    super::format_error(&why)
+                );
+                continue;
+            }
+        };
+
+        crate::sort_versions(&mut releases);
+
+        let latest = releases.iter().last();
+        let id: Box<str> = Box::from(&**device.device_id);
+        let newer = latest.map_or(false, |latest| is_newer(&device.version, &latest.version));
+        let held = newer && latest.map_or(false, |latest| !holds.permits(&id, &latest.version));
+
+        let mut changelog = Changelog::from(&releases[..]);
+        if let Some(ref connection) = connection {
+            if !releases.is_empty() {
+                match fwupd_advisories(connection, &device) {
+                    Ok(advisories) => changelog.apply_advisories(advisories),
+                    Err(why) => {
+                        warn!("failed to get release advisories for {}: {}", device.name, why)
+                    }
                 }
             }
         }
+
+        found += 1;
+        sender(FirmwareSignal::Fwupd(FwupdSignal {
+            info: FirmwareInfo {
+                id,
+                name: [&device.vendor, " ", &device.name].concat().into(),
+                current: device.version.clone(),
+                latest: latest.map(|latest| latest.version.clone()),
+                install_duration: latest.map_or(0, |latest| latest.install_duration),
+                held,
+                changelog,
+            },
+            upgradeable: newer && !held && pending != Some(PendingAction::Unlock),
+            device,
+            releases,
+            pending,
+        }));
     }
 
     info!("fwupd scanning complete");
+    ScanStatus::Found(found)
 }
 
-/// Update the fwupd remotes
-pub fn fwupd_updates(client: &FwupdClient) -> Result<(), fwupd_dbus::Error> {
-    const SECONDS_IN_DAY: u64 = 60 * 60 * 24;
+/// Records the outcome of updating a fwupd device to a release in the update history.
+pub(crate) fn fwupd_record_update<T>(
+    device: &FwupdDevice,
+    release: &FwupdRelease,
+    result: &Result<T, fwupd_dbus::Error>,
+    unattended: bool,
+) {
+    let outcome = match result {
+        Ok(_) => UpdateOutcome::Updated,
+        Err(why) => UpdateOutcome::Failed(crate::format_error(why).into()),
+    };
 
-    if crate::timestamp::exceeded(SECONDS_IN_DAY).ok().unwrap_or(true) {
-        info!("refreshing remotes");
+    let entry = HistoryEntry::new(
+        &**device.device_id,
+        &[&device.vendor, " ", &device.name].concat(),
+        &device.version,
+        &release.version,
+        outcome,
+        unattended,
+    );
+
+    if let Err(why) = history::record_update(&entry) {
+        error!("{}", crate::format_error(&why));
+    }
+}
+
+/// Calls a method of the fwupd daemon which takes the ID of a device as its only argument.
+pub(crate) fn fwupd_device_call(
+    device: &FwupdDevice,
+    method: &str,
+    timeout: Duration,
+) -> Result<(), dbus::Error> {
+    let connection = Connection::new_system()?;
+    let proxy = connection.with_proxy(FWUPD_SERVICE, FWUPD_PATH, timeout);
+    proxy.method_call(FWUPD_IFACE, method, (&**device.device_id,))
+}
 
-        if let Err(why) = crate::timestamp::refresh() {
-            error!("failed to update timestamp: {}", why);
+/// Connects to the fwupd daemon, using `Ping()` to wake it up and to check that it exists.
+pub(crate) fn fwupd_connect() -> Result<FwupdClient, crate::Error> {
+    let client = FwupdClient::new()?;
+    client.ping()?;
+    Ok(client)
+}
+
+/// Update the fwupd remotes, if the refresh interval has passed since they were last updated.
+///
+/// The refresh is deferred on a metered network connection, or while the battery is low, and
+/// the reason is logged. A forced refresh ignores both the refresh interval and these conditions.
+pub fn fwupd_updates(
+    client: &FwupdClient,
+    refresh_interval: Duration,
+    force: bool,
+) -> Result<MetadataRefresh, fwupd_dbus::Error> {
+    use crate::timestamp::{self, METADATA_REFRESH};
+
+    if !force {
+        if !timestamp::exceeded(METADATA_REFRESH, refresh_interval.as_secs()).ok().unwrap_or(true) {
+            return Ok(MetadataRefresh::NotDue);
         }
 
-        // NOTE: This attribute is required due to a clippy bug.
-        #[allow(clippy::identity_conversion)]
-        for remote in client.remotes()? {
-            if !remote.enabled {
-                continue;
-            }
+        if let Err(reason) = refresh_conditions() {
+            info!("deferring the refresh of remotes: {}", reason);
+            return Ok(MetadataRefresh::Deferred(reason));
+        }
+    }
 
-            if let fwupd_dbus::RemoteKind::Download = remote.kind {
-                info!("Updating {:?} metadata from {:?}", remote.remote_id, remote.uri);
-                if let Err(why) = remote.update_metadata(client) {
-                    error!(
-                        "failed to fetch updates from {}: {:?}",
-                        remote.filename_cache,
-                        super::format_error(why)
-                    );
-                }
+    info!("refreshing remotes");
+
+    if let Err(why) = timestamp::refresh(METADATA_REFRESH) {
+        error!("failed to update timestamp: {}", why);
+    }
+
+    // NOTE: This attribute is required due to a clippy bug.
+    #[allow(clippy::identity_conversion)]
+    for remote in client.remotes()? {
+        if !remote.enabled {
+            continue;
+        }
+
+        if let fwupd_dbus::RemoteKind::Download = remote.kind {
+            info!("Updating {:?} metadata from {:?}", remote.remote_id, remote.uri);
+            if let Err(why) = remote.update_metadata(client) {
+                error!(
+                    "failed to fetch updates from {}: {:?}",
+                    remote.filename_cache,
+                    super::format_error(&why)
+                );
             }
         }
     }
 
-    Ok(())
+    Ok(MetadataRefresh::Refreshed)
 }
 
+/// Check if the fwupd service is available on the system bus.
+pub fn fwupd_is_active() -> bool { crate::service_is_available(crate::FWUPD_SERVICE) }
+
 // Returns `true` if the `latest` string is a newer version than the `current` string.
 fn is_newer(current: &str, latest: &str) -> bool {
     human_sort::compare(current, latest) == Ordering::Less
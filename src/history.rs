@@ -0,0 +1,219 @@
+//! A history of the firmware updates which have been attempted.
+//!
+//! Each update is appended to `history.toml` in the XDG data directory as it completes, so
+//! that updates performed by different frontends are recorded in one place.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml::{value::Table, Value};
+
+/// An error that may occur when reading or writing the update history.
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    /// The XDG base directories could not be determined.
+    #[error("failed to get XDG base directory")]
+    BaseDirectory(#[from] xdg::BaseDirectoriesError),
+    /// The data directory could not be created.
+    #[error("failed to create data directory for the update history")]
+    Place(#[source] io::Error),
+    /// The history file exists, but could not be read.
+    #[error("failed to read the update history")]
+    Read(#[source] io::Error),
+    /// The history file could not be written.
+    #[error("failed to write to the update history")]
+    Write(#[source] io::Error),
+    /// The history file is not valid TOML.
+    #[error("the update history is invalid")]
+    Parse(#[source] toml::de::Error),
+}
+
+/// The outcome of an update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum UpdateOutcome {
+    /// The firmware was flashed.
+    Updated,
+    /// The firmware will be flashed on the next boot.
+    Scheduled,
+    /// The update failed, for the given reason.
+    Failed(Box<str>),
+}
+
+/// A record of an update in the update history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HistoryEntry {
+    /// When the update completed, in seconds since the Unix epoch.
+    pub time: u64,
+    /// The identifier of the device, as used by holds.
+    pub id: Box<str>,
+    /// The name of the device.
+    pub name: Box<str>,
+    /// The version that was installed before the update.
+    pub from: Box<str>,
+    /// The version that the update installs.
+    pub to: Box<str>,
+    /// The outcome of the update.
+    pub outcome: UpdateOutcome,
+    /// The update was performed automatically, without the user asking for it.
+    pub unattended: bool,
+}
+
+impl HistoryEntry {
+    /// Creates an entry for an update which has just completed.
+    pub fn new(
+        id: &str,
+        name: &str,
+        from: &str,
+        to: &str,
+        outcome: UpdateOutcome,
+        unattended: bool,
+    ) -> Self {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+
+        Self {
+            time,
+            id: id.into(),
+            name: name.into(),
+            from: from.into(),
+            to: to.into(),
+            outcome,
+            unattended,
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let string = |key: &str| value.get(key).and_then(Value::as_str).map(Box::from);
+
+        let outcome = match value.get("outcome").and_then(Value::as_str)? {
+            "updated" => UpdateOutcome::Updated,
+            "scheduled" => UpdateOutcome::Scheduled,
+            "failed" => UpdateOutcome::Failed(string("error").unwrap_or_default()),
+            _ => return None,
+        };
+
+        Some(Self {
+            time: value.get("time").and_then(Value::as_integer).map_or(0, |time| time as u64),
+            id: string("id")?,
+            name: string("name")?,
+            from: string("from")?,
+            to: string("to")?,
+            outcome,
+            unattended: value.get("unattended").and_then(Value::as_bool).unwrap_or(false),
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let string = |value: &str| Value::String(value.into());
+
+        let mut table = Table::new();
+        table.insert("time".into(), Value::Integer(self.time as i64));
+        table.insert("id".into(), string(&self.id));
+        table.insert("name".into(), string(&self.name));
+        table.insert("from".into(), string(&self.from));
+        table.insert("to".into(), string(&self.to));
+        table.insert("unattended".into(), Value::Boolean(self.unattended));
+
+        let outcome = match self.outcome {
+            UpdateOutcome::Updated => "updated",
+            UpdateOutcome::Scheduled => "scheduled",
+            UpdateOutcome::Failed(ref why) => {
+                table.insert("error".into(), string(why));
+                "failed"
+            }
+        };
+
+        table.insert("outcome".into(), string(outcome));
+        Value::Table(table)
+    }
+}
+
+/// Reads the update history, from the oldest to the newest update.
+///
+/// Entries which can not be understood are skipped.
+pub fn history() -> Result<Vec<HistoryEntry>, HistoryError> {
+    match fs::read_to_string(history_path()?) {
+        Ok(source) => parse(&source),
+        Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(why) => Err(HistoryError::Read(why)),
+    }
+}
+
+/// Appends an entry to the update history.
+pub fn record_update(entry: &HistoryEntry) -> Result<(), HistoryError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path()?)
+        .map_err(HistoryError::Write)?;
+
+    file.write_all(serialize(entry).as_bytes()).map_err(HistoryError::Write)
+}
+
+/// Serializes an entry as an element of the `update` array of tables.
+fn serialize(entry: &HistoryEntry) -> String {
+    let mut table = Table::new();
+    table.insert("update".into(), Value::Array(vec![entry.to_value()]));
+    [&Value::Table(table).to_string(), "\n"].concat()
+}
+
+fn parse(source: &str) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let value = source.parse::<Value>().map_err(HistoryError::Parse)?;
+
+    let entries = value
+        .get("update")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(HistoryEntry::from_value)
+        .collect();
+
+    Ok(entries)
+}
+
+/// Convenience function for fetching the path to the history file.
+fn history_path() -> Result<PathBuf, HistoryError> {
+    xdg::BaseDirectories::with_prefix("com.system76.FirmwareManager")?
+        .place_data_file("history.toml")
+        .map_err(HistoryError::Place)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appended_entries_are_parsed() {
+        let updated = HistoryEntry::new(
+            "b3a3eb7d",
+            "Logitech Unifying Receiver",
+            "RQR12.09_B0030",
+            "RQR12.10_B0032",
+            UpdateOutcome::Updated,
+            true,
+        );
+
+        let failed = HistoryEntry {
+            outcome: UpdateOutcome::Failed("device was unplugged".into()),
+            unattended: false,
+            ..updated.clone()
+        };
+
+        let scheduled = HistoryEntry::new(
+            "system76-system",
+            "System76 Thelio (thelio-r2)",
+            "2021-03-01_4fe6b3f",
+            "2021-06-15_a6ac1c1",
+            UpdateOutcome::Scheduled,
+            false,
+        );
+
+        let source = [serialize(&updated), serialize(&failed), serialize(&scheduled)].concat();
+        assert_eq!(parse(&source).unwrap(), vec![updated, failed, scheduled]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Holds which withhold firmware updates from devices.
+//!
+//! A device may be held at its current version, so that no update is offered for it, or have
+//! specific versions ignored. Holds are persisted in the XDG data directory, and the scanning
+//! functions honor them when determining if a device is upgradeable.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::PathBuf,
+};
+use toml::{value::Table, Value};
+
+/// An error that may occur when loading or saving holds.
+#[derive(Debug, Error)]
+pub enum HoldsError {
+    /// The XDG base directories could not be determined.
+    #[error("failed to get XDG base directory")]
+    BaseDirectory(#[from] xdg::BaseDirectoriesError),
+    /// The data directory could not be created.
+    #[error("failed to create data directory for holds")]
+    Place(#[source] io::Error),
+    /// The holds file exists, but could not be read.
+    #[error("failed to read holds")]
+    Read(#[source] io::Error),
+    /// The holds file could not be written.
+    #[error("failed to write holds")]
+    Write(#[source] io::Error),
+    /// The holds file is not valid TOML.
+    #[error("holds file is invalid")]
+    Parse(#[source] toml::de::Error),
+}
+
+/// A change to the holds of a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HoldChange {
+    /// Hold the device at its current version.
+    Hold,
+    /// Remove the hold of the device, and stop ignoring any of its versions.
+    Release,
+    /// Ignore a version of the device's firmware.
+    Ignore(Box<str>),
+    /// Stop ignoring a version of the device's firmware.
+    Unignore(Box<str>),
+}
+
+/// The holds of a single device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceHold {
+    /// The device is held at its current version.
+    pub held: bool,
+    /// Versions which are not offered as updates.
+    pub ignored: BTreeSet<Box<str>>,
+}
+
+impl DeviceHold {
+    fn is_empty(&self) -> bool { !self.held && self.ignored.is_empty() }
+}
+
+/// All holds, keyed by the identifier of their device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Holds {
+    devices: BTreeMap<Box<str>, DeviceHold>,
+}
+
+impl Holds {
+    /// Loads the holds from the data directory.
+    ///
+    /// If no holds have been saved, there are no holds.
+    pub fn load() -> Result<Self, HoldsError> {
+        match fs::read_to_string(holds_path()?) {
+            Ok(source) => Self::parse(&source),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(why) => Err(HoldsError::Read(why)),
+        }
+    }
+
+    /// Loads the holds, or no holds if they could not be loaded.
+    ///
+    /// Errors are logged.
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|why| {
+            error!("{}", crate::format_error(&why));
+            Self::default()
+        })
+    }
+
+    /// Saves the holds to the data directory.
+    pub fn save(&self) -> Result<(), HoldsError> {
+        fs::write(holds_path()?, self.to_value().to_string()).map_err(HoldsError::Write)
+    }
+
+    /// Applies a change to the holds of a device.
+    pub fn apply(&mut self, device: &str, change: HoldChange) {
+        let hold = self.devices.entry(device.into()).or_default();
+
+        match change {
+            HoldChange::Hold => hold.held = true,
+            HoldChange::Release => *hold = DeviceHold::default(),
+            HoldChange::Ignore(version) => {
+                hold.ignored.insert(version);
+            }
+            HoldChange::Unignore(version) => {
+                hold.ignored.remove(&version);
+            }
+        }
+
+        if hold.is_empty() {
+            self.devices.remove(device);
+        }
+    }
+
+    /// The holds of a device, if it has any.
+    pub fn get(&self, device: &str) -> Option<&DeviceHold> { self.devices.get(device) }
+
+    /// Checks if updating the device to the given version is permitted by its holds.
+    pub fn permits(&self, device: &str, version: &str) -> bool {
+        self.get(device).map_or(true, |hold| !hold.held && !hold.ignored.contains(version))
+    }
+
+    fn parse(source: &str) -> Result<Self, HoldsError> {
+        let value = source.parse::<Value>().map_err(HoldsError::Parse)?;
+        let mut holds = Self::default();
+
+        let devices = match value.get("devices") {
+            Some(Value::Table(devices)) => devices,
+            _ => return Ok(holds),
+        };
+
+        for (device, value) in devices {
+            let hold = DeviceHold {
+                held: value.get("held").and_then(Value::as_bool).unwrap_or(false),
+                ignored: value
+                    .get("ignored")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(Box::from)
+                    .collect(),
+            };
+
+            if !hold.is_empty() {
+                holds.devices.insert(device.as_str().into(), hold);
+            }
+        }
+
+        Ok(holds)
+    }
+
+    fn to_value(&self) -> Value {
+        let devices = self
+            .devices
+            .iter()
+            .map(|(device, hold)| {
+                let ignored = hold.ignored.iter().map(|version| Value::String(version.to_string()));
+
+                let mut table = Table::new();
+                table.insert("held".into(), Value::Boolean(hold.held));
+                table.insert("ignored".into(), Value::Array(ignored.collect()));
+
+                (device.to_string(), Value::Table(table))
+            })
+            .collect();
+
+        let mut table = Table::new();
+        table.insert("devices".into(), Value::Table(devices));
+        Value::Table(table)
+    }
+}
+
+/// Convenience function for fetching the path to the holds file.
+fn holds_path() -> Result<PathBuf, HoldsError> {
+    xdg::BaseDirectories::with_prefix("com.system76.FirmwareManager")?
+        .place_data_file("holds.toml")
+        .map_err(HoldsError::Place)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits() {
+        let mut holds = Holds::default();
+        assert!(holds.permits("dock", "1.0"));
+
+        holds.apply("dock", HoldChange::Ignore("1.1".into()));
+        assert!(holds.permits("dock", "1.0"));
+        assert!(!holds.permits("dock", "1.1"));
+        assert!(holds.permits("receiver", "1.1"));
+
+        holds.apply("dock", HoldChange::Hold);
+        assert!(!holds.permits("dock", "1.0"));
+
+        holds.apply("dock", HoldChange::Release);
+        assert!(holds.permits("dock", "1.1"));
+        assert_eq!(holds, Holds::default());
+    }
+
+    #[test]
+    fn unignored_devices_are_removed() {
+        let mut holds = Holds::default();
+        holds.apply("dock", HoldChange::Ignore("1.1".into()));
+        holds.apply("dock", HoldChange::Unignore("1.1".into()));
+        assert!(holds.get("dock").is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut holds = Holds::default();
+        holds.apply("dock", HoldChange::Hold);
+        holds.apply("receiver", HoldChange::Ignore("RQR12.10_B0032".into()));
+
+        let source = holds.to_value().to_string();
+        assert_eq!(Holds::parse(&source).unwrap(), holds);
+    }
+}
@@ -0,0 +1,247 @@
+//! An inventory of the firmware installed on a system, which may be exported as JSON or CSV.
+//!
+//! Devices are written in a stable order, with one field per line in JSON, so that the
+//! inventories of two systems may be compared with `diff`. JSON requires the `serde_json`
+//! feature.
+
+use crate::{Backend, FirmwareInfo, FwupdSignal};
+#[cfg(feature = "serde_json")]
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+/// The vendor of the firmware which the system76-firmware daemon provides.
+pub const SYSTEM76_VENDOR: &str = "System76";
+
+/// A format which an inventory may be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryFormat {
+    /// Comma-separated values, with a header row.
+    Csv,
+    /// A JSON array of objects.
+    #[cfg(feature = "serde_json")]
+    Json,
+}
+
+impl InventoryFormat {
+    /// The format which a file is named after, by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(InventoryFormat::Csv),
+            #[cfg(feature = "serde_json")]
+            "json" => Some(InventoryFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A device in the inventory, and the state of its firmware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryEntry {
+    /// The name of the device.
+    pub name: Box<str>,
+    /// The vendor of the device.
+    pub vendor: Box<str>,
+    /// The currently-installed version.
+    pub current: Box<str>,
+    /// The latest version of firmware for the device, if known.
+    pub latest: Option<Box<str>>,
+    /// The firmware service which manages the device.
+    pub backend: Backend,
+    /// A newer version than the installed version is available, even if it is held.
+    pub update_available: bool,
+}
+
+impl InventoryEntry {
+    /// An entry for a device which the given firmware service manages.
+    pub fn new(info: &FirmwareInfo, vendor: &str, backend: Backend) -> Self {
+        Self {
+            name: info.name.clone(),
+            vendor: vendor.into(),
+            current: info.current.clone(),
+            latest: info.latest.clone(),
+            backend,
+            update_available: info.latest.as_ref().map_or(false, |latest| *latest != info.current),
+        }
+    }
+
+    /// An entry for a device which was found by fwupd.
+    pub fn fwupd(signal: &FwupdSignal) -> Self {
+        Self::new(&signal.info, &signal.device.vendor, Backend::Fwupd)
+    }
+
+    /// An entry for System76 system firmware, or a Thelio I/O board.
+    pub fn system76(info: &FirmwareInfo) -> Self {
+        Self::new(info, SYSTEM76_VENDOR, Backend::System76)
+    }
+}
+
+/// The firmware of every device that was found on a system.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    /// Identifies the system by its board vendor, name, and version.
+    pub board: Box<str>,
+    /// Each device in the inventory.
+    pub entries: Vec<InventoryEntry>,
+}
+
+impl Inventory {
+    /// Creates an empty inventory for this system.
+    ///
+    /// The board is left empty if it could not be identified.
+    pub fn new() -> Self {
+        let board = crate::system_board_identity().unwrap_or_else(|why| {
+            warn!("failed to identify the system board: {}", why);
+            String::new()
+        });
+
+        Self { board: board.into(), entries: Vec::new() }
+    }
+
+    /// Adds a device to the inventory.
+    pub fn push(&mut self, entry: InventoryEntry) { self.entries.push(entry); }
+
+    /// Writes the inventory to a file, in the given format.
+    pub fn export(&self, path: &Path, format: InventoryFormat) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        self.write(&mut file, format)?;
+        file.sync_all()
+    }
+
+    /// Writes the inventory in the given format.
+    ///
+    /// Entries are sorted by their backend, name, and version, and every entry includes the
+    /// board, so that the output does not depend on the order that devices were found in.
+    pub fn write<W: Write>(&self, writer: &mut W, format: InventoryFormat) -> io::Result<()> {
+        let mut entries: Vec<&InventoryEntry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| {
+            (a.backend.name(), &a.name, &a.current).cmp(&(b.backend.name(), &b.name, &b.current))
+        });
+
+        match format {
+            InventoryFormat::Csv => self.write_csv(writer, &entries),
+            #[cfg(feature = "serde_json")]
+            InventoryFormat::Json => self.write_json(writer, &entries),
+        }
+    }
+
+    fn write_csv<W: Write>(&self, writer: &mut W, entries: &[&InventoryEntry]) -> io::Result<()> {
+        writeln!(writer, "board,backend,vendor,name,current,latest,update_available")?;
+
+        for entry in entries {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                csv_field(&self.board),
+                entry.backend.name(),
+                csv_field(&entry.vendor),
+                csv_field(&entry.name),
+                csv_field(&entry.current),
+                csv_field(entry.latest.as_deref().unwrap_or_default()),
+                entry.update_available
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde_json")]
+    fn write_json<W: Write>(&self, writer: &mut W, entries: &[&InventoryEntry]) -> io::Result<()> {
+        let entries: Vec<Value> = entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "board": self.board,
+                    "backend": entry.backend.name(),
+                    "vendor": entry.vendor,
+                    "name": entry.name,
+                    "current": entry.current,
+                    "latest": entry.latest,
+                    "update_available": entry.update_available,
+                })
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(&mut *writer, &entries)?;
+        writeln!(writer)
+    }
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote, or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        ["\"", &field.replace('"', "\"\""), "\""].concat()
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory() -> Inventory {
+        let entry = |name: &str, vendor: &str, current: &str, latest: Option<&str>, backend| {
+            InventoryEntry {
+                name: name.into(),
+                vendor: vendor.into(),
+                current: current.into(),
+                latest: latest.map(Box::from),
+                backend,
+                update_available: latest.map_or(false, |latest| latest != current),
+            }
+        };
+
+        Inventory {
+            board: "System76 Thelio (thelio-r2)".into(),
+            entries: vec![
+                entry("Samsung SSD 970 \"EVO\"", "Samsung", "2B2QEXE7", None, Backend::Fwupd),
+                entry("Thelio Io", "System76", "1.0", Some("1.1"), Backend::System76),
+                entry("Intel, Corp. ME", "Intel", "12.0", Some("12.0"), Backend::Fwupd),
+            ],
+        }
+    }
+
+    fn write(format: InventoryFormat) -> String {
+        let mut output = Vec::new();
+        inventory().write(&mut output, format).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            write(InventoryFormat::Csv),
+            "board,backend,vendor,name,current,latest,update_available\n\
+             System76 Thelio (thelio-r2),fwupd,Intel,\"Intel, Corp. ME\",12.0,12.0,false\n\
+             System76 Thelio (thelio-r2),fwupd,Samsung,\"Samsung SSD 970 \"\"EVO\"\"\",2B2QEXE7,,false\n\
+             System76 Thelio (thelio-r2),system76,System76,Thelio Io,1.0,1.1,true\n"
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json() {
+        let json = write(InventoryFormat::Json);
+        let entries: Vec<&str> = json.split("  },\n").collect();
+
+        assert_eq!(entries.len(), 3);
+        assert!(json.starts_with("[\n  {\n    \"board\": \"System76 Thelio (thelio-r2)\",\n"));
+        assert!(entries[0].contains("    \"name\": \"Intel, Corp. ME\",\n"));
+        assert!(entries[1].contains("    \"name\": \"Samsung SSD 970 \\\"EVO\\\"\",\n"));
+        assert!(entries[1].contains("    \"latest\": null,\n"));
+        assert!(entries[2].ends_with("    \"update_available\": true\n  }\n]\n"));
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(InventoryFormat::from_path(Path::new("a.CSV")), Some(InventoryFormat::Csv));
+        assert_eq!(InventoryFormat::from_path(Path::new("a")), None);
+
+        #[cfg(feature = "serde_json")]
+        assert_eq!(InventoryFormat::from_path(Path::new("a.json")), Some(InventoryFormat::Json));
+    }
+}
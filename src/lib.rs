@@ -4,6 +4,22 @@
 //!
 //! The firmware manager core manages all firmware tasks in an event loop, and provides a handful of
 //! useful capabilities that are useful to frontends of the firmware manager.
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature, signals, firmware information, and errors implement `Serialize`,
+//! with a JSON representation which frontends and scripts may rely upon:
+//!
+//! - Fields and enum variants are named in `snake_case`. Variants without data are strings,
+//!   such as `"disabled"`, and variants with data are objects, such as `{"found": 2}`.
+//! - Entities are integers, which are never zero, and are only valid for the current scan.
+//! - A `FirmwareSignal` is an object whose `signal` field names the variant, such as
+//!   `{"signal": "download_begin", "entity": 4294967297, "size": 1024}`.
+//! - Errors are objects with the `kind` of error and its `message`, including its causes.
+//! - fwupd devices and releases are reduced to the fields which frontends use, and the digests
+//!   of System76 firmware are omitted.
+//!
+//! The JSON format of the firmware inventory is enabled by the `serde_json` feature.
 
 #[macro_use]
 extern crate thiserror;
@@ -12,16 +28,47 @@ extern crate log;
 #[macro_use]
 extern crate shrinkwraprs;
 
+mod activation;
+mod advisories;
 mod cache;
+mod changelog;
+mod client;
+mod config;
+mod found;
+mod history;
+mod holds;
+mod inventory;
+mod notified;
+mod polkit;
+mod preflight;
+mod refresh;
+mod scan;
+mod security;
+#[cfg(feature = "serde")]
+mod serialization;
+mod services;
 mod timestamp;
 mod udev;
 mod users;
+mod verify;
 mod version_sorting;
 
 mod fwupd;
 mod system76;
 
-pub use self::users::user_is_admin;
+pub use self::{
+    activation::{fwupd_activate, fwupd_pending_action, fwupd_unlock, PendingAction},
+    changelog::{Changelog, ChangelogEntry, ReleaseTrust, TrustDetail, TrustWarning, Urgency},
+    config::{AutoUpdateConfig, BackendsConfig, Config, ConfigError, InvalidKey},
+    found::{FoundDevice, PendingUpdate, UpdateStatus},
+    history::{history, record_update, HistoryEntry, HistoryError, UpdateOutcome},
+    holds::{DeviceHold, HoldChange, Holds, HoldsError},
+    inventory::{Inventory, InventoryEntry, InventoryFormat, SYSTEM76_VENDOR},
+    polkit::Authorization,
+    preflight::{preflight_check, system_on_battery, PreflightError},
+    refresh::{MetadataRefresh, RefreshConditions, RefreshDeferral},
+    users::{user_authorization, user_is_admin, user_is_permitted, user_request_authorization},
+};
 
 pub use fwupd_dbus::{
     Client as FwupdClient, Device as FwupdDevice, Error as FwupdError, Release as FwupdRelease,
@@ -32,14 +79,21 @@ pub use system76_firmware_daemon::{
     SystemInfo as S76SystemInfo, ThelioIoInfo,
 };
 
-use self::version_sorting::sort_versions;
-pub use self::{fwupd::*, system76::*, udev::usb_hotplug_event_loop};
+use self::{client::ServiceClient, version_sorting::sort_versions};
+pub use self::{
+    fwupd::*, scan::*, security::*, services::*, system76::*, udev::usb_hotplug_event_loop,
+    verify::*,
+};
 pub use slotmap::DefaultKey as Entity;
 use slotmap::{SlotMap, SparseSecondaryMap};
 use std::{
     io,
-    process::Command,
-    sync::{mpsc::Receiver, Arc},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
 };
 pub use system76_firmware_daemon::Client as System76Client;
 
@@ -52,30 +106,81 @@ pub enum Error {
     /// Errors specific to system76 devices.
     #[error("error in system76-firmware client")]
     System76(#[from] System76Error),
+    /// The firmware service required by a request is not available.
+    #[error("the {} firmware service is not available", .0.name())]
+    Unavailable(Backend),
+    /// The host security attributes could not be fetched from fwupd.
+    #[error("failed to fetch host security attributes")]
+    Security(#[source] dbus::Error),
+    /// A Thelio I/O board did not report the revision that it was flashed with.
+    #[error("Thelio I/O board {} was not updated to revision {}", .0, .1)]
+    ThelioIoBoard(Box<str>, Box<str>),
+    /// fwupd could not be asked to verify the firmware of a device.
+    #[error("failed to verify firmware")]
+    Verify(#[source] dbus::Error),
+    /// The new firmware of a device could not be activated.
+    #[error("failed to activate device")]
+    Activate(#[source] dbus::Error),
+    /// A locked device could not be unlocked.
+    #[error("failed to unlock device")]
+    Unlock(#[source] dbus::Error),
 }
 
 /// A request for the background event loop to perform.
 #[derive(Debug)]
 pub enum FirmwareEvent {
+    /// Activate the new firmware of a fwupd-compatible device which needs activation.
+    Activate(Entity, Arc<FwupdDevice>),
+
     /// Upgrade the firmware of a fwupd-compatible device.
     Fwupd(Entity, Arc<FwupdDevice>, Arc<FwupdRelease>),
 
+    /// Upgrade the firmware of a fwupd-compatible device without asking the user, which is
+    /// recorded as such in the update history.
+    FwupdUnattended(Entity, Arc<FwupdDevice>, Arc<FwupdRelease>),
+
+    /// A firmware service started or stopped, which the event loop checks for when woken.
+    ServicesChanged,
+
     /// Stop processing events.
     Stop,
 
+    /// Refresh the fwupd remote metadata, even if it is not due or would be deferred, and then
+    /// search for available firmware devices.
+    Refresh,
+
     /// Upgrade system firmware for System76 systems.
-    S76System(Entity, System76Digest),
+    S76System(Entity, S76SystemUpdate),
 
     /// Search for available firmware devices.
     Scan,
 
-    /// Upgrade the firmware of Thelio I/O boarods.
-    ThelioIo(Entity, System76Digest),
+    /// Fetch the host security attributes from fwupd.
+    Security,
+
+    /// Upgrade the firmware of a Thelio I/O board.
+    ThelioIo(Entity, ThelioIoUpdate),
+
+    /// Unlock a locked fwupd-compatible device, so that it may be updated.
+    Unlock(Entity, Arc<FwupdDevice>),
+
+    /// Cancel the System76 system firmware update which is scheduled for the next boot.
+    Unschedule(Entity),
+
+    /// Verify the installed firmware of a fwupd-compatible device against its known checksums.
+    Verify(Entity, Arc<FwupdDevice>),
+
+    /// Record the installed firmware of a fwupd-compatible device as trusted.
+    VerifyUpdate(Entity, Arc<FwupdDevice>),
 }
 
 /// Information about a device and its current and latest firmware.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FirmwareInfo {
+    /// A stable identifier of this device, which holds are recorded against.
+    pub id: Box<str>,
+
     /// The name of this device.
     pub name: Box<str>,
 
@@ -87,6 +192,20 @@ pub struct FirmwareInfo {
 
     /// The time required for this firmware to be flashed, in seconds.
     pub install_duration: u32,
+
+    /// A newer version is available, but a hold withholds it.
+    pub held: bool,
+
+    /// The releases of firmware for this device, from the newest to the oldest.
+    pub changelog: Changelog,
+}
+
+impl FirmwareInfo {
+    /// The highest urgency of the releases which are newer than the installed firmware.
+    pub fn urgency(&self) -> Urgency { self.changelog.urgency_since(&self.current) }
+
+    /// Checks if a release which is newer than the installed firmware fixes security issues.
+    pub fn security_update(&self) -> bool { self.changelog.security_fixes_since(&self.current) }
 }
 
 /// A collection of all firmware device entities that a frontend is managing.
@@ -151,6 +270,14 @@ pub enum FirmwareSignal {
     /// Progress updates on firmware downloads.
     DownloadUpdate(Entity, usize),
 
+    /// A firmware service became available after it was lost or failed to connect.
+    ///
+    /// Devices are scanned again after this signal is sent.
+    BackendAvailable(Backend),
+
+    /// A firmware service left the system bus.
+    BackendLost(Backend),
+
     /// An error occurred
     Error(Option<Entity>, Error),
 
@@ -160,103 +287,239 @@ pub enum FirmwareSignal {
     /// Devices are being scanned
     Scanning,
 
-    /// Signals when scanning has completed.
-    ScanningComplete,
+    /// Signals when scanning has completed, with the outcome of scanning each firmware service.
+    ScanningComplete(ScanReport),
+
+    /// The host security attributes were fetched from fwupd.
+    Security(SecurityReport),
 
     /// System firmware was scheduled for installation.
     SystemScheduled,
 
     /// System76 system firmware was discovered.
-    S76System(FirmwareInfo, Option<(System76Digest, System76Changelog)>),
+    ///
+    /// The last field is `true` if an update is already scheduled for the next boot.
+    S76System(FirmwareInfo, Option<System76Digest>, bool),
 
-    /// Thelio I/O firmware was discovered.
-    ThelioIo(FirmwareInfo, Option<System76Digest>),
+    /// A Thelio I/O board was discovered, with the update available for it.
+    ThelioIo(FirmwareInfo, Option<ThelioIoUpdate>),
+
+    /// The installed firmware of a device was verified, or recorded as trusted.
+    Verification(Entity, Verification),
 }
 
 /// An event loop that should be run in the background, as this function will block until
 /// the stop signal is received.
+///
+/// The configuration is loaded from the configuration file. Firmware services are detected on
+/// the system bus, and reconnected to when they restart.
 pub fn event_loop<F: Fn(FirmwareSignal)>(receiver: Receiver<FirmwareEvent>, sender: F) {
-    let s76 = get_client("system76", s76_firmware_is_active, System76Client::new);
-
-    let fwupd = {
-        // Use Ping() to wake up fwupd, and to check if it exists.
-        let fwupd_connect = || {
-            let client = FwupdClient::new()?;
-            client.ping()?;
-            Ok(client)
-        };
+    event_loop_with_config(&Config::load_or_default(), receiver, sender)
+}
 
-        get_client::<_, _, fwupd_dbus::Error>("fwupd", || true, fwupd_connect)
+/// An event loop which uses the given configuration.
+///
+/// Frontends which load the configuration themselves, to report its errors, may pass it here.
+pub fn event_loop_with_config<F: Fn(FirmwareSignal)>(
+    config: &Config,
+    receiver: Receiver<FirmwareEvent>,
+    sender: F,
+) {
+    match DbusDetector::system() {
+        Ok(detector) => {
+            // Events of the frontend and of the watch are received from the same channel.
+            let (wake, events) = mpsc::channel();
+
+            if let Err(why) = detector.watch(wake.clone()) {
+                error!("failed to watch for firmware service restarts: {}", why);
+            }
+
+            forward_events(receiver, wake);
+            event_loop_with_detector(&detector, config, events, sender)
+        }
+        Err(why) => {
+            error!("failed to connect to the system bus: {}", why);
+            event_loop_with_detector(&|_: &str| false, config, receiver, sender)
+        }
+    }
+}
+
+/// An event loop which detects the availability of firmware services with the given detector.
+///
+/// Services which start or stop, as reported by `ServiceDetector::changes`, are reconnected to
+/// with backoff. When a service becomes available again, the frontend is sent a
+/// `FirmwareSignal::BackendAvailable` signal, and devices are scanned again.
+///
+/// The loop blocks until it receives an event, or a reconnection attempt is due. Changes are
+/// checked for whenever it wakes, so a detector which observes them should also send a
+/// `FirmwareEvent::ServicesChanged` through the receiver.
+///
+/// Backends which are disabled in the configuration are never connected to.
+pub fn event_loop_with_detector<D: ServiceDetector, F: Fn(FirmwareSignal)>(
+    detector: &D,
+    config: &Config,
+    receiver: Receiver<FirmwareEvent>,
+    sender: F,
+) {
+    let sender = &sender;
+    let available = |backend: Backend| {
+        config.backend_enabled(backend) && detector.is_available(backend.service())
     };
 
-    while let Ok(event) = receiver.recv() {
-        trace!("event loop received firmware event: {:?}", event);
-        match event {
-            FirmwareEvent::Scan => {
-                let sender = &sender;
-                sender(FirmwareSignal::Scanning);
+    let mut s76 = ServiceClient::new(Backend::System76, available(Backend::System76), || {
+        System76Client::new().map_err(Error::from)
+    });
 
-                if let Some(ref client) = s76 {
-                    s76_scan(client, sender);
-                }
+    let mut fwupd = ServiceClient::new(Backend::Fwupd, available(Backend::Fwupd), fwupd_connect);
 
-                if let Some(ref client) = fwupd {
-                    if let Err(why) = fwupd_updates(client) {
-                        eprintln!("failed to update fwupd remotes: {}", why);
-                    }
-                    fwupd_scan(client, sender);
-                }
+    loop {
+        for change in detector.changes() {
+            let backend = match change {
+                ServiceChange::Started(backend) | ServiceChange::Stopped(backend) => backend,
+            };
 
-                let _ = sender(FirmwareSignal::ScanningComplete);
+            if !config.backend_enabled(backend) {
+                continue;
             }
-            FirmwareEvent::Fwupd(entity, device, release) => {
-                let flags = fwupd_dbus::InstallFlags::empty();
-                let event = match fwupd.as_ref().map(|fwupd| {
-                    fwupd.update_device_with_release(
-                        &device,
-                        &release,
-                        flags,
-                        Some(|download_event| {
-                            use fwupd_dbus::FlashEvent::*;
-                            let event = match download_event {
-                                DownloadUpdate(progress) => {
-                                    FirmwareSignal::DownloadUpdate(entity, progress)
-                                }
-                                DownloadInitiate(size) => {
-                                    FirmwareSignal::DownloadBegin(entity, size)
-                                }
-                                DownloadComplete => FirmwareSignal::DownloadComplete(entity),
-                                FlashInProgress => FirmwareSignal::DeviceFlashing(entity),
-                                VerifyingChecksum => return,
-                            };
-
-                            sender(event);
-                        }),
-                    )
-                }) {
-                    Some(Ok(_)) => FirmwareSignal::DeviceUpdated(entity),
-                    Some(Err(why)) => FirmwareSignal::Error(Some(entity), why.into()),
-                    None => panic!("fwupd event assigned to non-fwupd button"),
-                };
 
-                sender(event);
+            match change {
+                ServiceChange::Started(Backend::System76) => s76.started(),
+                ServiceChange::Started(Backend::Fwupd) => fwupd.started(),
+                // Once its name is released, a service is only available if it is activatable.
+                ServiceChange::Stopped(Backend::System76) => {
+                    s76.stopped(sender, available(Backend::System76))
+                }
+                ServiceChange::Stopped(Backend::Fwupd) => {
+                    fwupd.stopped(sender, available(Backend::Fwupd))
+                }
             }
-            FirmwareEvent::S76System(entity, digest) => {
-                match s76.as_ref().map(|client| client.schedule(&digest)) {
-                    Some(Ok(_)) => sender(FirmwareSignal::SystemScheduled),
-                    Some(Err(why)) => sender(FirmwareSignal::Error(Some(entity), why.into())),
-                    None => panic!("thelio event assigned to non-thelio button"),
+        }
+
+        // Both are evaluated so that each client may make its reconnection attempt.
+        let s76_returned = s76.retry(sender);
+        let fwupd_returned = fwupd.retry(sender);
+        if s76_returned || fwupd_returned {
+            scan(config, &mut s76, &mut fwupd, sender, false);
+        }
+
+        // The loop only wakes up on its own when a reconnection attempt is due.
+        let event = match s76.retry_in().into_iter().chain(fwupd.retry_in()).min() {
+            Some(delay) => match receiver.recv_timeout(delay) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match receiver.recv() {
+                Ok(event) => event,
+                Err(_) => break,
+            },
+        };
+
+        trace!("event loop received firmware event: {:?}", event);
+        match event {
+            FirmwareEvent::Activate(entity, device) => {
+                let result =
+                    fwupd.require().and_then(|_| fwupd_activate(&device).map_err(Error::Activate));
+
+                // The device reports different firmware and flags once activated.
+                match result {
+                    Ok(()) => scan(config, &mut s76, &mut fwupd, sender, false),
+                    Err(why) => sender(FirmwareSignal::Error(Some(entity), why)),
                 }
             }
-            FirmwareEvent::ThelioIo(entity, digest) => {
+            // The changes are collected at the beginning of the loop.
+            FirmwareEvent::ServicesChanged => (),
+            FirmwareEvent::Refresh => scan(config, &mut s76, &mut fwupd, sender, true),
+            FirmwareEvent::Scan => scan(config, &mut s76, &mut fwupd, sender, false),
+            FirmwareEvent::Security => {
+                let result =
+                    fwupd.require().and_then(|_| host_security_report().map_err(Error::Security));
+
+                sender(match result {
+                    Ok(report) => FirmwareSignal::Security(report),
+                    Err(why) => FirmwareSignal::Error(None, why),
+                });
+            }
+            FirmwareEvent::Fwupd(entity, device, release) => {
+                fwupd_update(&mut fwupd, entity, &device, &release, false, sender)
+            }
+            FirmwareEvent::FwupdUnattended(entity, device, release) => {
+                fwupd_update(&mut fwupd, entity, &device, &release, true, sender)
+            }
+            FirmwareEvent::S76System(entity, update) => {
+                let result = s76
+                    .require()
+                    .and_then(|client| client.schedule(&update.digest).map_err(Error::from));
+
+                s76_record_update(
+                    S76_SYSTEM_ID,
+                    &update.name,
+                    &update.current,
+                    &update.version,
+                    &result,
+                    UpdateOutcome::Scheduled,
+                );
+
+                sender(match result {
+                    Ok(_) => FirmwareSignal::SystemScheduled,
+                    Err(why) => FirmwareSignal::Error(Some(entity), why),
+                });
+            }
+            FirmwareEvent::ThelioIo(entity, update) => {
                 sender(FirmwareSignal::DeviceFlashing(entity));
-                let event = match s76.as_ref().map(|client| client.thelio_io_update(&digest)) {
-                    Some(Ok(_)) => FirmwareSignal::DeviceUpdated(entity),
-                    Some(Err(why)) => FirmwareSignal::Error(Some(entity), why.into()),
-                    None => panic!("thelio event assigned to non-thelio button"),
-                };
+                let result =
+                    s76.require().and_then(|client| thelio_io_update_board(client, &update));
+
+                s76_record_update(
+                    &thelio_io_id(&update.board),
+                    &update.name,
+                    &update.current,
+                    &update.revision,
+                    &result,
+                    UpdateOutcome::Updated,
+                );
+
+                sender(match result {
+                    Ok(_) => FirmwareSignal::DeviceUpdated(entity),
+                    Err(why) => FirmwareSignal::Error(Some(entity), why),
+                });
+            }
+            FirmwareEvent::Unlock(entity, device) => {
+                let result =
+                    fwupd.require().and_then(|_| fwupd_unlock(&device).map_err(Error::Unlock));
 
-                sender(event);
+                match result {
+                    Ok(()) => scan(config, &mut s76, &mut fwupd, sender, false),
+                    Err(why) => sender(FirmwareSignal::Error(Some(entity), why)),
+                }
+            }
+            FirmwareEvent::Unschedule(entity) => {
+                let result =
+                    s76.require().and_then(|client| client.unschedule().map_err(Error::from));
+
+                match result {
+                    Ok(_) => scan(config, &mut s76, &mut fwupd, sender, false),
+                    Err(why) => sender(FirmwareSignal::Error(Some(entity), why)),
+                }
+            }
+            FirmwareEvent::Verify(entity, device) => {
+                let result =
+                    fwupd.require().and_then(|_| fwupd_verify(&device).map_err(Error::Verify));
+
+                sender(match result {
+                    Ok(verification) => FirmwareSignal::Verification(entity, verification),
+                    Err(why) => FirmwareSignal::Error(Some(entity), why),
+                });
+            }
+            FirmwareEvent::VerifyUpdate(entity, device) => {
+                let result = fwupd
+                    .require()
+                    .and_then(|_| fwupd_verify_update(&device).map_err(Error::Verify));
+
+                sender(match result {
+                    Ok(verification) => FirmwareSignal::Verification(entity, verification),
+                    Err(why) => FirmwareSignal::Error(Some(entity), why),
+                });
             }
             FirmwareEvent::Stop => {
                 trace!("received quit signal");
@@ -266,6 +529,174 @@ pub fn event_loop<F: Fn(FirmwareSignal)>(receiver: Receiver<FirmwareEvent>, send
     }
 }
 
+/// Updates a fwupd device to the given release, reporting its progress, and recording the update
+/// in the update history.
+fn fwupd_update<F: Fn(FirmwareSignal)>(
+    fwupd: &mut ServiceClient<FwupdClient>,
+    entity: Entity,
+    device: &FwupdDevice,
+    release: &FwupdRelease,
+    unattended: bool,
+    sender: &F,
+) {
+    let flags = fwupd_dbus::InstallFlags::empty();
+    let result = fwupd.require().and_then(|fwupd| {
+        let result = fwupd.update_device_with_release(
+            device,
+            release,
+            flags,
+            Some(|download_event| {
+                use fwupd_dbus::FlashEvent::*;
+                let event = match download_event {
+                    DownloadUpdate(progress) => FirmwareSignal::DownloadUpdate(entity, progress),
+                    DownloadInitiate(size) => FirmwareSignal::DownloadBegin(entity, size),
+                    DownloadComplete => FirmwareSignal::DownloadComplete(entity),
+                    FlashInProgress => FirmwareSignal::DeviceFlashing(entity),
+                    VerifyingChecksum => return,
+                };
+
+                sender(event);
+            }),
+        );
+
+        fwupd_record_update(device, release, &result, unattended);
+        result.map_err(Error::from)
+    });
+
+    sender(match result {
+        Ok(_) => FirmwareSignal::DeviceUpdated(entity),
+        Err(why) => FirmwareSignal::Error(Some(entity), why),
+    });
+}
+
+/// Forwards the events of a frontend to the channel of the event loop, on a background thread.
+///
+/// The event loop is stopped once the frontend drops its sender.
+fn forward_events(receiver: Receiver<FirmwareEvent>, sender: Sender<FirmwareEvent>) {
+    thread::spawn(move || {
+        for event in receiver {
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+
+        let _ = sender.send(FirmwareEvent::Stop);
+    });
+}
+
+/// Scans every connected firmware service for devices.
+///
+/// Holds are loaded on each scan, so that changes made by a frontend take effect. The fwupd
+/// remote metadata is refreshed first if it is due, or regardless if `force` is set.
+fn scan<F: Fn(FirmwareSignal)>(
+    config: &Config,
+    s76: &mut ServiceClient<System76Client>,
+    fwupd: &mut ServiceClient<FwupdClient>,
+    sender: &F,
+    force: bool,
+) {
+    sender(FirmwareSignal::Scanning);
+
+    let holds = Holds::load_or_default();
+
+    let status = |backend: Backend, default: ScanStatus| {
+        if config.backend_enabled(backend) {
+            default
+        } else {
+            ScanStatus::Disabled
+        }
+    };
+
+    let system76 = status(
+        Backend::System76,
+        s76.require()
+            .ok()
+            .map_or(ScanStatus::Unavailable, |client| s76_scan(client, &holds, sender)),
+    );
+
+    let mut refresh_deferred = None;
+
+    let fwupd = status(
+        Backend::Fwupd,
+        fwupd.require().ok().map_or(ScanStatus::Unavailable, |client| {
+            match fwupd_updates(client, config.refresh_interval(), force) {
+                Ok(MetadataRefresh::Deferred(reason)) => refresh_deferred = Some(reason),
+                Ok(_) => (),
+                Err(why) => eprintln!("failed to update fwupd remotes: {}", why),
+            }
+
+            fwupd_scan(client, &holds, sender)
+        }),
+    );
+
+    sender(FirmwareSignal::ScanningComplete(ScanReport { fwupd, system76, refresh_deferred }));
+}
+
+/// Checks if the interval between refreshes of the fwupd remote metadata has passed, so that the
+/// next scan refreshes it.
+pub fn metadata_refresh_due(config: &Config) -> bool {
+    let interval = config.refresh_interval().as_secs();
+    timestamp::exceeded(timestamp::METADATA_REFRESH, interval).unwrap_or(true)
+}
+
+/// Checks if the interval between the notifier's checks for updates has passed.
+///
+/// If the user asked to be reminded later, the check is instead due once the snooze interval has
+/// passed. The check is not recorded until `notification_checked` is called, so that a check which
+/// fails is attempted again.
+pub fn notification_check_due(config: &Config) -> bool {
+    notification_cache().map_or(true, |dir| notified::check_due_at(&dir, config))
+}
+
+/// Checks if the user asked to be reminded of updates later, and the snooze interval has not yet
+/// passed.
+pub fn notification_snoozed(config: &Config) -> bool {
+    notification_cache().map_or(false, |dir| notified::snoozed_at(&dir, config))
+}
+
+/// Records that the notifier has checked for updates, after a firmware service was scanned.
+///
+/// The next check is due once the interval between checks has passed, and the snooze is cleared.
+pub fn notification_checked() {
+    if let Some(dir) = notification_cache() {
+        notified::checked_at(&dir);
+    }
+}
+
+/// Snoozes the notifier, so that the user is reminded of updates once the snooze interval has
+/// passed, rather than the interval between checks.
+pub fn notification_snooze() {
+    if let Some(dir) = notification_cache() {
+        notified::snooze_at(&dir);
+    }
+}
+
+/// Checks if the notifier should notify about the given updates, which are pairs of device IDs
+/// and the versions which are offered for them.
+///
+/// Updates are only notified about if one of them was not part of the last notification, or if
+/// the reminder interval has passed since the last notification. Nothing is recorded until
+/// `notification_shown` is called, so that updates are notified about again if the notification
+/// could not be shown.
+pub fn notification_required(config: &Config, updates: &[(&str, &str)]) -> bool {
+    notification_cache().map_or(true, |dir| notified::required_at(&dir, config, updates))
+}
+
+/// Records that the user was notified about the given updates.
+///
+/// The updates replace those of the last notification, so that updates which were installed are
+/// forgotten, and the reminder interval starts again.
+pub fn notification_shown(updates: &[(&str, &str)]) {
+    if let Some(dir) = notification_cache() {
+        notified::shown_at(&dir, updates);
+    }
+}
+
+/// The cache directory, in which the notifier records its state.
+fn notification_cache() -> Option<PathBuf> {
+    cache::cache_dir().map_err(|why| error!("{}", format_error(&why))).ok()
+}
+
 /// Function for getting a timmed string from a file.
 fn read_trimmed(path: &str) -> io::Result<String> {
     let mut vendor = std::fs::read_to_string(path)?;
@@ -294,8 +725,9 @@ pub(crate) fn system_board_identity() -> io::Result<String> {
 }
 
 /// Generic function for attaining a DBus client connection to a firmware service.
-pub fn get_client<F, T, E>(name: &str, is_active: fn() -> bool, connect: F) -> Option<T>
+pub fn get_client<A, F, T, E>(name: &str, is_active: A, connect: F) -> Option<T>
 where
+    A: FnOnce() -> bool,
     F: FnOnce() -> Result<T, E>,
     E: std::fmt::Display,
 {
@@ -306,35 +738,8 @@ where
     }
 }
 
-/// Checks if a systemd service is active.
-fn systemd_service_is_active(name: &str) -> bool {
-    Command::new("systemctl")
-        .args(&["-q", "is-active", name])
-        .status()
-        .map_err(|why| error!("{}", why))
-        .ok()
-        .map_or(false, |status| status.success())
-}
-
-/// Finds the lowest revision from anything that is or may become an `Iterator` of strings.
-fn lowest_revision<'a, I: IntoIterator<Item = &'a str>>(list: I) -> &'a str {
-    use std::cmp::Ordering;
-    let mut list = list.into_iter();
-    match list.next() {
-        Some(mut lowest_revision) => {
-            for rev in list {
-                if human_sort::compare(lowest_revision, &rev) == Ordering::Greater {
-                    lowest_revision = &rev;
-                }
-            }
-            lowest_revision
-        }
-        None => "",
-    }
-}
-
-/// Helper for formatting errors for logs.
-fn format_error<E: std::error::Error>(why: E) -> String {
+/// Formats an error, followed by each of its causes.
+pub fn format_error(why: &dyn std::error::Error) -> String {
     let mut error_message = format!("{}", why);
     let mut cause = why.source();
     while let Some(error) = cause {
@@ -344,21 +749,3 @@ fn format_error<E: std::error::Error>(why: E) -> String {
 
     error_message
 }
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn lowest_revision() {
-        let input = vec!["", "F10", "F5"];
-        let rev = super::lowest_revision(input.iter().cloned());
-        assert_eq!(rev, "");
-
-        let input = vec!["F3", "F10", "F5"];
-        let rev = super::lowest_revision(input.iter().cloned());
-        assert_eq!(rev, "F3");
-
-        let input = vec!["F10", "F3", "F5"];
-        let rev = super::lowest_revision(input.iter().cloned());
-        assert_eq!(rev, "F3");
-    }
-}
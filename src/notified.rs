@@ -0,0 +1,206 @@
+//! The updates which the notifier last notified the user about.
+//!
+//! Each update is recorded in the cache as the ID of its device and the version which was
+//! offered, one per line, so that the notifier does not notify about the same updates again.
+//! The timestamps of the notifier's checks, snoozes, and notifications are kept alongside them.
+//!
+//! Each function takes the directory of this state, which is the cache directory outside of
+//! tests.
+
+use crate::{
+    timestamp::{self, NOTIFICATION_CHECK, NOTIFICATION_SHOWN, NOTIFICATION_SNOOZE},
+    Config,
+};
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+/// The name of the file in the cache which records the notified updates.
+const NOTIFIED_FILE: &str = "notified_updates";
+
+/// The device ID and version of each update which was notified about.
+pub type Notified = BTreeSet<(Box<str>, Box<str>)>;
+
+/// An error that may occur when reading or writing the notified updates.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to read notified updates")]
+    Read(#[source] io::Error),
+    #[error("failed to write notified updates")]
+    Write(#[source] io::Error),
+    #[error("failed to remove notified updates")]
+    Remove(#[source] io::Error),
+}
+
+/// Checks if the interval between the notifier's checks for updates has passed, with the state
+/// of the notifier in the given directory.
+pub fn check_due_at(dir: &Path, config: &Config) -> bool {
+    let snooze = config.notification_snooze().as_secs();
+    timestamp::exceeded_at(&dir.join(NOTIFICATION_SNOOZE), snooze).unwrap_or_else(|_| {
+        let interval = config.notification_interval().as_secs();
+        timestamp::exceeded_at(&dir.join(NOTIFICATION_CHECK), interval).unwrap_or(true)
+    })
+}
+
+/// Checks if the user asked to be reminded of updates later, and the snooze interval has not yet
+/// passed, with the state of the notifier in the given directory.
+pub fn snoozed_at(dir: &Path, config: &Config) -> bool {
+    let snooze = config.notification_snooze().as_secs();
+    timestamp::exceeded_at(&dir.join(NOTIFICATION_SNOOZE), snooze)
+        .map_or(false, |exceeded| !exceeded)
+}
+
+/// Records that the notifier has checked for updates in the given directory, and clears the
+/// snooze.
+pub fn checked_at(dir: &Path) {
+    if let Err(why) = timestamp::refresh_at(&dir.join(NOTIFICATION_CHECK)) {
+        error!("failed to update notification timestamp: {}", why);
+    }
+
+    if let Err(why) = timestamp::remove_at(&dir.join(NOTIFICATION_SNOOZE)) {
+        error!("failed to clear notification snooze: {}", why);
+    }
+}
+
+/// Snoozes the notifier, with its state in the given directory.
+pub fn snooze_at(dir: &Path) {
+    if let Err(why) = timestamp::refresh_at(&dir.join(NOTIFICATION_SNOOZE)) {
+        error!("failed to snooze notifications: {}", why);
+    }
+
+    // The updates are notified about again, even though they were already notified about.
+    if let Err(why) = clear_at(&dir.join(NOTIFIED_FILE)) {
+        error!("failed to clear notified updates: {}", why);
+    }
+}
+
+/// Checks if the notifier should notify about the given updates, with the state of the notifier
+/// in the given directory.
+pub fn required_at(dir: &Path, config: &Config, updates: &[(&str, &str)]) -> bool {
+    let notified = load_at(&dir.join(NOTIFIED_FILE)).unwrap_or_else(|why| {
+        error!("{}", crate::format_error(&why));
+        Notified::new()
+    });
+
+    let reminder = config.notification_reminder().as_secs();
+    !notified_updates(updates).is_subset(&notified)
+        || timestamp::exceeded_at(&dir.join(NOTIFICATION_SHOWN), reminder).unwrap_or(true)
+}
+
+/// Records that the user was notified about the given updates, in the given directory.
+pub fn shown_at(dir: &Path, updates: &[(&str, &str)]) {
+    if let Err(why) = save_at(&dir.join(NOTIFIED_FILE), &notified_updates(updates)) {
+        error!("{}", crate::format_error(&why));
+    }
+
+    if let Err(why) = timestamp::refresh_at(&dir.join(NOTIFICATION_SHOWN)) {
+        error!("failed to update notification timestamp: {}", why);
+    }
+}
+
+/// Fetches the updates which were last notified about from the file at the given path.
+///
+/// If none have been recorded, no updates were notified about.
+pub fn load_at(path: &Path) -> Result<Notified, Error> {
+    match fs::read_to_string(path) {
+        Ok(source) => Ok(parse(&source)),
+        Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(Notified::new()),
+        Err(why) => Err(Error::Read(why)),
+    }
+}
+
+/// Records the updates which were notified about in the file at the given path.
+pub fn save_at(path: &Path, notified: &Notified) -> Result<(), Error> {
+    fs::write(path, serialize(notified)).map_err(Error::Write)
+}
+
+/// Forgets the updates which were notified about, so that they are notified about again.
+pub fn clear_at(path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Err(why) if why.kind() != io::ErrorKind::NotFound => Err(Error::Remove(why)),
+        _ => Ok(()),
+    }
+}
+
+fn notified_updates(updates: &[(&str, &str)]) -> Notified {
+    updates.iter().map(|&(device, version)| (device.into(), version.into())).collect()
+}
+
+fn parse(source: &str) -> Notified {
+    source
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(2, '\t');
+            Some((fields.next()?.into(), fields.next()?.into()))
+        })
+        .collect()
+}
+
+fn serialize(notified: &Notified) -> String {
+    notified.iter().map(|(device, version)| [&**device, "\t", &**version, "\n"].concat()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let notified: Notified = vec![
+            ("a45df35ac0e948ee180fe216a5f703f32dda163f".into(), "1.2.3".into()),
+            ("system76/thelio-io/0".into(), "0.2 beta".into()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(parse(&serialize(&notified)), notified);
+        assert_eq!(parse("missing-version\n"), Notified::new());
+    }
+
+    /// Marks a timestamp as recorded long ago, so that any interval since it has passed.
+    fn expire(dir: &Path, name: &str) { fs::write(dir.join(name), "1").unwrap(); }
+
+    // The state is shared by every notification function, so they are tested together, in a
+    // directory of the test in place of the user's cache.
+    #[test]
+    fn notification_gating() {
+        let dir = &std::env::temp_dir().join(format!("firmware-manager-{}", std::process::id()));
+        fs::create_dir_all(dir).unwrap();
+
+        let config = Config::default();
+        let updates = [("device", "1.1"), ("dock", "2.0")];
+
+        // The first check is due, and is not due again until the interval has passed.
+        assert!(check_due_at(dir, &config));
+        checked_at(dir);
+        assert!(!check_due_at(dir, &config));
+
+        // Snoozing delays the check until the snooze has passed, regardless of the interval.
+        assert!(!snoozed_at(dir, &config));
+        snooze_at(dir);
+        assert!(snoozed_at(dir, &config));
+        assert!(!check_due_at(dir, &config));
+        expire(dir, NOTIFICATION_SNOOZE);
+        assert!(!snoozed_at(dir, &config));
+        assert!(check_due_at(dir, &config));
+        checked_at(dir);
+        assert!(!check_due_at(dir, &config));
+
+        // Updates are only recorded once they were shown.
+        assert!(required_at(dir, &config, &updates));
+        assert!(required_at(dir, &config, &updates));
+        shown_at(dir, &updates);
+        assert!(!required_at(dir, &config, &updates));
+        assert!(!required_at(dir, &config, &updates[..1]));
+        assert!(required_at(dir, &config, &[("device", "1.2")]));
+
+        // The same updates are notified about again once the reminder interval has passed.
+        expire(dir, NOTIFICATION_SHOWN);
+        assert!(required_at(dir, &config, &updates));
+        shown_at(dir, &updates);
+
+        // Snoozed updates are notified about again.
+        snooze_at(dir);
+        assert!(required_at(dir, &config, &updates));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,159 @@
+//! Authorization checks through polkit.
+
+use dbus::{arg::Variant, blocking::Connection};
+use std::{collections::HashMap, time::Duration};
+
+const AUTHORITY_DEST: &str = "org.freedesktop.PolicyKit1";
+const AUTHORITY_PATH: &str = "/org/freedesktop/PolicyKit1/Authority";
+const AUTHORITY_IFACE: &str = "org.freedesktop.PolicyKit1.Authority";
+
+/// Permits the authority to prompt the user for authentication.
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+/// Non-interactive checks are answered immediately.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interactive checks wait for the user to respond to the authentication agent.
+const INTERACTIVE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The polkit actions which fwupd requires to update firmware.
+///
+/// The system76-firmware daemon does not define polkit actions, so the authorization of its
+/// users is inferred from these, or from the administrative groups when fwupd is not installed.
+pub const FWUPD_ACTIONS: &[&str] =
+    &["org.freedesktop.fwupd.update-internal", "org.freedesktop.fwupd.update-hotplug"];
+
+/// The authorization of the current user to update firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Authorization {
+    /// The user may update firmware.
+    Authorized,
+    /// The user may update firmware after authenticating.
+    Challenge,
+    /// The user may not update firmware.
+    Denied,
+}
+
+impl Authorization {
+    /// Combines the authorizations of multiple actions.
+    ///
+    /// The user is authorized if any action is authorized, as the firmware of that backend may
+    /// then be managed.
+    fn combine(self, other: Self) -> Self {
+        use Authorization::*;
+        match (self, other) {
+            (Authorized, _) | (_, Authorized) => Authorized,
+            (Challenge, _) | (_, Challenge) => Challenge,
+            (Denied, Denied) => Denied,
+        }
+    }
+}
+
+/// Checks if the current user is authorized to update firmware, without prompting them.
+pub fn check_authorization() -> Result<Authorization, dbus::Error> { authorization(false) }
+
+/// Requests authorization to update firmware, prompting the user through their authentication
+/// agent when required.
+///
+/// This blocks until the user has responded, so it should not be called from a UI thread.
+pub fn request_authorization() -> Result<Authorization, dbus::Error> { authorization(true) }
+
+fn authorization(interactive: bool) -> Result<Authorization, dbus::Error> {
+    authorization_with(&Connection::new_system()?, interactive)
+}
+
+/// Combines the authorizations of every action which polkit knows of.
+///
+/// If no action could be checked, as when polkit is not running, the last error is returned.
+fn authorization_with(
+    connection: &Connection,
+    interactive: bool,
+) -> Result<Authorization, dbus::Error> {
+    let mut result = None;
+    let mut error = None;
+
+    for action in FWUPD_ACTIONS {
+        let authorization = match check(connection, action, false) {
+            Ok(Authorization::Challenge) if interactive => check(connection, action, true)?,
+            Ok(authorization) => authorization,
+            // Actions of services that are not installed are unknown to polkit.
+            Err(why) => {
+                debug!("failed to check polkit action {}: {}", action, why);
+                error = Some(why);
+                continue;
+            }
+        };
+
+        let combined =
+            result.map_or(authorization, |result: Authorization| result.combine(authorization));
+
+        result = Some(combined);
+        if combined == Authorization::Authorized {
+            break;
+        }
+    }
+
+    match (result, error) {
+        (Some(result), _) => Ok(result),
+        (None, Some(why)) => Err(why),
+        (None, None) => Ok(Authorization::Denied),
+    }
+}
+
+/// Calls `CheckAuthorization` for an action, with this process's bus connection as the subject.
+fn check(
+    connection: &Connection,
+    action: &str,
+    interactive: bool,
+) -> Result<Authorization, dbus::Error> {
+    let unique_name = connection.unique_name().to_string();
+
+    let mut subject_details = HashMap::new();
+    subject_details.insert("name", Variant(unique_name.as_str()));
+    let subject = ("system-bus-name", subject_details);
+
+    let details: HashMap<&str, &str> = HashMap::new();
+    let (flags, timeout) = if interactive {
+        (ALLOW_USER_INTERACTION, INTERACTIVE_TIMEOUT)
+    } else {
+        (0, CHECK_TIMEOUT)
+    };
+
+    let ((authorized, challenge, _),): ((bool, bool, HashMap<String, String>),) = connection
+        .with_proxy(AUTHORITY_DEST, AUTHORITY_PATH, timeout)
+        .method_call(AUTHORITY_IFACE, "CheckAuthorization", (subject, action, details, flags, ""))?;
+
+    Ok(if authorized {
+        Authorization::Authorized
+    } else if challenge {
+        Authorization::Challenge
+    } else {
+        Authorization::Denied
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{authorization_with, Authorization::*};
+    use crate::services::tests::PrivateBus;
+
+    #[test]
+    fn combine() {
+        assert_eq!(Denied.combine(Denied), Denied);
+        assert_eq!(Denied.combine(Challenge), Challenge);
+        assert_eq!(Challenge.combine(Denied), Challenge);
+        assert_eq!(Challenge.combine(Authorized), Authorized);
+        assert_eq!(Authorized.combine(Denied), Authorized);
+    }
+
+    #[test]
+    fn missing_polkit_is_an_error() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        // Without an authority on the bus, the caller falls back to checking groups.
+        assert!(authorization_with(&bus.connect(), false).is_err());
+    }
+}
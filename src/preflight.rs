@@ -0,0 +1,25 @@
+//! Checks which are made before firmware is updated.
+
+/// A reason for firmware not to be updated right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum PreflightError {
+    /// The system is running on battery power, and could lose power while flashing.
+    #[error("the system is running on battery power")]
+    OnBattery,
+}
+
+/// Checks if the system is in a state where firmware may be updated without supervision.
+pub fn preflight_check() -> Result<(), PreflightError> {
+    if system_on_battery() {
+        return Err(PreflightError::OnBattery);
+    }
+
+    Ok(())
+}
+
+/// Checks if the system is running on battery power, according to UPower.
+///
+/// Systems without UPower are assumed to be on AC power.
+pub fn system_on_battery() -> bool {
+    upower_dbus::UPower::new(-1).and_then(|upower| upower.on_battery()).unwrap_or(false)
+}
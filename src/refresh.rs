@@ -0,0 +1,292 @@
+//! Conditions which defer refreshing the fwupd remote metadata.
+//!
+//! Metadata is not refreshed on a metered network connection, as reported by NetworkManager, nor
+//! while UPower reports that the battery is low, unless the refresh was requested by the user.
+
+use dbus::{
+    blocking::{stdintf::org_freedesktop_dbus::Properties, Connection},
+    channel::Channel,
+};
+use std::time::Duration;
+
+const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+
+const UPOWER_SERVICE: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const UPOWER_DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+const UPOWER_DEVICE_IFACE: &str = "org.freedesktop.UPower.Device";
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The `Metered` values of NetworkManager which mean that the connection is metered: `yes`, and
+/// `guess-yes`.
+const NM_METERED: [u32; 2] = [1, 3];
+
+/// The `WarningLevel` of UPower from which the battery is low: `low`, `critical`, and `action`.
+const UPOWER_WARNING_LOW: u32 = 3;
+
+/// A reason for the fwupd remote metadata not to be refreshed right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RefreshDeferral {
+    /// The network connection is metered, such as a tethered phone.
+    #[error("the network connection is metered")]
+    Metered,
+    /// The system is running on battery power, and the battery is low.
+    #[error("the battery is low")]
+    LowBattery,
+}
+
+/// The outcome of a request to refresh the fwupd remote metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataRefresh {
+    /// The metadata was refreshed.
+    Refreshed,
+    /// The refresh interval has not passed since the metadata was last refreshed.
+    NotDue,
+    /// The refresh was skipped, and will be attempted again on the next request.
+    Deferred(RefreshDeferral),
+}
+
+/// Checks the network connection and battery through NetworkManager and UPower.
+///
+/// Defaults to the system bus, but may connect to any bus by its address, such as a private bus
+/// spawned for testing. Services which are not running are assumed not to defer refreshes.
+pub struct RefreshConditions {
+    connection: Connection,
+}
+
+impl RefreshConditions {
+    /// Checks the conditions through the services of the system bus.
+    pub fn system() -> Result<Self, dbus::Error> {
+        Connection::new_system().map(|connection| Self { connection })
+    }
+
+    /// Checks the conditions through the services of the bus at the given address.
+    pub fn with_address(address: &str) -> Result<Self, dbus::Error> {
+        let mut channel = Channel::open_private(address)?;
+        channel.register()?;
+        Ok(Self { connection: Connection::from(channel) })
+    }
+
+    /// Checks if the metadata may be refreshed now, or returns the reason to defer it.
+    pub fn check(&self) -> Result<(), RefreshDeferral> {
+        if self.metered() {
+            Err(RefreshDeferral::Metered)
+        } else if self.low_battery() {
+            Err(RefreshDeferral::LowBattery)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks if NetworkManager considers the primary connection to be metered.
+    pub fn metered(&self) -> bool {
+        self.connection
+            .with_proxy(NM_SERVICE, NM_PATH, TIMEOUT)
+            .get::<u32>(NM_SERVICE, "Metered")
+            .map_err(|why| debug!("failed to get metered state from NetworkManager: {}", why))
+            .map_or(false, |metered| NM_METERED.contains(&metered))
+    }
+
+    /// Checks if UPower reports that the system is on battery power, and that its battery is low.
+    pub fn low_battery(&self) -> bool {
+        let on_battery = self
+            .connection
+            .with_proxy(UPOWER_SERVICE, UPOWER_PATH, TIMEOUT)
+            .get::<bool>(UPOWER_SERVICE, "OnBattery")
+            .map_err(|why| debug!("failed to get battery state from UPower: {}", why))
+            .unwrap_or(false);
+
+        on_battery
+            && self
+                .connection
+                .with_proxy(UPOWER_SERVICE, UPOWER_DISPLAY_DEVICE, TIMEOUT)
+                .get::<u32>(UPOWER_DEVICE_IFACE, "WarningLevel")
+                .map_err(|why| debug!("failed to get battery level from UPower: {}", why))
+                .map_or(false, |level| level >= UPOWER_WARNING_LOW)
+    }
+}
+
+/// Checks if the metadata may be refreshed now, through the services of the system bus.
+pub(crate) fn refresh_conditions() -> Result<(), RefreshDeferral> {
+    RefreshConditions::system()
+        .map_err(|why| error!("failed to connect to the system bus: {}", why))
+        .map_or(Ok(()), |conditions| conditions.check())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::tests::PrivateBus;
+    use dbus::{
+        arg::Variant,
+        channel::{MatchingReceiver, Sender},
+        message::MatchRule,
+        strings::ErrorName,
+    };
+    use std::{
+        ffi::CString,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread::{self, JoinHandle},
+    };
+
+    #[derive(Clone, Copy)]
+    enum Value {
+        Bool(bool),
+        U32(u32),
+    }
+
+    /// Owns a name on a private bus, and answers requests for properties in the background, as
+    /// NetworkManager or UPower would.
+    struct StandIn {
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl StandIn {
+        fn spawn(
+            bus: &PrivateBus,
+            name: &'static str,
+            properties: Vec<(&'static str, &'static str, Value)>,
+        ) -> Self {
+            let connection = bus.connect();
+            connection.request_name(name, false, true, false).unwrap();
+
+            connection.start_receive(
+                MatchRule::new_method_call(),
+                Box::new(move |message, connection| {
+                    let path = message.path().map(|path| path.to_string()).unwrap_or_default();
+                    let (_, property): (&str, &str) = message.read2().unwrap_or_default();
+
+                    let value = properties
+                        .iter()
+                        .find(|&&(p, name, _)| p == path && name == property)
+                        .map(|&(_, _, value)| value);
+
+                    let reply = match value {
+                        Some(Value::Bool(value)) => message.method_return().append1(Variant(value)),
+                        Some(Value::U32(value)) => message.method_return().append1(Variant(value)),
+                        None => message.error(
+                            &ErrorName::from("org.freedesktop.DBus.Error.UnknownProperty"),
+                            &CString::new(property).unwrap(),
+                        ),
+                    };
+
+                    let _ = connection.send(reply);
+                    true
+                }),
+            );
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let stop = stop.clone();
+                thread::spawn(move || {
+                    while !stop.load(Ordering::SeqCst) {
+                        let _ = connection.process(Duration::from_millis(10));
+                    }
+
+                    // The name is released before the next stand-in requests it.
+                    let _ = connection.release_name(name);
+                })
+            };
+
+            Self { stop, thread: Some(thread) }
+        }
+    }
+
+    impl Drop for StandIn {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn network_manager(bus: &PrivateBus, metered: u32) -> StandIn {
+        StandIn::spawn(bus, NM_SERVICE, vec![(NM_PATH, "Metered", Value::U32(metered))])
+    }
+
+    fn upower(bus: &PrivateBus, on_battery: bool, warning_level: u32) -> StandIn {
+        StandIn::spawn(
+            bus,
+            UPOWER_SERVICE,
+            vec![
+                (UPOWER_PATH, "OnBattery", Value::Bool(on_battery)),
+                (UPOWER_DISPLAY_DEVICE, "WarningLevel", Value::U32(warning_level)),
+            ],
+        )
+    }
+
+    #[test]
+    fn refreshes_without_services() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        let conditions = RefreshConditions::with_address(&bus.address).unwrap();
+        assert_eq!(conditions.check(), Ok(()));
+    }
+
+    #[test]
+    fn defers_on_metered_connections() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        let conditions = RefreshConditions::with_address(&bus.address).unwrap();
+
+        for &(metered, expected) in &[
+            (0, Ok(())),
+            (1, Err(RefreshDeferral::Metered)),
+            (2, Ok(())),
+            (3, Err(RefreshDeferral::Metered)),
+            (4, Ok(())),
+        ] {
+            let _nm = network_manager(&bus, metered);
+            assert_eq!(conditions.check(), expected, "Metered = {}", metered);
+        }
+    }
+
+    #[test]
+    fn defers_on_low_battery() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        let conditions = RefreshConditions::with_address(&bus.address).unwrap();
+
+        for &(on_battery, warning_level, expected) in &[
+            (false, 3, Ok(())),
+            (true, 1, Ok(())),
+            (true, 3, Err(RefreshDeferral::LowBattery)),
+            (true, 4, Err(RefreshDeferral::LowBattery)),
+        ] {
+            let _upower = upower(&bus, on_battery, warning_level);
+            assert_eq!(conditions.check(), expected, "{} {}", on_battery, warning_level);
+        }
+    }
+
+    #[test]
+    fn metered_connections_are_reported_first() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        let _nm = network_manager(&bus, 1);
+        let _upower = upower(&bus, true, 3);
+
+        let conditions = RefreshConditions::with_address(&bus.address).unwrap();
+        assert_eq!(conditions.check(), Err(RefreshDeferral::Metered));
+    }
+}
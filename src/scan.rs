@@ -0,0 +1,119 @@
+//! Outcomes of scanning the firmware services for devices.
+
+use crate::{Backend, RefreshDeferral};
+
+/// The outcome of scanning a firmware service for devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ScanStatus {
+    /// The service was disabled in the configuration.
+    Disabled,
+    /// The service is not installed, or could not be connected to.
+    Unavailable,
+    /// The service was available, but it failed to list its devices.
+    Errored(Box<str>),
+    /// The service was scanned, and this many devices were found.
+    Found(usize),
+    /// This many devices were found, but the service failed to list the rest of its devices.
+    Incomplete(usize, Box<str>),
+}
+
+impl Default for ScanStatus {
+    fn default() -> Self { ScanStatus::Unavailable }
+}
+
+/// The outcome of a scan for each firmware service.
+///
+/// This distinguishes a system without managed firmware from a system where the firmware
+/// services are missing, or failing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScanReport {
+    /// The outcome of scanning the fwupd service.
+    pub fwupd: ScanStatus,
+    /// The outcome of scanning the system76-firmware service.
+    pub system76: ScanStatus,
+    /// The reason that the fwupd remote metadata was not refreshed before the scan, if it was due
+    /// to be refreshed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub refresh_deferred: Option<RefreshDeferral>,
+}
+
+impl ScanReport {
+    /// The outcome of scanning the given backend.
+    pub fn status(&self, backend: Backend) -> &ScanStatus {
+        match backend {
+            Backend::Fwupd => &self.fwupd,
+            Backend::System76 => &self.system76,
+        }
+    }
+
+    /// The outcome of each backend, in the order that they were scanned.
+    pub fn iter(&self) -> impl Iterator<Item = (Backend, &ScanStatus)> {
+        Backend::ALL.iter().map(move |&backend| (backend, self.status(backend)))
+    }
+
+    /// The total number of devices that were found.
+    pub fn devices(&self) -> usize {
+        self.iter()
+            .map(|(_, status)| match *status {
+                ScanStatus::Found(devices) | ScanStatus::Incomplete(devices, _) => devices,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Returns `true` if at least one backend was successfully scanned.
+    pub fn any_scanned(&self) -> bool {
+        self.iter()
+            .any(|(_, status)| matches!(status, ScanStatus::Found(_) | ScanStatus::Incomplete(..)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_services_are_not_scanned() {
+        let report = ScanReport::default();
+        assert!(!report.any_scanned());
+        assert_eq!(report.devices(), 0);
+
+        let report = ScanReport {
+            fwupd: ScanStatus::Errored("timed out".into()),
+            system76: ScanStatus::Unavailable,
+            refresh_deferred: None,
+        };
+
+        assert!(!report.any_scanned());
+    }
+
+    #[test]
+    fn found_devices_are_counted() {
+        let report = ScanReport {
+            fwupd: ScanStatus::Found(0),
+            system76: ScanStatus::Unavailable,
+            refresh_deferred: None,
+        };
+        assert!(report.any_scanned());
+        assert_eq!(report.devices(), 0);
+
+        let report = ScanReport {
+            fwupd: ScanStatus::Found(3),
+            system76: ScanStatus::Found(2),
+            refresh_deferred: None,
+        };
+        assert_eq!(report.devices(), 5);
+
+        let report = ScanReport {
+            fwupd: ScanStatus::Unavailable,
+            system76: ScanStatus::Incomplete(1, "failed to list Thelio I/O boards".into()),
+            refresh_deferred: None,
+        };
+
+        assert!(report.any_scanned());
+        assert_eq!(report.devices(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+//! Host security attributes, as reported by fwupd.
+//!
+//! fwupd assesses the platform security of the host, and summarizes it as a Host Security ID
+//! (HSI). Each attribute of the assessment is mapped into a `SecurityAttribute`.
+
+use crate::services::{FWUPD_IFACE, FWUPD_PATH, FWUPD_SERVICE};
+use dbus::{
+    arg::{prop_cast, PropMap},
+    blocking::{stdintf::org_freedesktop_dbus::Properties, Connection},
+};
+use std::time::Duration;
+
+/// fwupd may need to probe the hardware before it can answer.
+const SECURITY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The attribute flag which fwupd sets when an attribute meets its requirement.
+const FLAG_SUCCESS: u64 = 1;
+
+/// The kind of security attribute, for attributes which the firmware manager recognizes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SecurityAttributeKind {
+    /// UEFI Secure Boot.
+    SecureBoot,
+    /// The Trusted Platform Module.
+    Tpm,
+    /// Protection from DMA attacks by the IOMMU.
+    Iommu,
+    /// Protection of the system firmware from being overwritten.
+    BiosWriteProtection,
+    /// The state of the Intel Management Engine.
+    ManagementEngine,
+    /// An attribute which is not recognized, by its AppStream ID.
+    Other(Box<str>),
+}
+
+impl SecurityAttributeKind {
+    /// Maps the AppStream ID of an attribute to its kind.
+    pub fn from_appstream_id(id: &str) -> Self {
+        let suffix = id.trim_start_matches("org.fwupd.hsi.");
+        let group = suffix.split('.').next().unwrap_or(suffix);
+
+        match (group, suffix) {
+            (_, "Uefi.SecureBoot") => SecurityAttributeKind::SecureBoot,
+            ("Tpm", _) => SecurityAttributeKind::Tpm,
+            ("Iommu", _) => SecurityAttributeKind::Iommu,
+            ("Spi", _) => SecurityAttributeKind::BiosWriteProtection,
+            ("Mei", _) => SecurityAttributeKind::ManagementEngine,
+            _ => SecurityAttributeKind::Other(id.into()),
+        }
+    }
+}
+
+/// The state which fwupd found a security attribute in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SecurityResult {
+    /// The state could not be determined.
+    Unknown,
+    /// The feature is enabled.
+    Enabled,
+    /// The feature is not enabled.
+    NotEnabled,
+    /// The feature is in a valid state.
+    Valid,
+    /// The feature is not in a valid state.
+    NotValid,
+    /// The feature is locked.
+    Locked,
+    /// The feature is not locked.
+    NotLocked,
+    /// The feature is encrypted.
+    Encrypted,
+    /// The feature is not encrypted.
+    NotEncrypted,
+    /// The feature has been tainted.
+    Tainted,
+    /// The feature has not been tainted.
+    NotTainted,
+    /// The feature was found.
+    Found,
+    /// The feature was not found.
+    NotFound,
+    /// The feature is supported.
+    Supported,
+    /// The feature is not supported.
+    NotSupported,
+}
+
+impl From<u32> for SecurityResult {
+    fn from(result: u32) -> Self {
+        use SecurityResult::*;
+        match result {
+            1 => Enabled,
+            2 => NotEnabled,
+            3 => Valid,
+            4 => NotValid,
+            5 => Locked,
+            6 => NotLocked,
+            7 => Encrypted,
+            8 => NotEncrypted,
+            9 => Tainted,
+            10 => NotTainted,
+            11 => Found,
+            12 => NotFound,
+            13 => Supported,
+            14 => NotSupported,
+            _ => Unknown,
+        }
+    }
+}
+
+/// An attribute of the security of the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SecurityAttribute {
+    /// The AppStream ID of the attribute, such as `org.fwupd.hsi.Uefi.SecureBoot`.
+    pub id: Box<str>,
+    /// The kind of attribute.
+    pub kind: SecurityAttributeKind,
+    /// The name of the attribute, as given by fwupd.
+    pub name: Box<str>,
+    /// The HSI level which requires this attribute, or zero if it is a runtime issue.
+    pub level: u32,
+    /// The state that the attribute was found in.
+    pub result: SecurityResult,
+    /// The attribute meets its requirement.
+    pub passed: bool,
+    /// A link to documentation about the attribute.
+    pub uri: Option<Box<str>>,
+}
+
+impl SecurityAttribute {
+    fn from_props(props: &PropMap) -> Option<Self> {
+        let string = |key: &str| prop_cast::<String>(props, key).map(|value| Box::from(&**value));
+
+        let id = string("AppstreamId")?;
+
+        Some(Self {
+            kind: SecurityAttributeKind::from_appstream_id(&id),
+            name: string("Name").unwrap_or_else(|| id.clone()),
+            level: prop_cast::<u32>(props, "HsiLevel").copied().unwrap_or(0),
+            result: prop_cast::<u32>(props, "HsiResult").copied().unwrap_or(0).into(),
+            passed: prop_cast::<u64>(props, "Flags")
+                .map_or(false, |flags| flags & FLAG_SUCCESS != 0),
+            uri: string("Uri"),
+            id,
+        })
+    }
+}
+
+/// The security assessment of the host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SecurityReport {
+    /// The Host Security ID, such as `HSI:1`, if fwupd computed one.
+    pub host_security_id: Option<Box<str>>,
+    /// Each attribute of the assessment.
+    pub attributes: Vec<SecurityAttribute>,
+}
+
+/// Fetches the host security attributes from fwupd.
+pub fn host_security_report() -> Result<SecurityReport, dbus::Error> {
+    let connection = Connection::new_system()?;
+    let proxy = connection.with_proxy(FWUPD_SERVICE, FWUPD_PATH, SECURITY_TIMEOUT);
+
+    let host_security_id = proxy
+        .get::<String>(FWUPD_IFACE, "HostSecurityId")
+        .ok()
+        .filter(|id| !id.is_empty())
+        .map(Box::from);
+
+    let (attributes,): (Vec<PropMap>,) =
+        proxy.method_call(FWUPD_IFACE, "GetHostSecurityAttrs", ())?;
+
+    let attributes = attributes.iter().filter_map(SecurityAttribute::from_props).collect();
+
+    Ok(SecurityReport { host_security_id, attributes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dbus::arg::{RefArg, Variant};
+
+    #[test]
+    fn attribute_kinds() {
+        use SecurityAttributeKind::*;
+        let kind = SecurityAttributeKind::from_appstream_id;
+
+        assert_eq!(kind("org.fwupd.hsi.Uefi.SecureBoot"), SecureBoot);
+        assert_eq!(kind("org.fwupd.hsi.Tpm.Version20"), Tpm);
+        assert_eq!(kind("org.fwupd.hsi.Spi.Bioswe"), BiosWriteProtection);
+        assert_eq!(kind("org.fwupd.hsi.Mei.OverrideStrap"), ManagementEngine);
+        assert_eq!(
+            kind("org.fwupd.hsi.Kernel.Tainted"),
+            Other("org.fwupd.hsi.Kernel.Tainted".into())
+        );
+    }
+
+    #[test]
+    fn attribute_from_props() {
+        let mut props = PropMap::new();
+        let mut insert = |key: &str, value: Box<dyn RefArg>| {
+            props.insert(key.into(), Variant(value));
+        };
+
+        insert("AppstreamId", Box::new(String::from("org.fwupd.hsi.Iommu")));
+        insert("Name", Box::new(String::from("IOMMU")));
+        insert("HsiLevel", Box::new(2u32));
+        insert("HsiResult", Box::new(1u32));
+        insert("Flags", Box::new(1u64));
+
+        let attribute = SecurityAttribute::from_props(&props).unwrap();
+        assert_eq!(attribute.kind, SecurityAttributeKind::Iommu);
+        assert_eq!(&*attribute.name, "IOMMU");
+        assert_eq!(attribute.level, 2);
+        assert_eq!(attribute.result, SecurityResult::Enabled);
+        assert!(attribute.passed);
+        assert_eq!(attribute.uri, None);
+
+        assert_eq!(SecurityAttribute::from_props(&PropMap::new()), None);
+    }
+}
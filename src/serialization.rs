@@ -0,0 +1,311 @@
+//! Serialization of the types which can not derive `Serialize`, because they hold data from the
+//! firmware services, or have tuple variants that are given named fields.
+
+use crate::{
+    ConfigError, Entity, Error, FirmwareSignal, FwupdDevice, FwupdRelease, FwupdSignal,
+    HistoryError, HoldsError, InvalidKey, PreflightError, ThelioIoUpdate,
+};
+use serde::ser::{Serialize, SerializeMap, SerializeStruct, Serializer};
+use slotmap::KeyData;
+
+/// Entities are serialized as the integer of their key, which is never zero.
+fn key(entity: Entity) -> u64 { KeyData::from(entity).as_ffi() }
+
+/// Errors are serialized with the kind of error, and the message of the error and its causes.
+fn serialize_error<S: Serializer>(
+    serializer: S,
+    kind: &str,
+    error: &dyn std::error::Error,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("Error", 2)?;
+    state.serialize_field("kind", kind)?;
+    state.serialize_field("message", &crate::format_error(error))?;
+    state.end()
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            Error::Fwupd(_) => "fwupd",
+            Error::System76(_) => "system76",
+            Error::Unavailable(_) => "unavailable",
+            Error::Security(_) => "security",
+            Error::ThelioIoBoard(..) => "thelio_io_board",
+            Error::Verify(_) => "verify",
+            Error::Activate(_) => "activate",
+            Error::Unlock(_) => "unlock",
+        };
+
+        serialize_error(serializer, kind, self)
+    }
+}
+
+impl Serialize for ConfigError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            ConfigError::BaseDirectory(_) => "base_directory",
+            ConfigError::Read(..) => "read",
+            ConfigError::Syntax(..) => "syntax",
+            ConfigError::Invalid(..) => "invalid",
+        };
+
+        serialize_error(serializer, kind, self)
+    }
+}
+
+impl Serialize for InvalidKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            InvalidKey::Unknown(_) => "unknown",
+            InvalidKey::Type(..) => "type",
+            InvalidKey::Zero(_) => "zero",
+            InvalidKey::TooLarge(..) => "too_large",
+        };
+
+        serialize_error(serializer, kind, self)
+    }
+}
+
+impl Serialize for HistoryError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            HistoryError::BaseDirectory(_) => "base_directory",
+            HistoryError::Place(_) => "place",
+            HistoryError::Read(_) => "read",
+            HistoryError::Write(_) => "write",
+            HistoryError::Parse(_) => "parse",
+        };
+
+        serialize_error(serializer, kind, self)
+    }
+}
+
+impl Serialize for HoldsError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            HoldsError::BaseDirectory(_) => "base_directory",
+            HoldsError::Place(_) => "place",
+            HoldsError::Read(_) => "read",
+            HoldsError::Write(_) => "write",
+            HoldsError::Parse(_) => "parse",
+        };
+
+        serialize_error(serializer, kind, self)
+    }
+}
+
+impl Serialize for PreflightError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            PreflightError::OnBattery => "on_battery",
+        };
+
+        serialize_error(serializer, kind, self)
+    }
+}
+
+/// The fields of a fwupd device which frontends use.
+struct Device<'a>(&'a FwupdDevice);
+
+impl Serialize for Device<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let device = self.0;
+        let mut state = serializer.serialize_struct("FwupdDevice", 7)?;
+        state.serialize_field("device_id", &**device.device_id)?;
+        state.serialize_field("name", &device.name)?;
+        state.serialize_field("vendor", &device.vendor)?;
+        state.serialize_field("version", &device.version)?;
+        state.serialize_field("guids", &device.guid)?;
+        state.serialize_field("flags", &device.flags.bits())?;
+        state.serialize_field("needs_reboot", &device.needs_reboot())?;
+        state.end()
+    }
+}
+
+/// The fields of a fwupd release which frontends use.
+struct Release<'a>(&'a FwupdRelease);
+
+impl Serialize for Release<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let release = self.0;
+        let mut state = serializer.serialize_struct("FwupdRelease", 3)?;
+        state.serialize_field("version", &release.version)?;
+        state.serialize_field("created", &release.created)?;
+        state.serialize_field("description", &release.description)?;
+        state.end()
+    }
+}
+
+fn serialize_fwupd<M: SerializeMap>(map: &mut M, signal: &FwupdSignal) -> Result<(), M::Error> {
+    let releases: Vec<Release> = signal.releases.iter().map(Release).collect();
+
+    map.serialize_entry("info", &signal.info)?;
+    map.serialize_entry("device", &Device(&signal.device))?;
+    map.serialize_entry("upgradeable", &signal.upgradeable)?;
+    map.serialize_entry("releases", &releases)?;
+    map.serialize_entry("pending", &signal.pending)
+}
+
+impl Serialize for FwupdSignal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(5))?;
+        serialize_fwupd(&mut map, self)?;
+        map.end()
+    }
+}
+
+/// The digest is only meaningful to the daemon, so it is not serialized.
+impl Serialize for ThelioIoUpdate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ThelioIoUpdate", 2)?;
+        state.serialize_field("board", &self.board)?;
+        state.serialize_field("revision", &self.revision)?;
+        state.end()
+    }
+}
+
+/// Signals are objects whose `signal` field names the variant, followed by its fields.
+impl Serialize for FirmwareSignal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        match self {
+            FirmwareSignal::DeviceFlashing(entity) => {
+                map.serialize_entry("signal", "device_flashing")?;
+                map.serialize_entry("entity", &key(*entity))?;
+            }
+            FirmwareSignal::DeviceUpdated(entity) => {
+                map.serialize_entry("signal", "device_updated")?;
+                map.serialize_entry("entity", &key(*entity))?;
+            }
+            FirmwareSignal::DownloadBegin(entity, size) => {
+                map.serialize_entry("signal", "download_begin")?;
+                map.serialize_entry("entity", &key(*entity))?;
+                map.serialize_entry("size", size)?;
+            }
+            FirmwareSignal::DownloadComplete(entity) => {
+                map.serialize_entry("signal", "download_complete")?;
+                map.serialize_entry("entity", &key(*entity))?;
+            }
+            FirmwareSignal::DownloadUpdate(entity, bytes) => {
+                map.serialize_entry("signal", "download_update")?;
+                map.serialize_entry("entity", &key(*entity))?;
+                map.serialize_entry("bytes", bytes)?;
+            }
+            FirmwareSignal::BackendAvailable(backend) => {
+                map.serialize_entry("signal", "backend_available")?;
+                map.serialize_entry("backend", backend)?;
+            }
+            FirmwareSignal::BackendLost(backend) => {
+                map.serialize_entry("signal", "backend_lost")?;
+                map.serialize_entry("backend", backend)?;
+            }
+            FirmwareSignal::Error(entity, error) => {
+                map.serialize_entry("signal", "error")?;
+                map.serialize_entry("entity", &entity.map(key))?;
+                map.serialize_entry("error", error)?;
+            }
+            FirmwareSignal::Fwupd(signal) => {
+                map.serialize_entry("signal", "fwupd")?;
+                serialize_fwupd(&mut map, signal)?;
+            }
+            FirmwareSignal::Scanning => map.serialize_entry("signal", "scanning")?,
+            FirmwareSignal::ScanningComplete(report) => {
+                map.serialize_entry("signal", "scanning_complete")?;
+                map.serialize_entry("report", report)?;
+            }
+            FirmwareSignal::Security(report) => {
+                map.serialize_entry("signal", "security")?;
+                map.serialize_entry("report", report)?;
+            }
+            FirmwareSignal::SystemScheduled => map.serialize_entry("signal", "system_scheduled")?,
+            FirmwareSignal::S76System(info, digest, scheduled) => {
+                map.serialize_entry("signal", "s76_system")?;
+                map.serialize_entry("info", info)?;
+                map.serialize_entry("update_available", &digest.is_some())?;
+                map.serialize_entry("scheduled", scheduled)?;
+            }
+            FirmwareSignal::ThelioIo(info, update) => {
+                map.serialize_entry("signal", "thelio_io")?;
+                map.serialize_entry("info", info)?;
+                map.serialize_entry("update", update)?;
+            }
+            FirmwareSignal::Verification(entity, verification) => {
+                map.serialize_entry("signal", "verification")?;
+                map.serialize_entry("entity", &key(*entity))?;
+                map.serialize_entry("verification", verification)?;
+            }
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Backend, Changelog, ChangelogEntry, FirmwareInfo, ReleaseTrust, ScanReport, ScanStatus,
+        Urgency,
+    };
+
+    fn info() -> FirmwareInfo {
+        FirmwareInfo {
+            id: "system76-system".into(),
+            name: "System76 Thelio (thelio-r2)".into(),
+            current: "2021-03-01_4fe6b3f".into(),
+            latest: Some("2021-05-12_2c7a1e0".into()),
+            install_duration: 1,
+            held: false,
+            changelog: Changelog {
+                entries: vec![
+                    ChangelogEntry {
+                        version: "2021-05-12_2c7a1e0".into(),
+                        date: Some(1_620_777_600),
+                        description: Some("<p>Fixes suspend on AMD systems</p>".into()),
+                        urgency: Urgency::Critical,
+                        cves: vec!["CVE-2021-0001".into()],
+                        trust: Some(ReleaseTrust {
+                            signed_payload: true,
+                            signed_metadata: true,
+                            remote: "lvfs".into(),
+                        }),
+                    },
+                    ChangelogEntry {
+                        version: "2021-03-01_4fe6b3f".into(),
+                        ..ChangelogEntry::default()
+                    },
+                ],
+            },
+        }
+    }
+
+    fn golden<T: Serialize>(value: &T, golden: &str) {
+        let json = serde_json::to_string_pretty(value).expect("failed to serialize");
+        assert_eq!(json, golden.trim_end());
+    }
+
+    #[test]
+    fn firmware_info() {
+        golden(&info(), include_str!("../tests/golden/firmware_info.json"));
+    }
+
+    #[test]
+    fn signals() {
+        let entity = Entity::from(KeyData::from_ffi(1 << 32 | 1));
+
+        let signals = vec![
+            FirmwareSignal::Scanning,
+            FirmwareSignal::S76System(info(), None, false),
+            FirmwareSignal::DownloadUpdate(entity, 4096),
+            FirmwareSignal::Error(Some(entity), Error::Unavailable(Backend::System76)),
+            FirmwareSignal::ScanningComplete(ScanReport {
+                fwupd: ScanStatus::Errored("timed out".into()),
+                system76: ScanStatus::Found(1),
+                refresh_deferred: None,
+            }),
+        ];
+
+        golden(&signals, include_str!("../tests/golden/signals.json"));
+    }
+}
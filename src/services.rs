@@ -0,0 +1,310 @@
+//! Detection of the firmware services that the firmware manager communicates with.
+
+use crate::FirmwareEvent;
+use dbus::{blocking::Connection, channel::Channel, message::MatchRule};
+use std::{
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// The well-known bus name of the fwupd daemon.
+pub const FWUPD_SERVICE: &str = "org.freedesktop.fwupd";
+
+/// The object path of the fwupd daemon's manager.
+pub(crate) const FWUPD_PATH: &str = "/";
+
+/// The interface of the fwupd daemon's manager.
+pub(crate) const FWUPD_IFACE: &str = "org.freedesktop.fwupd";
+
+/// The well-known bus name of the system76-firmware daemon.
+pub const S76_SERVICE: &str = "com.system76.FirmwareDaemon";
+
+const DBUS_DEST: &str = "org.freedesktop.DBus";
+const DBUS_PATH: &str = "/org/freedesktop/DBus";
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the watch waits on the bus before it checks if its detector was dropped.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A firmware service which the firmware manager may source firmware from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Backend {
+    /// The fwupd daemon, which sources firmware from the LVFS.
+    Fwupd,
+    /// The system76-firmware daemon, which manages System76 system and Thelio I/O firmware.
+    System76,
+}
+
+impl Backend {
+    /// All backends, in the order that they are scanned.
+    pub const ALL: [Backend; 2] = [Backend::System76, Backend::Fwupd];
+
+    /// A short name for the backend, for use in logs.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Fwupd => "fwupd",
+            Backend::System76 => "system76",
+        }
+    }
+
+    /// The well-known bus name which the backend's daemon owns on the system bus.
+    pub fn service(self) -> &'static str {
+        match self {
+            Backend::Fwupd => FWUPD_SERVICE,
+            Backend::System76 => S76_SERVICE,
+        }
+    }
+
+    /// The backend whose daemon owns the given bus name, if any.
+    pub fn from_service(service: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|backend| backend.service() == service)
+    }
+}
+
+/// A change in the availability of a firmware service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceChange {
+    /// The service acquired its name on the bus.
+    Started(Backend),
+    /// The service released its name on the bus.
+    Stopped(Backend),
+}
+
+/// A strategy for detecting if a firmware service is available on the system.
+pub trait ServiceDetector {
+    /// Returns `true` if the service is running, or may be started on demand.
+    fn is_available(&self, service: &str) -> bool;
+
+    /// Returns the changes in service availability which were observed since the last call.
+    ///
+    /// Detectors which cannot observe services starting and stopping return nothing.
+    fn changes(&self) -> Vec<ServiceChange> { Vec::new() }
+}
+
+/// Closures may be used as detectors, which is useful for frontends that already know which
+/// services are available.
+impl<F: Fn(&str) -> bool> ServiceDetector for F {
+    fn is_available(&self, service: &str) -> bool { self(service) }
+}
+
+/// Detects services by their name ownership, or their activatability, on a DBus bus.
+///
+/// Defaults to the system bus, but may connect to any bus by its address, such as a private bus
+/// spawned for testing.
+pub struct DbusDetector {
+    connection: Connection,
+    /// The address of the bus, or `None` for the system bus.
+    address: Option<String>,
+    changes: Arc<Mutex<Vec<ServiceChange>>>,
+}
+
+impl DbusDetector {
+    /// Detects services on the system bus.
+    pub fn system() -> Result<Self, dbus::Error> {
+        Ok(Self { connection: open(None)?, address: None, changes: Arc::default() })
+    }
+
+    /// Detects services on the bus at the given address.
+    pub fn with_address(address: &str) -> Result<Self, dbus::Error> {
+        let connection = open(Some(address))?;
+        Ok(Self { connection, address: Some(address.to_owned()), changes: Arc::default() })
+    }
+
+    /// Watches for `NameOwnerChanged` signals of the firmware services on a background thread.
+    ///
+    /// Observed changes are collected by `ServiceDetector::changes`, and each one sends a
+    /// `FirmwareEvent::ServicesChanged` to `wake`, so that an event loop blocked on that channel
+    /// collects them. The thread exits once the detector is dropped.
+    pub fn watch(&self, wake: Sender<FirmwareEvent>) -> Result<(), dbus::Error> {
+        let connection = open(self.address.as_deref())?;
+        let rule = MatchRule::new_signal(DBUS_DEST, "NameOwnerChanged").with_sender(DBUS_DEST);
+        let changes = Arc::downgrade(&self.changes);
+
+        connection.add_match(rule, {
+            let changes = changes.clone();
+            move |(name, _, owner): (String, String, String), _, _| {
+                let backend = match Backend::from_service(&name) {
+                    Some(backend) => backend,
+                    None => return true,
+                };
+
+                let changes = match changes.upgrade() {
+                    Some(changes) => changes,
+                    None => return false,
+                };
+
+                let change = if owner.is_empty() {
+                    ServiceChange::Stopped(backend)
+                } else {
+                    ServiceChange::Started(backend)
+                };
+
+                debug!("observed service change: {:?}", change);
+                changes.lock().expect("service changes mutex poisoned").push(change);
+                wake.send(FirmwareEvent::ServicesChanged).is_ok()
+            }
+        })?;
+
+        thread::spawn(move || {
+            while changes.strong_count() > 0 {
+                if let Err(why) = connection.process(WATCH_TIMEOUT) {
+                    error!("failed to watch for firmware service changes: {}", why);
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Checks if the service currently has an owner on the bus.
+    pub fn has_owner(&self, service: &str) -> Result<bool, dbus::Error> {
+        self.bus().method_call(DBUS_DEST, "NameHasOwner", (service,)).map(|(owned,): (bool,)| owned)
+    }
+
+    /// Checks if the service can be started on demand by the bus.
+    pub fn is_activatable(&self, service: &str) -> Result<bool, dbus::Error> {
+        self.bus()
+            .method_call(DBUS_DEST, "ListActivatableNames", ())
+            .map(|(names,): (Vec<String>,)| names.iter().any(|name| name == service))
+    }
+
+    fn bus(&self) -> dbus::blocking::Proxy<'_, &Connection> {
+        self.connection.with_proxy(DBUS_DEST, DBUS_PATH, DBUS_TIMEOUT)
+    }
+}
+
+impl ServiceDetector for DbusDetector {
+    fn is_available(&self, service: &str) -> bool {
+        let available = self
+            .has_owner(service)
+            .and_then(|owned| if owned { Ok(true) } else { self.is_activatable(service) });
+
+        available.map_err(|why| error!("failed to detect {}: {}", service, why)).unwrap_or(false)
+    }
+
+    fn changes(&self) -> Vec<ServiceChange> {
+        std::mem::take(&mut *self.changes.lock().expect("service changes mutex poisoned"))
+    }
+}
+
+/// Opens a connection to the bus at the given address, or to the system bus.
+fn open(address: Option<&str>) -> Result<Connection, dbus::Error> {
+    match address {
+        Some(address) => {
+            let mut channel = Channel::open_private(address)?;
+            channel.register()?;
+            Ok(Connection::from(channel))
+        }
+        None => Connection::new_system(),
+    }
+}
+
+/// Checks if a service is available on the system bus.
+pub fn service_is_available(service: &str) -> bool {
+    DbusDetector::system()
+        .map_err(|why| error!("failed to connect to the system bus: {}", why))
+        .map_or(false, |detector| detector.is_available(service))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    /// A private bus daemon which is killed on drop.
+    pub(crate) struct PrivateBus {
+        daemon: Child,
+        pub(crate) address: String,
+    }
+
+    impl PrivateBus {
+        /// Spawns a private bus, or returns `None` if `dbus-daemon` is not installed.
+        pub(crate) fn spawn() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(&["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .ok()?;
+
+            let mut address = String::new();
+            let stdout = daemon.stdout.take()?;
+            BufReader::new(stdout).read_line(&mut address).ok()?;
+            address.truncate(address.trim_end().len());
+
+            Some(Self { daemon, address })
+        }
+
+        /// Opens a new connection to this bus.
+        pub(crate) fn connect(&self) -> Connection {
+            let mut channel = Channel::open_private(&self.address).expect("failed to open bus");
+            channel.register().expect("failed to register on bus");
+            Connection::from(channel)
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    #[test]
+    fn detects_owned_names() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        let detector = DbusDetector::with_address(&bus.address).unwrap();
+        assert!(!detector.is_available(FWUPD_SERVICE));
+        assert!(!detector.is_available(S76_SERVICE));
+
+        let fwupd = bus.connect();
+        fwupd.request_name(FWUPD_SERVICE, false, true, false).unwrap();
+
+        assert!(detector.is_available(FWUPD_SERVICE));
+        assert!(!detector.is_available(S76_SERVICE));
+    }
+
+    #[test]
+    fn watches_name_owner_changes() {
+        let bus = match PrivateBus::spawn() {
+            Some(bus) => bus,
+            None => return,
+        };
+
+        let detector = DbusDetector::with_address(&bus.address).unwrap();
+        let (wake, woken) = std::sync::mpsc::channel();
+        detector.watch(wake).unwrap();
+        assert!(detector.changes().is_empty());
+
+        let s76 = bus.connect();
+        s76.request_name(S76_SERVICE, false, true, false).unwrap();
+        s76.release_name(S76_SERVICE).unwrap();
+
+        // Ownership changes of unrelated names are ignored.
+        s76.request_name("com.example.Unrelated", false, true, false).unwrap();
+
+        // Each change wakes the event loop.
+        for _ in 0..2 {
+            let event = woken.recv_timeout(Duration::from_secs(5));
+            assert!(matches!(event, Ok(FirmwareEvent::ServicesChanged)));
+        }
+
+        let changes = detector.changes();
+        let expected = vec![
+            ServiceChange::Started(Backend::System76),
+            ServiceChange::Stopped(Backend::System76),
+        ];
+
+        assert_eq!(changes, expected);
+    }
+}
@@ -1,19 +1,81 @@
 //! Functions specific to working with system76 firmware.
 
-use crate::{lowest_revision, FirmwareInfo, FirmwareSignal};
-use std::error::Error as _;
+use crate::{
+    history, Changelog, Error, FirmwareInfo, FirmwareSignal, HistoryEntry, Holds, ScanStatus,
+    UpdateOutcome,
+};
+use std::{error::Error as _, fs, io, path::Path};
 use system76_firmware_daemon::{
-    Client as System76Client, SystemInfo as S76SystemInfo, ThelioIoInfo,
+    Client as System76Client, Digest as System76Digest, SystemInfo as S76SystemInfo, ThelioIoInfo,
 };
 
+/// The identifier of System76 system firmware, for holds.
+pub const S76_SYSTEM_ID: &str = "system76-system";
+
+/// Where system76-firmware places system firmware that is scheduled to be flashed on the next boot.
+///
+/// The EFI system partition is usually only readable by root.
+const SCHEDULED_UPDATE_DIR: &str = "/boot/efi/system76-firmware-update";
+
+/// The label of the boot entry which system76-firmware sets as the next boot, to flash an update.
+const SCHEDULED_UPDATE_LABEL: &str = "system76-firmware-update";
+
+/// Where the kernel exposes EFI variables, which are readable by every user.
+const EFIVARS_DIR: &str = "/sys/firmware/efi/efivars";
+
+/// The vendor GUID of the EFI global variables, such as `BootNext`.
+const EFI_GLOBAL_GUID: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+/// The prefix of the identifiers of Thelio I/O boards, for holds.
+///
+/// Each board is identified by the name the daemon lists it with, so that holds apply to a single
+/// board, even though every board receives the same firmware.
+pub const THELIO_IO_ID: &str = "system76-thelio-io";
+
+/// The system firmware update of a System76 system, which is scheduled for the next boot.
+#[derive(Debug, Clone)]
+pub struct S76SystemUpdate {
+    /// The name of the system.
+    pub name: Box<str>,
+    /// The version of the installed firmware.
+    pub current: Box<str>,
+    /// The digest of the firmware to schedule.
+    pub digest: System76Digest,
+    /// The version of the firmware to schedule.
+    pub version: Box<str>,
+}
+
+/// The firmware update of a single Thelio I/O board.
+#[derive(Debug, Clone)]
+pub struct ThelioIoUpdate {
+    /// The identifier of the board, as listed by the daemon.
+    pub board: Box<str>,
+    /// The name of the board.
+    pub name: Box<str>,
+    /// The revision of the installed firmware.
+    pub current: Box<str>,
+    /// The digest of the firmware to flash.
+    pub digest: System76Digest,
+    /// The revision of the firmware to flash.
+    pub revision: Box<str>,
+}
+
 /// Scan for available System76 firmware
-pub fn s76_scan<F: Fn(FirmwareSignal)>(client: &System76Client, sender: F) {
+///
+/// Firmware whose latest version is withheld by the given holds is marked as held.
+pub fn s76_scan<F: Fn(FirmwareSignal)>(
+    client: &System76Client,
+    holds: &Holds,
+    sender: F,
+) -> ScanStatus {
     info!("scanning for system76 devices");
 
+    let mut found = 0;
+
     // Thelio system firmware check.
     if let Ok(current) = client.bios() {
-        let info = match client.download() {
-            Ok(S76SystemInfo { digest, changelog }) => Some((digest, changelog)),
+        let (digest, changelog) = match client.download() {
+            Ok(S76SystemInfo { digest, changelog }) => (Some(digest), Changelog::from(&changelog)),
             Err(why) => {
                 let mut error_message = format!("{}", why);
                 let mut cause = why.source();
@@ -22,69 +84,231 @@ pub fn s76_scan<F: Fn(FirmwareSignal)>(client: &System76Client, sender: F) {
                     cause = error.source();
                 }
                 error!("failed to download system76 changelog: {}", error_message);
-                None
+                (None, Changelog::default())
             }
         };
 
         let name: Box<str> = crate::system_board_identity().map(Box::from).unwrap_or(current.model);
 
+        let latest = changelog.latest().map(|entry| entry.version.clone());
+
         let fw = FirmwareInfo {
+            id: S76_SYSTEM_ID.into(),
             name,
+            held: is_held(holds, S76_SYSTEM_ID, &current.version, latest.as_deref()),
             current: current.version,
-            latest: info.as_ref().map(|(_, changelog)| {
-                changelog.versions.iter().next().expect("empty changelog").bios.clone()
-            }),
+            latest,
             install_duration: 1,
+            changelog,
         };
 
-        sender(FirmwareSignal::S76System(fw, info));
+        found += 1;
+        sender(FirmwareSignal::S76System(fw, digest, s76_update_scheduled()));
     }
 
     info!("scanning for Thelio I/O devices");
 
-    // Thelio I/O system firmware check.
-    let event = match client.thelio_io_list() {
+    // Thelio I/O firmware check, for each connected board.
+    let mut error = None;
+    match client.thelio_io_list() {
         Ok(list) => {
-            if list.is_empty() {
+            let available = if list.is_empty() {
                 None
             } else {
-                let lowest_revision = lowest_revision(list.iter().map(|(_, rev)| rev.as_ref()));
-
-                let current =
-                    Box::from(if lowest_revision.is_empty() { "N/A" } else { lowest_revision });
-
-                let (latest, digest) = match client.thelio_io_download() {
-                    Ok(info) => {
-                        let ThelioIoInfo { digest, revision } = info;
-                        (Some(revision), Some(digest))
-                    }
+                match client.thelio_io_download() {
+                    Ok(ThelioIoInfo { digest, revision }) => Some((digest, revision)),
                     Err(why) => {
                         error!("failed to download Thelio I/O digest: {:?}", why);
-                        (None, None)
+                        None
                     }
-                };
+                }
+            };
+
+            for (board, revision) in list {
+                let latest = available.as_ref().map(|(_, revision)| &**revision);
+                let fw = thelio_io_info(holds, &board, &revision, latest);
 
-                let fw = FirmwareInfo {
-                    name: "Thelio I/O".into(),
-                    current,
-                    latest,
-                    install_duration: 15,
-                };
+                let update = available.as_ref().map(|(digest, revision)| ThelioIoUpdate {
+                    board: board.into(),
+                    name: fw.name.clone(),
+                    current: fw.current.clone(),
+                    digest: digest.clone(),
+                    revision: revision.clone(),
+                });
 
-                Some(FirmwareSignal::ThelioIo(fw, digest))
+                found += 1;
+                sender(FirmwareSignal::ThelioIo(fw, update));
             }
         }
-        Err(why) => Some(FirmwareSignal::Error(None, why.into())),
+        Err(why) => {
+            error = Some(crate::format_error(&why).into());
+            sender(FirmwareSignal::Error(None, why.into()));
+        }
+    }
+
+    info!("finished scanning for system76 devices");
+
+    // System firmware which was found is still reported when the boards could not be listed.
+    match error {
+        Some(why) if found > 0 => ScanStatus::Incomplete(found, why),
+        Some(why) => ScanStatus::Errored(why),
+        None => ScanStatus::Found(found),
+    }
+}
+
+/// The identifier of a Thelio I/O board, for holds.
+pub fn thelio_io_id(board: &str) -> String { [THELIO_IO_ID, "-", board].concat() }
+
+/// The firmware of a Thelio I/O board, and whether its latest revision is held.
+fn thelio_io_info(
+    holds: &Holds,
+    board: &str,
+    revision: &str,
+    latest: Option<&str>,
+) -> FirmwareInfo {
+    let id = thelio_io_id(board);
+    let current = if revision.is_empty() { "N/A" } else { revision };
+
+    FirmwareInfo {
+        held: is_held(holds, &id, current, latest),
+        id: id.into(),
+        name: format!("Thelio I/O ({})", board).into(),
+        current: current.into(),
+        latest: latest.map(Box::from),
+        install_duration: 15,
+        changelog: Changelog::default(),
+    }
+}
+
+/// Checks if system firmware has been scheduled to be flashed on the next boot.
+///
+/// The daemon does not report this, so the boot entry that it sets as the next boot is checked,
+/// as EFI variables may be read without privileges. Where they can not be read, the update
+/// directory on the EFI system partition is checked instead.
+pub fn s76_update_scheduled() -> bool {
+    match next_boot_label() {
+        Ok(label) => label.map_or(false, |label| label == SCHEDULED_UPDATE_LABEL),
+        Err(why) => {
+            warn!("failed to read the next boot entry: {}", why);
+            Path::new(SCHEDULED_UPDATE_DIR).exists()
+        }
+    }
+}
+
+/// The label of the boot entry that is set to be booted next, if one is set.
+fn next_boot_label() -> io::Result<Option<String>> {
+    let efivar =
+        |name: &str| fs::read(Path::new(EFIVARS_DIR).join([name, EFI_GLOBAL_GUID].join("-")));
+
+    let boot_next = match efivar("BootNext") {
+        Ok(data) => data,
+        Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(why) => return Err(why),
+    };
+
+    // Each variable begins with its attributes, followed by its value.
+    let entry = match boot_next.get(4..6) {
+        Some(&[low, high]) => u16::from_le_bytes([low, high]),
+        _ => return Ok(None),
+    };
+
+    efivar(&format!("Boot{:04X}", entry)).map(|data| load_option_label(&data))
+}
+
+/// Reads the label of a boot entry, which follows its attributes and the length of its device
+/// path as a null-terminated UCS-2 string.
+fn load_option_label(efivar: &[u8]) -> Option<String> {
+    let label: Vec<u16> = efivar
+        .get(10..)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&character| character != 0)
+        .collect();
+
+    String::from_utf16(&label).ok()
+}
+
+/// Flashes the firmware of a Thelio I/O board.
+///
+/// The daemon can not flash a single board: it flashes every connected board which needs the
+/// update. The boards are therefore listed again afterwards, and only the revision of this board
+/// decides whether its update succeeded.
+pub(crate) fn thelio_io_update_board(
+    client: &System76Client,
+    update: &ThelioIoUpdate,
+) -> Result<(), Error> {
+    client.thelio_io_update(&update.digest)?;
+
+    let list = client.thelio_io_list()?;
+    if list.get(&*update.board).map_or(false, |revision| **revision == *update.revision) {
+        Ok(())
+    } else {
+        Err(Error::ThelioIoBoard(update.board.clone(), update.revision.clone()))
+    }
+}
+
+/// Records the outcome of scheduling or flashing System76 firmware in the update history.
+///
+/// `success` is the outcome which is recorded if the update did not fail.
+pub(crate) fn s76_record_update<T>(
+    id: &str,
+    name: &str,
+    from: &str,
+    to: &str,
+    result: &Result<T, Error>,
+    success: UpdateOutcome,
+) {
+    let outcome = match result {
+        Ok(_) => success,
+        Err(why) => UpdateOutcome::Failed(crate::format_error(why).into()),
     };
 
-    if let Some(event) = event {
-        sender(event);
+    let entry = HistoryEntry::new(id, name, from, to, outcome, false);
+    if let Err(why) = history::record_update(&entry) {
+        error!("{}", crate::format_error(&why));
     }
+}
+
+/// Check if the system76-firmware-daemon service is available on the system bus.
+pub fn s76_firmware_is_active() -> bool { crate::service_is_available(crate::S76_SERVICE) }
 
-    info!("finished scanning for system76 devices")
+/// An update is held if a newer version is available, but the holds do not permit it.
+fn is_held(holds: &Holds, id: &str, current: &str, latest: Option<&str>) -> bool {
+    latest.map_or(false, |latest| latest != current && !holds.permits(id, latest))
 }
 
-/// Check if the system76-firmware-daemon service is active.
-pub fn s76_firmware_is_active() -> bool {
-    crate::systemd_service_is_active("system76-firmware-daemon")
+#[cfg(test)]
+mod tests {
+    use crate::{HoldChange, Holds};
+
+    #[test]
+    fn load_option_label() {
+        let mut efivar = vec![7, 0, 0, 0, 1, 0, 0, 0, 4, 0];
+        for character in "system76-firmware-update".encode_utf16() {
+            efivar.extend_from_slice(&character.to_le_bytes());
+        }
+        efivar.extend(&[0, 0, 0x7f, 0xff, 4, 0]);
+
+        assert_eq!(
+            super::load_option_label(&efivar).as_deref(),
+            Some(super::SCHEDULED_UPDATE_LABEL)
+        );
+
+        assert_eq!(super::load_option_label(&[7, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn thelio_io_boards_are_held_independently() {
+        let mut holds = Holds::default();
+        holds.apply(&super::thelio_io_id("0"), HoldChange::Hold);
+
+        let first = super::thelio_io_info(&holds, "0", "0.1.0", Some("0.2.0"));
+        let second = super::thelio_io_info(&holds, "1", "", Some("0.2.0"));
+
+        assert_eq!(&*first.id, "system76-thelio-io-0");
+        assert_eq!(&*second.id, "system76-thelio-io-1");
+        assert!(first.held);
+        assert!(!second.held);
+        assert_eq!(&*second.current, "N/A");
+    }
 }
@@ -2,10 +2,22 @@ use crate::cache;
 
 use std::{
     fs, io,
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::{Duration, SystemTime},
 };
 
+/// The timestamp of the last refresh of the fwupd remote metadata.
+pub const METADATA_REFRESH: &str = "last_refresh";
+
+/// The timestamp of the last check for updates by the notifier.
+pub const NOTIFICATION_CHECK: &str = "last_notification_check";
+
+/// The time at which the user asked the notifier to remind them of updates later.
+pub const NOTIFICATION_SNOOZE: &str = "notification_snooze";
+
+/// The timestamp of the last notification about available updates.
+pub const NOTIFICATION_SHOWN: &str = "last_notification";
+
 /// An error that may occur when reading or writing the timestamp file.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -15,32 +27,49 @@ pub enum Error {
     Read(#[source] io::Error),
     #[error("failed to write timestamp")]
     Write(#[source] io::Error),
+    #[error("failed to remove timestamp")]
+    Remove(#[source] io::Error),
     #[error("failed to create cache directory for timestamp file")]
     Parent(#[source] io::Error),
 }
 
-/// Fetches the timestamp that is currently stored in the cache.
-pub fn last() -> Result<u64, Error> {
-    let path = &*timestamp_path()?;
+/// Refreshes the timestamp in the cache.
+pub fn refresh(name: &str) -> Result<(), Error> {
+    trace!("refreshing the {} timestamp file in cache", name);
+    refresh_at(&timestamp_path(name)?)
+}
 
+/// Determines if the time since the last recorded timestamp has exceeded `seconds`.
+pub fn exceeded(name: &str, seconds: u64) -> Result<bool, Error> {
+    exceeded_at(&timestamp_path(name)?, seconds)
+}
+
+/// Fetches the timestamp that is stored in the file at the given path.
+pub fn last_at(path: &Path) -> Result<u64, Error> {
     fs::read_to_string(path)
         .map_err(Error::Read)
         .map(|string| string.trim().parse::<u64>().unwrap_or(0))
 }
 
-/// Refreshes the timestamp in the cache.
-pub fn refresh() -> Result<(), Error> {
-    trace!("refreshing the timestamp file in cache");
-    let path = &*timestamp_path()?;
-
+/// Refreshes the timestamp in the file at the given path.
+pub fn refresh_at(path: &Path) -> Result<(), Error> {
     let parent = path.parent().expect("timestmap file does not have a parent directory");
     fs::create_dir_all(parent).map_err(Error::Parent)?;
     fs::write(path, current().to_string()).map_err(Error::Write)
 }
 
-/// Determines if the time since the last recorded timestamp has exceeded `seconds`.
-pub fn exceeded(seconds: u64) -> Result<bool, Error> {
-    last().map(|last| time_exceeded(last, current(), seconds))
+/// Removes the timestamp in the file at the given path, if it exists.
+pub fn remove_at(path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Err(why) if why.kind() != io::ErrorKind::NotFound => Err(Error::Remove(why)),
+        _ => Ok(()),
+    }
+}
+
+/// Determines if the time since the timestamp in the file at the given path has exceeded
+/// `seconds`.
+pub fn exceeded_at(path: &Path, seconds: u64) -> Result<bool, Error> {
+    last_at(path).map(|last| time_exceeded(last, current(), seconds))
 }
 
 /// Convenience function for fetching the current time in seconds since the UNIX Epoch.
@@ -57,8 +86,8 @@ fn time_exceeded(last: u64, current: u64, limit: u64) -> bool {
 }
 
 /// Convenience function for fetching the path to the timestamp file.
-fn timestamp_path() -> Result<PathBuf, Error> {
-    cache::cache("last_refresh").map_err(Error::Cache)
+fn timestamp_path(name: &str) -> Result<PathBuf, Error> {
+    cache::cache(name).map_err(Error::Cache)
 }
 
 #[cfg(test)]
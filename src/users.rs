@@ -1,18 +1,70 @@
+use crate::{
+    polkit::{self, Authorization},
+    Config,
+};
 use users::os::unix::GroupExt;
 
 /// Check if the user is an administrator on this system.
-pub fn user_is_admin() -> bool {
+///
+/// The user is an administrator if polkit authorizes them to update firmware. Users who must
+/// authenticate first are considered administrators if they are in an administrative group, as
+/// the firmware services will prompt them to authenticate when firmware is updated.
+pub fn user_is_admin(config: &Config) -> bool {
+    user_is_permitted(config, user_authorization(config))
+}
+
+/// Check if the given authorization permits the user to manage firmware without unlocking.
+pub fn user_is_permitted(config: &Config, authorization: Authorization) -> bool {
+    match authorization {
+        Authorization::Authorized => true,
+        Authorization::Challenge => current_user_in_admin_group(config),
+        Authorization::Denied => false,
+    }
+}
+
+/// Fetches the authorization of the user to update firmware from polkit.
+///
+/// If polkit is not available, authorization is determined by membership of an administrative
+/// group of the configuration instead.
+pub fn user_authorization(config: &Config) -> Authorization {
+    polkit::check_authorization().unwrap_or_else(|why| {
+        warn!("polkit authorization check failed: {}", why);
+        group_authorization(config)
+    })
+}
+
+/// Requests authorization to update firmware from polkit, prompting the user to authenticate.
+///
+/// This blocks until the user has responded, so it should not be called from a UI thread.
+pub fn user_request_authorization(config: &Config) -> Authorization {
+    polkit::request_authorization().unwrap_or_else(|why| {
+        warn!("polkit authorization request failed: {}", why);
+        group_authorization(config)
+    })
+}
+
+fn group_authorization(config: &Config) -> Authorization {
+    if current_user_in_admin_group(config) {
+        Authorization::Authorized
+    } else {
+        Authorization::Denied
+    }
+}
+
+fn current_user_in_admin_group(config: &Config) -> bool {
     users::get_current_username()
-        .map_or(false, |username| username == "root" || user_in_admin_group(&username))
+        .map_or(false, |username| username == "root" || user_in_admin_group(config, &username))
 }
 
-/// Check if a user is in an administrative group, such as `adm` or `sudo`.
-fn user_in_admin_group(user: &std::ffi::OsStr) -> bool {
-    let in_group = |name| {
+/// Check if a user is in an administrative group, such as `adm`, `sudo`, or `wheel`.
+///
+/// The groups are defined by the `admin-groups` key of the configuration.
+fn user_in_admin_group(config: &Config, user: &std::ffi::OsStr) -> bool {
+    let in_group = |name: &String| {
         users::get_group_by_name(name).map_or(false, |group| {
             group.members().into_iter().any(|member| member.as_os_str() == user)
         })
     };
 
-    include_str!("admin-groups").lines().filter(|g| !g.is_empty()).any(in_group)
+    config.admin_groups.iter().any(in_group)
 }
@@ -0,0 +1,101 @@
+//! Verification of installed firmware against its known checksums.
+
+use crate::fwupd_device_call;
+use fwupd_dbus::Device as FwupdDevice;
+use std::time::Duration;
+
+/// Reading the firmware back from a device may take a while.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Errors which fwupd returns when the installed firmware does not match its known checksums.
+const MISMATCH_ERRORS: &[&str] =
+    &["org.freedesktop.fwupd.Internal", "org.freedesktop.fwupd.NotFound"];
+
+/// The outcome of verifying the installed firmware of a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Verification {
+    /// The installed firmware matches its known checksum.
+    Verified,
+    /// The checksum of the installed firmware was recorded as trusted.
+    Trusted,
+    /// The device does not support verification, or no checksums are known for it.
+    Unsupported,
+    /// The installed firmware does not match its known checksum, for the given reason.
+    Failed(Box<str>),
+}
+
+/// Verifies the installed firmware of a device against the checksums which were recorded by
+/// `fwupd_verify_update`, or which are known to the LVFS metadata.
+pub fn fwupd_verify(device: &FwupdDevice) -> Result<Verification, dbus::Error> {
+    call(device, "Verify", Verification::Verified)
+}
+
+/// Records the checksum of the firmware which is installed on a device as trusted, so that it
+/// may later be verified.
+pub fn fwupd_verify_update(device: &FwupdDevice) -> Result<Verification, dbus::Error> {
+    call(device, "VerifyUpdate", Verification::Trusted)
+}
+
+fn call(
+    device: &FwupdDevice,
+    method: &str,
+    success: Verification,
+) -> Result<Verification, dbus::Error> {
+    fwupd_device_call(device, method, VERIFY_TIMEOUT).map(|_| success).or_else(verification_error)
+}
+
+/// Only a checksum mismatch is a failed verification. Any other error, such as a device which
+/// could not be read, is a failure to verify the device at all.
+fn verification_error(why: dbus::Error) -> Result<Verification, dbus::Error> {
+    match (why.name(), why.message()) {
+        (Some("org.freedesktop.fwupd.NotSupported"), _) => Ok(Verification::Unsupported),
+        (Some(name), Some(message))
+            if MISMATCH_ERRORS.contains(&name) && is_checksum_mismatch(message) =>
+        {
+            Ok(Verification::Failed(message.into()))
+        }
+        _ => Err(why),
+    }
+}
+
+/// fwupd reports a mismatch either as having no matching checksum, or with the checksum that it
+/// expected to find.
+fn is_checksum_mismatch(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("checksum") || message.contains("expected")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verification_errors() {
+        let error = dbus::Error::new_custom;
+
+        assert_eq!(
+            verification_error(error("org.freedesktop.fwupd.NotSupported", "no checksums")).ok(),
+            Some(Verification::Unsupported)
+        );
+
+        assert_eq!(
+            verification_error(error("org.freedesktop.fwupd.Internal", "checksum mismatch")).ok(),
+            Some(Verification::Failed("checksum mismatch".into()))
+        );
+
+        assert_eq!(
+            verification_error(error(
+                "org.freedesktop.fwupd.NotFound",
+                "For UEFI 1.0 expected abc, got def"
+            ))
+            .ok(),
+            Some(Verification::Failed("For UEFI 1.0 expected abc, got def".into()))
+        );
+
+        assert!(verification_error(error("org.freedesktop.fwupd.Internal", "read failed")).is_err());
+        assert!(verification_error(error("org.freedesktop.fwupd.AuthFailed", "")).is_err());
+        assert!(verification_error(error("org.freedesktop.DBus.Error.NoReply", "")).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+mod localize;
+mod state;
+mod ui;
+
+use self::state::{Event, State};
+use crossterm::{
+    event::{self as terminal_event, Event as TerminalEvent},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use firmware_manager::{
+    event_loop_with_config, format_error, system_on_battery, usb_hotplug_event_loop, Config,
+    FirmwareEvent,
+};
+use i18n_embed::DesktopLanguageRequester;
+use std::{
+    error::Error,
+    io,
+    process::{exit, Command},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
+
+fn main() {
+    translate();
+
+    let mut error = None;
+    let config = Config::load().unwrap_or_else(|why| {
+        error = Some([&*fl!("error-config"), ": ", &format_error(&why)].concat());
+        Config::default()
+    });
+
+    match run(config, error) {
+        Ok(true) => reboot(),
+        Ok(false) => (),
+        Err(why) => {
+            eprintln!("firmware-manager-tui: {}", why);
+            exit(1);
+        }
+    }
+}
+
+/// Runs the interface until it is closed, returning `true` if the system should be rebooted.
+///
+/// Like the CLI, the interface runs the event loop of the core itself, instead of attaching to
+/// the session service as the GTK frontend does. It is meant for managing firmware over SSH, or
+/// from a text console, where there may be no session bus for the service to be started on. An
+/// update which it starts is therefore not shown by the frontends that attach to the service, nor
+/// the reverse.
+fn run(config: Config, error: Option<String>) -> Result<bool, Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    let (tx_events, events) = mpsc::channel();
+
+    // The same signals which drive the GTK widget drive this interface.
+    let background = {
+        let tx_events = tx_events.clone();
+        thread::spawn(move || {
+            event_loop_with_config(&config, receiver, |signal| {
+                let _ = tx_events.send(Event::Firmware(signal));
+            });
+
+            let _ = tx_events.send(Event::Stop);
+        })
+    };
+
+    // Reading from the terminal blocks, so keys are read in their own thread.
+    thread::spawn(move || loop {
+        let event = match terminal_event::read() {
+            Ok(TerminalEvent::Key(key)) => Event::Input(key),
+            Ok(TerminalEvent::Resize(..)) => Event::Resize,
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+
+        if tx_events.send(event).is_err() {
+            break;
+        }
+    });
+
+    let flashing = Arc::new(AtomicBool::new(false));
+
+    let usb_trigger = {
+        let flashing = flashing.clone();
+        let sender = sender.clone();
+        usb_hotplug_event_loop(move || {
+            if !flashing.load(Ordering::SeqCst) {
+                let _ = sender.send(FirmwareEvent::Scan);
+            }
+        })
+    };
+
+    let mut state = State::new(sender.clone(), flashing, system_on_battery(), error);
+    let _ = sender.send(FirmwareEvent::Scan);
+
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    terminal.hide_cursor()?;
+
+    let result = main_loop(&mut terminal, &mut state, &events);
+
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = terminal.show_cursor();
+
+    if let Some(handle) = usb_trigger {
+        handle.abort();
+    }
+
+    let _ = sender.send(FirmwareEvent::Stop);
+    let _ = background.join();
+
+    result?;
+    Ok(state.reboot)
+}
+
+/// Draws the interface after each event, until it is closed or the system must be rebooted.
+fn main_loop<B: Backend>(
+    terminal: &mut Terminal<B>,
+    state: &mut State,
+    events: &Receiver<Event>,
+) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| ui::draw(frame, state))?;
+
+        match events.recv() {
+            Ok(Event::Firmware(signal)) => state.signal(signal),
+            Ok(Event::Input(key)) => state.key(key),
+            Ok(Event::Resize) => (),
+            Ok(Event::Stop) | Err(_) => return Ok(()),
+        }
+
+        if state.quit || state.reboot {
+            return Ok(());
+        }
+    }
+}
+
+/// Convenience function for rebooting the system.
+fn reboot() {
+    println!("{}", fl!("rebooting"));
+    if let Err(why) = Command::new("systemctl").arg("reboot").status() {
+        eprintln!("{}: {}", fl!("error-reboot"), why);
+    }
+}
+
+fn translate() {
+    let localizer = crate::localize::localizer();
+    let requested_languages = DesktopLanguageRequester::requested_languages();
+
+    if let Err(error) = localizer.select(&requested_languages) {
+        eprintln!("Error while loading languages for firmware-manager-tui {}", error);
+    }
+}
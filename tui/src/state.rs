@@ -0,0 +1,371 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use firmware_manager::{
+    format_error, Entities, Entity, FirmwareEvent, FirmwareSignal, FoundDevice, ScanReport,
+    UpdateStatus, Urgency,
+};
+use slotmap::SecondaryMap;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+};
+
+/// An event which is processed by the main loop of the interface.
+#[allow(clippy::large_enum_variant)]
+pub enum Event {
+    /// A signal was received from the event loop of the core.
+    Firmware(FirmwareSignal),
+    /// A key was pressed.
+    Input(KeyEvent),
+    /// The terminal was resized, and must be drawn again.
+    Resize,
+    /// The event loop of the core has stopped.
+    Stop,
+}
+
+/// The progress of a request which was made for a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Idle,
+    Waiting,
+    Downloading(u64, u64),
+    Flashing,
+    Scheduling,
+    Updated,
+}
+
+/// A device which was found by a scan.
+pub struct Device {
+    pub found: FoundDevice,
+    pub action: Action,
+    /// The changelog of the device is shown beneath it.
+    pub expanded: bool,
+}
+
+impl Deref for Device {
+    type Target = FoundDevice;
+
+    fn deref(&self) -> &FoundDevice { &self.found }
+}
+
+impl DerefMut for Device {
+    fn deref_mut(&mut self) -> &mut FoundDevice { &mut self.found }
+}
+
+/// Manages the devices that were found, and the requests which the user makes for them.
+pub struct State {
+    pub entities: Entities,
+    pub devices: SecondaryMap<Entity, Device>,
+    /// System firmware, which is listed first, ordered by the urgency of their updates.
+    pub systems: Vec<Entity>,
+    /// Device firmware, ordered by the urgency of their updates.
+    pub others: Vec<Entity>,
+    /// The device whose update is waiting to be confirmed before the system reboots.
+    pub dialog: Option<Entity>,
+    /// The last error which was reported by the core.
+    pub error: Option<String>,
+    /// The system is running on battery power.
+    pub has_battery: bool,
+    pub quit: bool,
+    /// The system should be rebooted once the interface has closed.
+    pub reboot: bool,
+    /// The outcome of the last scan, which is unset while scanning.
+    pub report: Option<ScanReport>,
+    /// The position of the selected device in the list.
+    pub selected: usize,
+    /// A device is being flashed, so the interface may not be closed, nor devices scanned.
+    flashing: Arc<AtomicBool>,
+    sender: Sender<FirmwareEvent>,
+}
+
+impl State {
+    pub fn new(
+        sender: Sender<FirmwareEvent>,
+        flashing: Arc<AtomicBool>,
+        has_battery: bool,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            entities: Entities::default(),
+            devices: SecondaryMap::new(),
+            systems: Vec::new(),
+            others: Vec::new(),
+            dialog: None,
+            error,
+            has_battery,
+            quit: false,
+            reboot: false,
+            report: None,
+            selected: 0,
+            flashing,
+            sender,
+        }
+    }
+
+    /// Every device, in the order that they are listed.
+    pub fn order(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.systems.iter().chain(self.others.iter()).cloned()
+    }
+
+    /// The device which is selected in the list.
+    pub fn selected(&self) -> Option<Entity> { self.order().nth(self.selected) }
+
+    /// Handles a signal from the event loop of the core.
+    pub fn signal(&mut self, signal: FirmwareSignal) {
+        match signal {
+            FirmwareSignal::DeviceFlashing(entity) => {
+                self.flashing.store(true, Ordering::SeqCst);
+                let system = self.entities.is_system(entity);
+                self.set_action(entity, if system { Action::Scheduling } else { Action::Flashing });
+            }
+            FirmwareSignal::DeviceUpdated(entity) => {
+                self.flashing.store(false, Ordering::SeqCst);
+                if let Some(device) = self.devices.get_mut(entity) {
+                    device.updated();
+                    device.action = Action::Updated;
+                }
+
+                if self.entities.is_system(entity) {
+                    self.reboot = true;
+                }
+            }
+            FirmwareSignal::DownloadBegin(entity, size) => {
+                self.set_action(entity, Action::Downloading(0, size))
+            }
+            FirmwareSignal::DownloadComplete(entity) => {
+                if let Some(device) = self.devices.get_mut(entity) {
+                    if let Action::Downloading(_, size) = device.action {
+                        device.action = Action::Downloading(size, size);
+                    }
+                }
+            }
+            FirmwareSignal::DownloadUpdate(entity, bytes) => {
+                if let Some(device) = self.devices.get_mut(entity) {
+                    if let Action::Downloading(downloaded, size) = device.action {
+                        let downloaded = downloaded.saturating_add(bytes as u64).min(size);
+                        device.action = Action::Downloading(downloaded, size);
+                    }
+                }
+            }
+            // Devices of a firmware service that left the bus can no longer be managed.
+            FirmwareSignal::BackendLost(_) => self.scan(),
+            FirmwareSignal::Error(entity, why) => {
+                self.flashing.store(false, Ordering::SeqCst);
+                self.error = Some(format_error(&why));
+                if let Some(entity) = entity {
+                    self.set_action(entity, Action::Idle);
+                }
+            }
+            FirmwareSignal::Scanning => {
+                self.entities.clear();
+                self.devices.clear();
+                self.systems.clear();
+                self.others.clear();
+                self.dialog = None;
+                self.report = None;
+                self.selected = 0;
+            }
+            FirmwareSignal::ScanningComplete(report) => self.report = Some(report),
+            // When system firmware is successfully scheduled, reboot the system.
+            FirmwareSignal::SystemScheduled => self.reboot = true,
+            FirmwareSignal::BackendAvailable(_)
+            | FirmwareSignal::Security(_)
+            | FirmwareSignal::Verification(..) => (),
+            signal => {
+                if let Some(found) = FoundDevice::from_signal(signal) {
+                    self.add(Device { found, action: Action::Idle, expanded: false });
+                }
+            }
+        }
+    }
+
+    /// Handles a key which was pressed.
+    pub fn key(&mut self, key: KeyEvent) {
+        let interrupt = key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL;
+
+        if self.dialog.is_some() {
+            match key.code {
+                KeyCode::Enter | KeyCode::Char('y') => self.confirm(),
+                KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => self.dialog = None,
+                _ if interrupt => self.dialog = None,
+                _ => (),
+            }
+
+            return;
+        }
+
+        match key.code {
+            _ if interrupt => self.close(),
+            KeyCode::Char('q') | KeyCode::Esc => self.close(),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some(device) =
+                    self.selected().and_then(|entity| self.devices.get_mut(entity))
+                {
+                    device.expanded = !device.expanded;
+                }
+            }
+            KeyCode::Char('u') => {
+                if let Some(entity) = self.selected() {
+                    self.update(entity);
+                }
+            }
+            KeyCode::Char('r') | KeyCode::F(5) => {
+                self.error = None;
+                self.scan();
+            }
+            _ => (),
+        }
+    }
+
+    /// Records a device which was found, after every device with an update that is at least as
+    /// urgent.
+    fn add(&mut self, device: Device) {
+        let entity = self.entities.create();
+        if device.system {
+            self.entities.associate_system(entity);
+        }
+
+        let urgency = device.info.urgency();
+        let devices = &self.devices;
+        let list = if device.system { &mut self.systems } else { &mut self.others };
+
+        let position = list
+            .iter()
+            .position(|&other| {
+                devices.get(other).map_or(Urgency::Unknown, |other| other.info.urgency()) < urgency
+            })
+            .unwrap_or(list.len());
+
+        list.insert(position, entity);
+        self.devices.insert(entity, device);
+    }
+
+    /// Closes the interface, unless a device is being flashed.
+    fn close(&mut self) {
+        if !self.flashing.load(Ordering::SeqCst) {
+            self.quit = true;
+        }
+    }
+
+    /// Installs the update of the device whose reboot was confirmed.
+    fn confirm(&mut self) {
+        if let Some(entity) = self.dialog.take() {
+            if let Some(event) = self.devices.get(entity).and_then(|device| device.event(entity)) {
+                self.set_action(entity, Action::Waiting);
+                let _ = self.sender.send(event);
+            }
+        }
+    }
+
+    fn scan(&self) {
+        if !self.flashing.load(Ordering::SeqCst) {
+            let _ = self.sender.send(FirmwareEvent::Scan);
+        }
+    }
+
+    /// Moves the selection by the given number of rows, stopping at the first and last rows.
+    fn select(&mut self, rows: isize) {
+        let len = self.systems.len() + self.others.len();
+        if len == 0 {
+            return;
+        }
+
+        let selected = self.selected as isize + rows;
+        self.selected = selected.max(0).min(len as isize - 1) as usize;
+    }
+
+    fn set_action(&mut self, entity: Entity, action: Action) {
+        if let Some(device) = self.devices.get_mut(entity) {
+            device.action = action;
+        }
+    }
+
+    /// Updates the device, or performs the action which it is waiting on.
+    ///
+    /// Devices which are waiting to be activated or unlocked have that action performed instead,
+    /// system firmware which is already scheduled has its update cancelled, and firmware which is
+    /// flashed on reboot must first be confirmed.
+    fn update(&mut self, entity: Entity) {
+        let device = match self.devices.get(entity) {
+            Some(device) if device.action == Action::Idle => device,
+            _ => return,
+        };
+
+        if device.status == UpdateStatus::Available && device.system {
+            self.dialog = Some(entity);
+            return;
+        }
+
+        let event = match device.event(entity) {
+            Some(event) => event,
+            None => return,
+        };
+
+        self.set_action(entity, Action::Waiting);
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+    use firmware_manager::{FirmwareInfo, System76Digest};
+    use std::sync::mpsc::{self, Receiver};
+
+    fn state() -> (State, Receiver<FirmwareEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (State::new(sender, Arc::new(AtomicBool::new(false)), false, None), receiver)
+    }
+
+    fn press(state: &mut State, key: char) { state.key(KeyEvent::from(KeyCode::Char(key))); }
+
+    fn system_firmware() -> Device {
+        let info = FirmwareInfo {
+            id: "system76-system".into(),
+            name: "System Firmware".into(),
+            current: "1.0".into(),
+            latest: Some("1.1".into()),
+            install_duration: 0,
+            held: false,
+            changelog: Default::default(),
+        };
+
+        let signal = FirmwareSignal::S76System(info, Some(System76Digest("4fe6b3f".into())), false);
+        let found = FoundDevice::from_signal(signal).expect("system firmware is a device");
+        Device { found, action: Action::Idle, expanded: false }
+    }
+
+    #[test]
+    fn system_updates_are_confirmed_before_rebooting() {
+        let (mut state, events) = state();
+        state.add(system_firmware());
+        let entity = state.selected().unwrap();
+
+        // Declining the dialog sends nothing.
+        press(&mut state, 'u');
+        assert_eq!(state.dialog, Some(entity));
+        press(&mut state, 'n');
+        assert_eq!(state.dialog, None);
+        assert!(events.try_recv().is_err());
+
+        press(&mut state, 'u');
+        press(&mut state, 'y');
+        assert_eq!(state.dialog, None);
+        assert_eq!(state.devices[entity].action, Action::Waiting);
+        assert!(matches!(events.try_recv(), Ok(FirmwareEvent::S76System(e, _)) if e == entity));
+
+        // A device which is waiting may not be updated again.
+        press(&mut state, 'u');
+        assert_eq!(state.dialog, None);
+
+        assert!(!state.reboot);
+        state.signal(FirmwareSignal::SystemScheduled);
+        assert!(state.reboot);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{
+    fl,
+    state::{Action, Device, State},
+};
+use firmware_manager::{
+    Changelog, PendingAction, ReleaseTrust, TrustDetail, TrustWarning, UpdateStatus,
+};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+/// The number of characters between the brackets of a download's progress bar.
+const PROGRESS_WIDTH: usize = 20;
+
+/// Draws the device list, the reboot confirmation dialog, and the key bindings.
+pub fn draw<B: Backend>(frame: &mut Frame<B>, state: &State) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+        .split(frame.size());
+
+    let title = Span::styled(fl!("title"), Style::default().add_modifier(Modifier::BOLD));
+    frame.render_widget(Paragraph::new(title), chunks[0]);
+
+    if state.systems.is_empty() && state.others.is_empty() {
+        let message = match state.report {
+            None => fl!("view-scanning"),
+            Some(ref report) if report.any_scanned() => fl!("view-empty"),
+            Some(_) => fl!("view-unavailable"),
+        };
+
+        let view = Paragraph::new(message).wrap(Wrap { trim: true });
+        frame.render_widget(view, chunks[1]);
+    } else {
+        draw_devices(frame, state, chunks[1]);
+    }
+
+    let footer = match state.error {
+        Some(ref error) => Span::styled(error.as_str(), Style::default().fg(Color::Red)),
+        None if state.dialog.is_some() => Span::raw(fl!("keys-dialog")),
+        None => Span::raw(fl!("keys")),
+    };
+
+    frame.render_widget(Paragraph::new(footer), chunks[2]);
+
+    if let Some(device) = state.dialog.and_then(|entity| state.devices.get(entity)) {
+        draw_dialog(frame, state, device);
+    }
+}
+
+/// Lists system firmware and device firmware in their own sections, like the GTK devices view.
+fn draw_devices<B: Backend>(frame: &mut Frame<B>, state: &State, area: Rect) {
+    let width = area.width as usize;
+    let header = Style::default().add_modifier(Modifier::BOLD);
+
+    let name_width = state
+        .order()
+        .filter_map(|entity| state.devices.get(entity))
+        .map(|device| device.info.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut items = Vec::new();
+    let mut selected = None;
+    let mut position = 0;
+
+    let sections = [
+        (&state.systems, fl!("header-system-firmware")),
+        (&state.others, fl!("header-device-firmware")),
+    ];
+
+    for (entities, title) in sections.iter() {
+        if entities.is_empty() {
+            continue;
+        }
+
+        if !items.is_empty() {
+            items.push(ListItem::new(""));
+        }
+
+        items.push(ListItem::new(Span::styled(title.as_str(), header)));
+
+        for &entity in entities.iter() {
+            let device = match state.devices.get(entity) {
+                Some(device) => device,
+                None => continue,
+            };
+
+            if position == state.selected {
+                selected = Some(items.len());
+            }
+
+            position += 1;
+            items.push(ListItem::new(device_text(device, name_width, width)));
+        }
+    }
+
+    let list = List::new(items)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+
+    let mut list_state = ListState::default();
+    list_state.select(selected);
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// The row of a device, followed by its changelog if it was expanded.
+fn device_text(device: &Device, name_width: usize, width: usize) -> Text<'static> {
+    let info = &device.info;
+    let name = format!("{:<width$}", info.name, width = name_width);
+
+    let mut row = vec![Span::raw(name), Span::raw("  "), Span::raw(info.current.to_string())];
+
+    let (status, color) = status(device);
+    row.push(Span::raw("  "));
+    row.push(Span::styled(status, Style::default().fg(color)));
+
+    if device.action == Action::Idle && device.upgradeable() && info.security_update() {
+        let badge = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+        row.push(Span::raw("  "));
+        row.push(Span::styled(fl!("badge-security-update"), badge));
+    }
+
+    let mut lines = vec![Spans::from(row)];
+
+    if device.expanded {
+        lines.extend(changelog(&info.changelog, width.saturating_sub(6)));
+    }
+
+    Text::from(lines)
+}
+
+/// Describes the state of a device's firmware, or the progress of its update.
+fn status(device: &Device) -> (String, Color) {
+    let version = device.info.latest.as_deref().unwrap_or_default();
+
+    match device.action {
+        Action::Idle => (),
+        Action::Waiting => return (fl!("action-waiting"), Color::Yellow),
+        Action::Downloading(downloaded, size) => {
+            let fraction = if size == 0 { 1.0 } else { downloaded as f64 / size as f64 };
+            let filled = (fraction * PROGRESS_WIDTH as f64) as usize;
+            let progress = format!(
+                "{} [{}{}] {:>3}%",
+                fl!("action-downloading"),
+                "#".repeat(filled),
+                " ".repeat(PROGRESS_WIDTH - filled),
+                (fraction * 100.0) as u8
+            );
+
+            return (progress, Color::Yellow);
+        }
+        Action::Flashing => return (fl!("action-flashing"), Color::Yellow),
+        Action::Scheduling => return (fl!("action-scheduling"), Color::Yellow),
+        Action::Updated => return (fl!("action-updated"), Color::Green),
+    }
+
+    match device.status {
+        UpdateStatus::UpToDate => (fl!("status-up-to-date"), Color::Reset),
+        UpdateStatus::Available => (fl!("status-available", version = version), Color::Cyan),
+        UpdateStatus::Held => (fl!("status-held"), Color::Reset),
+        UpdateStatus::Scheduled => (fl!("status-scheduled"), Color::Cyan),
+        UpdateStatus::Pending(PendingAction::Activate) => (fl!("status-activation"), Color::Cyan),
+        UpdateStatus::Pending(PendingAction::Unlock) => (fl!("status-locked"), Color::Cyan),
+    }
+}
+
+/// The releases of a changelog, from the newest to the oldest, wrapped to the given width.
+fn changelog(changelog: &Changelog, width: usize) -> Vec<Spans<'static>> {
+    let indent = "    ";
+    let version = Style::default().add_modifier(Modifier::BOLD);
+
+    if changelog.is_empty() {
+        return vec![Spans::from([indent, &*fl!("changelog-unavailable")].concat())];
+    }
+
+    let mut lines = Vec::new();
+    for entry in &changelog.entries {
+        let title = Span::styled(entry.version.to_string(), version);
+        lines.push(Spans::from(vec![Span::raw(indent), title]));
+
+        let description = match entry.description {
+            Some(ref description) => html2runes::markdown::convert_string(description),
+            None => fl!("changelog-unavailable"),
+        };
+
+        for line in wrap(&description, width) {
+            lines.push(Spans::from([indent, indent, line.as_str()].concat()));
+        }
+    }
+
+    lines
+}
+
+/// Asks for confirmation before installing firmware which is flashed on reboot, like the
+/// firmware update dialog of the GTK application.
+fn draw_dialog<B: Backend>(frame: &mut Frame<B>, state: &State, device: &Device) {
+    let area = centered(frame.size(), 80, 80);
+    let version = device.info.latest.as_deref().unwrap_or_default();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let mut lines = vec![Spans::from(fl!("update-available", version = version)), Spans::default()];
+
+    if state.has_battery {
+        let warning = Style::default().fg(Color::Yellow);
+        lines.push(Spans::from(Span::styled(fl!("update-connect-to-ac"), warning)));
+        lines.push(Spans::default());
+    }
+
+    lines.push(Spans::from(fl!(
+        "update-guide",
+        url = "https://support.system76.com/articles/system-firmware/"
+    )));
+
+    let changelog = &device.info.changelog;
+    if let Some(trust) = changelog.latest().and_then(|entry| entry.trust.as_ref()) {
+        lines.push(Spans::default());
+        lines.push(Spans::from(Span::styled(fl!("trust-header"), bold)));
+        lines.extend(trust_details(trust).into_iter().map(Spans::from));
+    }
+
+    lines.push(Spans::default());
+    lines.push(Spans::from(Span::styled(fl!("changelog"), bold)));
+    lines.extend(self::changelog(changelog, area.width.saturating_sub(10) as usize));
+
+    let title = Span::styled(fl!("header-firmware-update"), bold);
+    let dialog = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(dialog, area);
+}
+
+/// Describes how far a release may be trusted, with a warning for releases which are unsigned or
+/// still being tested.
+fn trust_details(trust: &ReleaseTrust) -> Vec<String> {
+    let mut details: Vec<String> = trust
+        .details()
+        .into_iter()
+        .map(|detail| match detail {
+            TrustDetail::PayloadSigned => fl!("trust-payload-signed"),
+            TrustDetail::PayloadUnsigned => fl!("trust-payload-unsigned"),
+            TrustDetail::MetadataSigned => fl!("trust-metadata-signed"),
+            TrustDetail::MetadataUnsigned => fl!("trust-metadata-unsigned"),
+            TrustDetail::LvfsRemote => fl!("trust-remote-lvfs"),
+            TrustDetail::Remote(remote) => fl!("trust-remote", remote = remote),
+        })
+        .collect();
+
+    details.extend(trust.warning().map(|warning| match warning {
+        TrustWarning::Unsigned => fl!("trust-warning-unsigned"),
+        TrustWarning::Testing => fl!("trust-warning-testing"),
+    }));
+
+    details
+}
+
+/// A rectangle in the center of the area, sized as a percentage of it.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = area.width * width / 100;
+    let height = area.height * height / 100;
+
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Wraps text at word boundaries so that no line is wider than the given width, where possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.trim_end().lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+
+        for word in paragraph.split_whitespace() {
+            let word_width = word.chars().count();
+            if line_width != 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+
+            if line_width != 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+
+            line.push_str(word);
+            line_width += word_width;
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_wrapped_at_words() {
+        assert_eq!(
+            wrap("Fixes suspend on AMD systems\n\n* Improves fan curves\n", 12),
+            vec!["Fixes", "suspend on", "AMD systems", "", "* Improves", "fan curves"]
+        );
+    }
+}